@@ -1,15 +1,9 @@
-use std::{
-	error::Error,
-	fs::File,
-	io::{BufRead, BufReader, Read},
-};
+use std::error::Error;
 
-use waytinier::{BufferAccessor, TopLevelWindowWizard, abstraction::app::App};
+use waytinier::{Filter, Image, TopLevelWindowWizard, abstraction::app::App};
 
 struct AppState {
-	img_w: usize,
-	img_h: usize,
-	machine: Vec<u8>,
+	machine: Image,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -21,47 +15,30 @@ fn main() -> Result<(), Box<dyn Error>> {
 		.spawn()?;
 	app.push_presenter(window);
 
-	let (img_w, img_h, machine) = parse_pix("pix.ppm")?;
 	let mut state = AppState {
-		img_w,
-		img_h,
-		machine,
+		machine: Image::load("pix.ppm")?,
 	};
 
 	loop {
-		if app.work(&mut state, |state, ss| {
-			let buf = match ss.buf {
-				BufferAccessor::ShmSlice(ptr) => unsafe { &mut **ptr },
-				BufferAccessor::DmaBufFd(_owned_fd) => {
-					return;
-				}
-			};
+		if app.work(&mut state, |state, mut ss| {
 			let (r, g, b) = hsv_to_rgb((ss.frame % 360) as f64, 1.0, 1.0);
-			let start_x = ss.w as isize / 2 - state.img_w as isize / 2;
-			let start_y = ss.h as isize / 2 - state.img_h as isize / 2;
-
-			for y in 0..ss.h as usize {
-				for x in 0..ss.w as usize {
-					let surface_ix = (ss.w as usize * y + x) * 4;
-
-					let rel_x = x as isize - start_x;
-					let rel_y = y as isize - start_y;
-
-					if rel_x >= 0
-						&& rel_x < state.img_w as isize
-						&& rel_y >= 0 && rel_y < state.img_h as isize
-					{
-						let img_ix = (rel_y as usize * img_w + rel_x as usize) * 3;
-						buf[surface_ix + 2] = state.machine[img_ix];
-						buf[surface_ix + 1] = state.machine[img_ix + 1];
-						buf[surface_ix] = state.machine[img_ix + 2];
-					} else {
-						buf[surface_ix] = b.wrapping_sub(x as u8);
-						buf[surface_ix + 1] = g.wrapping_add(y as u8);
-						buf[surface_ix + 2] = r.wrapping_shl(x as u32);
-					}
+			let (img_w, img_h) = (state.machine.w as i32, state.machine.h as i32);
+			let start_x = ss.w as i32 / 2 - img_w / 2;
+			let start_y = ss.h as i32 / 2 - img_h / 2;
+			let Some(mut pixels) = ss.pixels() else {
+				return;
+			};
+			for (y, row) in pixels.rows().enumerate() {
+				for (x, px) in row.chunks_exact_mut(4).enumerate() {
+					px[0] = b.wrapping_sub(x as u8);
+					px[1] = g.wrapping_add(y as u8);
+					px[2] = r.wrapping_shl(x as u32);
 				}
 			}
+			let Some(mut painter) = ss.painter() else {
+				return;
+			};
+			painter.blit(&state.machine, (start_x, start_y, img_w, img_h), Filter::Nearest);
 		})? {
 			break;
 		}
@@ -69,31 +46,6 @@ fn main() -> Result<(), Box<dyn Error>> {
 	Ok(())
 }
 
-fn parse_pix(path: &str) -> Result<(usize, usize, Vec<u8>), Box<dyn Error>> {
-	let file = File::open(path)?;
-	let mut rd = BufReader::new(file);
-
-	let mut buf = String::new();
-	rd.read_line(&mut buf)?;
-	if buf.trim_end() != "P6" {
-		// println!("{:?}", unsafe { buf.as_mut_vec() });
-		return Err("file format not ppm".into());
-	}
-
-	buf.clear();
-	rd.read_line(&mut buf)?;
-	let mut nonws = buf.split_whitespace();
-	let w = nonws.next().ok_or("parsing w failed")?.parse()?;
-	let h = nonws.next().ok_or("parsing h failed")?.parse()?;
-
-	rd.skip_until(b'\n')?;
-
-	let mut raster: Vec<u8> = vec![];
-	rd.read_to_end(&mut raster)?;
-
-	Ok((w, h, raster))
-}
-
 // stolen from hsv library
 #[allow(dead_code)]
 pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (u8, u8, u8) {
@@ -1,29 +1,20 @@
-use std::error::Error;
-
-use waytinier::abstraction::app::{App, TopLevelWindow};
+use waytinier::{App, TopLevelWindowWizard, wayland::WaytinierError};
 
 struct AppState {}
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> Result<(), WaytinierError> {
 	let mut app = App::new()?;
-	let window = TopLevelWindow::spawner(&mut app).spawn()?;
-	let _ = app.push_presenter(window)?;
+	let window = TopLevelWindowWizard::new(&mut app).spawn()?;
+	app.push_presenter(window);
 
 	let mut state = AppState {};
-	loop {
-		if app.work(&mut state, |_state, ss| {
-			for y in 0..ss.h as usize {
-				for x in 0..ss.w as usize {
-					let ix = (ss.w as usize * y + x) * ss.pf.width() as usize;
-					ss.buf[ix] = 0xff;
-					ss.buf[ix + 1] = 0xe4;
-					ss.buf[ix + 2] = 0xff;
-					ss.buf[ix + 3] = 0xff;
-				}
+
+	while !app.finished {
+		app.work(&mut state, |_state, mut ss| {
+			if let Some(mut pixels) = ss.pixels() {
+				pixels.fill(0xffffe4ffu32);
 			}
-		})? {
-			break;
-		};
+		})?;
 	}
 	Ok(())
 }
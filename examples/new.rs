@@ -1,4 +1,4 @@
-use waytinier::{App, BufferAccessor, DmaBackend, TopLevelWindowWizard, wayland::WaytinierError};
+use waytinier::{App, DmaBackend, TopLevelWindowWizard, wayland::WaytinierError};
 
 struct AppState {}
 
@@ -11,18 +11,10 @@ fn main() -> Result<(), WaytinierError> {
 	let mut state = AppState {};
 
 	while !app.finished {
-		app.work(&mut state, |_state, ss| match ss.buf {
-			BufferAccessor::ShmSlice(slice) => {
-				let slice = unsafe { &mut **slice };
-				slice.chunks_mut(4).for_each(|chunk| {
-					chunk[0] = 255;
-					chunk[1] = 255;
-					chunk[2] = 255;
-					chunk[3] = 255;
-				});
-			}
-			BufferAccessor::DmaBufFd(_fd) => {
-				// panic!()
+		app.work(&mut state, |_state, mut ss| {
+			// dmabufs aren't mapped, there's nothing to draw into
+			if let Some(mut pixels) = ss.pixels() {
+				pixels.fill(0xffffffffu32);
 			}
 		})?;
 	}
@@ -1,24 +1,52 @@
 #![allow(dead_code)]
 #![allow(unused)]
 
-use std::{collections::HashMap, error::Error, marker::PhantomData};
+use std::{
+	collections::{HashMap, VecDeque},
+	error::Error,
+	marker::PhantomData,
+	rc::Rc,
+};
 
 use crate::{
 	Rl,
 	abstraction::{
-		presenter::{Presenter, PresenterMap, PresenterObject, TopLevelWindow},
+		csd::{BORDER, FrameHit},
+		cursor::ThemedCursor,
+		damage::{self, Rect},
+		draw::Painter,
+		pixels::PixelBuffer,
+		presenter::{
+			BufferTransform, Canvas, LayerSurface, Popup, Presenter, PresenterMap, PresenterObject,
+			RenderMode, SubSurfaceLayer, TopLevelWindow,
+		},
 		wizard::TopLevelWindowWizard,
 	},
 	dbug, init_logger, rl, wait_for_sync,
 	wayland::{
-		God, IdentManager, PixelFormat, WaytinierError,
+		God, Id, IdentManager, PixelFormat, Raw, WaylandObjectKind, WaytinierError,
 		buffer::{BufferAccessor, BufferBackend},
 		compositor::Compositor,
+		cursor_shape::{CursorShape, CursorShapeDevice, CursorShapeManager},
+		decoration::{DecorationManager, DecorationMode},
 		display::Display,
+		fractional_scale::FractionalScaleManager,
+		layer_shell::{KeyboardInteractivity, Layer, LayerAnchor, WlrLayerShell, WlrLayerSurface},
+		output::{Output, OutputInfo, Transform},
+		pointer::{BTN_LEFT, BTN_RIGHT, Pointer, PointerEvent},
+		region::Region,
 		registry::Registry,
+		seat::Seat,
 		shm::ShmBackend,
+		subcompositor::SubCompositor,
 		surface::Surface,
-		wire::MessageManager,
+		viewporter::Viewporter,
+		wire::{Action, MessageManager},
+		xdg_shell::{
+			positioner::{Positioner, XdgPositioner},
+			toplevel::{ResizeEdge, WindowState, XdgTopLevel},
+			wm_base::XdgWmBase,
+		},
 	},
 };
 
@@ -27,10 +55,33 @@ pub struct App {
 	pub(crate) compositor: Rl<Compositor>,
 	pub(crate) registry: Rl<Registry>,
 	pub(crate) display: Rl<Display>,
+	pub(crate) outputs: Vec<Rl<Output>>,
+	pub(crate) viewporter: Option<Rl<Viewporter>>,
+	pub(crate) fractional_scale_manager: Option<Rl<FractionalScaleManager>>,
+	pub(crate) subcompositor: Option<Rl<SubCompositor>>,
+	pub(crate) xdg_wm_base: Option<Rl<XdgWmBase>>,
+	pub(crate) seat: Option<Rl<Seat>>,
+	pub(crate) pointer: Option<Rl<Pointer>>,
+	pub(crate) cursor_shape_device: Option<Rl<CursorShapeDevice>>,
+	// enter serial and shape the cursor was last set for
+	pub(crate) cursor: Option<(u32, CursorShape)>,
+	// xcursor fallback when there's no cursor shape device
+	pub(crate) themed_cursor: Option<ThemedCursor>,
+	pub(crate) decoration_manager: Option<Rl<DecorationManager>>,
+	pub(crate) layer_shell: Option<Rl<WlrLayerShell>>,
+	pub(crate) events: VecDeque<AppEvent>,
 	pub finished: bool,
 	pub(crate) god: God,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppEvent {
+	// registry name of the output, see OutputInfo::global
+	OutputAdded(u32),
+	OutputChanged(u32),
+	OutputRemoved(u32),
+}
+
 impl App {
 	pub fn new() -> Result<Self, WaytinierError> {
 		init_logger();
@@ -40,106 +91,1212 @@ impl App {
 		let registry = Registry::new_registered_made(&mut god, &display);
 		wait_for_sync!(display, &mut god);
 		let compositor = Compositor::new_registered_bound(&mut god, &registry)?;
-		Ok(Self {
+		// fractional scaling needs both, otherwise we stick to integer buffer scales
+		let (viewporter, fractional_scale_manager) = {
+			let reg = registry.borrow();
+			if reg.has(WaylandObjectKind::Viewporter)
+				&& reg.has(WaylandObjectKind::FractionalScaleManager)
+			{
+				drop(reg);
+				(
+					Some(Viewporter::new_registered_bound(&mut god, &registry)?),
+					Some(FractionalScaleManager::new_registered_bound(&mut god, &registry)?),
+				)
+			} else {
+				(None, None)
+			}
+		};
+		let mut app = Self {
 			presenters: PresenterMap::default(),
 			compositor,
 			registry,
 			display,
+			outputs: vec![],
+			viewporter,
+			fractional_scale_manager,
+			subcompositor: None,
+			xdg_wm_base: None,
+			seat: None,
+			pointer: None,
+			cursor_shape_device: None,
+			cursor: None,
+			themed_cursor: None,
+			decoration_manager: None,
+			layer_shell: None,
+			events: VecDeque::new(),
 			finished: false,
 			god,
-		})
+		};
+		app.sync_outputs()?;
+		// let the outputs send their initial state
+		wait_for_sync!(app.display, &mut app.god);
+		app.sync_outputs()?;
+		Ok(app)
+	}
+
+	pub fn push_presenter(&mut self, presenter: Box<dyn PresenterObject>) -> usize {
+		self.presenters.push(presenter)
 	}
 
-	pub fn push_presenter(&mut self, presenter: Box<dyn PresenterObject>) {
-		self.presenters.push(presenter);
+	pub fn poll_event(&mut self) -> Option<AppEvent> {
+		self.events.pop_front()
+	}
+
+	pub fn outputs(&self) -> Vec<OutputInfo> {
+		self.outputs.iter().map(|o| o.borrow().info.clone()).collect()
+	}
+
+	// the outputs the window's surface is currently shown on
+	pub fn window_outputs(&mut self, presenter_id: usize) -> Option<Vec<OutputInfo>> {
+		let surface = self.canvas_mut(presenter_id)?.surface.clone();
+		let surface = surface.borrow();
+		Some(
+			self.outputs
+				.iter()
+				.filter(|o| surface.outputs.contains(&o.borrow().id))
+				.map(|o| o.borrow().info.clone())
+				.collect(),
+		)
+	}
+
+	// binds outputs that appeared in the registry, drops the ones that went away
+	// and turns whatever changed into events
+	pub(crate) fn sync_outputs(&mut self) -> Result<(), WaytinierError> {
+		let globals = self.registry.borrow().find_globals(WaylandObjectKind::Output);
+		let mut i = 0;
+		while i < self.outputs.len() {
+			let global = self.outputs[i].borrow().global;
+			if globals.contains(&global) {
+				i += 1;
+				continue;
+			}
+			let output = self.outputs.remove(i);
+			let output = output.borrow();
+			output.release(&mut self.god);
+			if output.announced {
+				self.events.push_back(AppEvent::OutputRemoved(global.raw()));
+			}
+			// no leave event comes for an output that's gone, so the surfaces forget it here
+			for presenter in self.presenters.inner.values_mut() {
+				let canvas = presenter.canvas();
+				canvas.surface.borrow_mut().outputs.retain(|id| *id != output.id);
+				Self::refit_surface(
+					&mut self.god,
+					&self.outputs,
+					&canvas.surface,
+					canvas.buffer_transform,
+				);
+			}
+		}
+		for global in globals {
+			if self.outputs.iter().any(|o| o.borrow().global == global) {
+				continue;
+			}
+			let output = Output::new_registered_bound(&mut self.god, &self.registry, global)?;
+			self.outputs.push(output);
+		}
+		for output in &self.outputs {
+			let mut output = output.borrow_mut();
+			if !output.changed {
+				continue;
+			}
+			output.changed = false;
+			let global = output.info.global;
+			if output.announced {
+				self.events.push_back(AppEvent::OutputChanged(global));
+			} else {
+				output.announced = true;
+				self.events.push_back(AppEvent::OutputAdded(global));
+			}
+		}
+		Ok(())
 	}
 
 	pub(crate) fn make_surface(&mut self, w: u32, h: u32, pf: PixelFormat) -> Rl<Surface> {
 		Surface::new_registered_made(&mut self.god, &self.compositor, w, h, pf)
 	}
 
-	pub fn work<F, S>(&mut self, state: &mut S, mut render_fun: F) -> Result<bool, WaytinierError>
+	// the compositor's preference wins, otherwise the densest output the surface is on
+	fn pick_scale(outputs: &[Rl<Output>], surface: &Surface) -> u32 {
+		if let Some(scale) = surface.preferred_scale {
+			return scale;
+		}
+		outputs
+			.iter()
+			.map(|o| o.borrow())
+			.filter(|o| surface.outputs.contains(&o.id))
+			.map(|o| o.info.scale.max(1) as u32)
+			.max()
+			.unwrap_or(1)
+	}
+
+	// picks the scale and transform the window's buffer should have, queues a resize
+	// and returns true if the current buffer doesn't fit anymore
+	fn refit_surface(
+		god: &mut God,
+		outputs: &[Rl<Output>],
+		surface: &Rl<Surface>,
+		buffer_transform: BufferTransform,
+	) -> bool {
+		let mut surf = surface.borrow_mut();
+		let fractional = surf.fractional.as_ref().and_then(|f| f.borrow().preferred);
+		let mut changed = match fractional {
+			Some(scale) => surf.set_fractional_scale(god, scale),
+			None => {
+				let scale = Self::pick_scale(outputs, &surf);
+				surf.set_buffer_scale(god, scale)
+			}
+		};
+		let transform = match buffer_transform {
+			BufferTransform::Fixed(t) => t,
+			BufferTransform::Preferred => surf.preferred_transform.unwrap_or_default(),
+		};
+		if transform != surf.transform {
+			surf.set_buffer_transform(god, transform);
+			changed = true;
+		}
+		if changed && surf.attached_buf.is_some() {
+			god.wlmm.queue(Action::Resize(surf.w, surf.h, surface.clone()));
+			return true;
+		}
+		false
+	}
+
+	pub fn set_buffer_transform(&mut self, presenter_id: usize, transform: BufferTransform) {
+		if let Some(canvas) = self.canvas_mut(presenter_id) {
+			canvas.set_buffer_transform(transform);
+		}
+	}
+
+	pub fn set_render_mode(&mut self, presenter_id: usize, mode: RenderMode) {
+		if let Some(canvas) = self.canvas_mut(presenter_id) {
+			canvas.render_mode = mode;
+		}
+	}
+
+	// gets the presenter drawn on its next frame, needed with RenderMode::OnDemand
+	pub fn invalidate(&mut self, presenter_id: usize) {
+		if let Some(canvas) = self.canvas_mut(presenter_id) {
+			canvas.dirty = true;
+		}
+	}
+
+	// packs the buffers in the shm pool behind the presenter towards its start, which
+	// helps after lots of resizes. the pool keeps its size, only later growth is avoided
+	pub fn compact_shm_pool(&mut self, presenter_id: usize) -> Result<(), WaytinierError> {
+		let backend = self
+			.canvas_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("presenter with this id"))?
+			.backend
+			.clone();
+		if let BufferBackend::Shm(shm) = &*backend.borrow() {
+			shm.pool.borrow_mut().compact(&mut self.god)?;
+		}
+		Ok(())
+	}
+
+	pub fn set_opaque_region(&mut self, presenter_id: usize, region: Option<Region>) {
+		if let Some(canvas) = self.canvas_mut(presenter_id) {
+			canvas.set_opaque_region(region);
+		}
+	}
+
+	pub fn set_input_region(&mut self, presenter_id: usize, region: Option<Region>) {
+		if let Some(canvas) = self.canvas_mut(presenter_id) {
+			canvas.set_input_region(region);
+		}
+	}
+
+	// hands the attached buffer to the render closure, then attaches and damages it.
+	// committing is up to the caller
+	fn render_surface<R>(
+		god: &mut God,
+		surface: &Rl<Surface>,
+		frame: usize,
+		presenter_id: usize,
+		window_state: Option<WindowState>,
+		render_mode: Option<&mut RenderMode>,
+		render: R,
+	) -> Result<(), WaytinierError>
 	where
-		F: FnMut(&mut S, Snapshot),
+		R: FnOnce(Snapshot),
 	{
-		for (id, presenter) in &mut self.presenters.inner {
-			// only tlw for now
-			let window = match presenter.any().downcast_mut::<TopLevelWindow>() {
-				Some(w) => w,
-				None => continue,
+		let mut surf = surface.borrow_mut();
+		let damage =
+			Self::draw_frame(&mut surf, frame, presenter_id, window_state, render_mode, render)?;
+		surf.attach_buffer(god)?;
+		surf.update_viewport(god);
+		for (x, y, w, h) in &damage {
+			surf.damage_buffer(god, (*x, *y), (*w, *h));
+		}
+		Ok(())
+	}
+
+	// runs the render closure on the surface's attached buffer, keeping buffer ages and
+	// the damage history up to date. returns what changed, in buffer pixels
+	pub(crate) fn draw_frame<R>(
+		surf: &mut Surface,
+		frame: usize,
+		presenter_id: usize,
+		window_state: Option<WindowState>,
+		render_mode: Option<&mut RenderMode>,
+		render: R,
+	) -> Result<Vec<Rect>, WaytinierError>
+	where
+		R: FnOnce(Snapshot),
+	{
+		let (w, h) = surf.buffer_size();
+		let size = (w as i32, h as i32);
+		surf.drawn += 1;
+		let drawn = surf.drawn;
+		let mut damage = vec![];
+		{
+			let buf = surf
+				.attached_buf
+				.as_ref()
+				.ok_or(WaytinierError::ExpectedSomeValue("no buffer attached to surface"))?;
+			let mut buf = buf.borrow_mut();
+			let age = buf.drawn_at.map_or(0, |at| drawn - at);
+			buf.drawn_at = Some(drawn);
+			let (age, stale) = match surf.damage_history.since(age, size) {
+				Some(stale) => (age as u32, stale),
+				None => (0, vec![(0, 0, size.0, size.1)]),
 			};
-			let cb = &mut window.frame_cb;
-			let frame = &mut window.frame;
+			let accessor = buf
+				.accessor
+				.as_mut()
+				.ok_or(WaytinierError::ExpectedSomeValue("buffer accessor"))?;
+			render(Snapshot {
+				buf: accessor,
+				w,
+				h,
+				stride: surf.pf.stride(w),
+				logical_w: surf.w,
+				logical_h: surf.h,
+				scale: surf.scale_factor(),
+				transform: surf.transform,
+				pf: surf.pf,
+				frame,
+				presenter_id,
+				window_state,
+				age,
+				stale,
+				damage: &mut damage,
+				render_mode,
+			});
+		}
+		// nothing reported means the closure doesn't track damage, so all of it changed
+		let damage = if damage.is_empty() {
+			vec![(0, 0, size.0, size.1)]
+		} else {
+			damage::merge(&damage, size)
+		};
+		surf.damage_history.push(damage.clone());
+		Ok(damage)
+	}
 
-			self.god.handle_events();
+	// points the surface at a buffer the compositor is done reading, adding one to the
+	// swapchain if it has room. false means they're all still in use
+	fn acquire_buffer(
+		god: &mut God,
+		registry: &Rl<Registry>,
+		surface: &Rl<Surface>,
+		backend: &Rl<BufferBackend>,
+	) -> Result<bool, WaytinierError> {
+		let released = surface.borrow().swapchain.iter().find(|b| !b.borrow().in_use).cloned();
+		if let Some(buf) = released {
+			surface.borrow_mut().attached_buf = Some(buf);
+			return Ok(true);
+		}
+		// there's no wl_buffer behind them for the compositor to read
+		if matches!(&*backend.borrow(), BufferBackend::Memory(_)) {
+			return Err(WaytinierError::NotPresentable);
+		}
+		let max = backend.borrow().max_buffers();
+		let count = surface.borrow().swapchain.len();
+		if count >= max {
+			// without a swapchain there's nothing to wait for
+			return Ok(max == 1);
+		}
+		let (w, h) = surface.borrow().buffer_size();
+		let buf = backend.borrow_mut().make_buffer(god, w, h, surface, backend, registry)?;
+		let mut surf = surface.borrow_mut();
+		surf.swapchain.push(buf.clone());
+		surf.attached_buf = Some(buf);
+		Ok(true)
+	}
 
-			// check if user wants to close window - the cb might not be a good idea
-			if window.xdg_toplevel.borrow().close_requested && (window.close_cb)() {
-				// make a trait object with a set_finished() method
-				window.finished = true;
-				continue;
+	fn render_subsurface(
+		god: &mut God,
+		registry: &Rl<Registry>,
+		parent: &Rl<Surface>,
+		sub: &mut SubSurfaceLayer,
+		frame: usize,
+		presenter_id: usize,
+		window_state: Option<WindowState>,
+	) -> Result<(), WaytinierError> {
+		{
+			// subsurfaces follow the scale of their parent
+			let parent = parent.borrow();
+			let mut surf = sub.surface.borrow_mut();
+			let rescaled = match parent.fractional_scale {
+				Some(scale) if surf.viewport.is_some() => surf.set_fractional_scale(god, scale),
+				_ => surf.set_buffer_scale(god, parent.scale),
+			};
+			if rescaled && surf.attached_buf.is_some() {
+				god.wlmm.queue(Action::Resize(surf.w, surf.h, sub.surface.clone()));
+				return Ok(());
+			}
+		}
+		// the old frame stays up until the compositor lets go of a buffer
+		if !Self::acquire_buffer(god, registry, &sub.surface, &sub.backend)? {
+			return Ok(());
+		}
+		Self::render_surface(
+			god,
+			&sub.surface,
+			frame,
+			presenter_id,
+			window_state,
+			None,
+			&mut sub.render_cb,
+		)?;
+		sub.surface.borrow().commit(god);
+		Ok(())
+	}
+
+	pub(crate) fn window_mut(&mut self, presenter_id: usize) -> Option<&mut TopLevelWindow> {
+		self.presenters.inner.get_mut(&presenter_id)?.any().downcast_mut::<TopLevelWindow>()
+	}
+
+	pub(crate) fn popup_mut(&mut self, presenter_id: usize) -> Option<&mut Popup> {
+		self.presenters.inner.get_mut(&presenter_id)?.any().downcast_mut::<Popup>()
+	}
+
+	pub(crate) fn layer_mut(&mut self, presenter_id: usize) -> Option<&mut LayerSurface> {
+		self.presenters.inner.get_mut(&presenter_id)?.any().downcast_mut::<LayerSurface>()
+	}
+
+	// the wl_output for a registry name, None gives the null object
+	pub(crate) fn output_id(&self, global: Option<u32>) -> Result<Id, WaytinierError> {
+		let Some(global) = global else {
+			return Ok(Id::default());
+		};
+		Ok(self
+			.outputs
+			.iter()
+			.find(|o| o.borrow().info.global == global)
+			.ok_or(WaytinierError::ExpectedSomeValue("output with this global"))?
+			.borrow()
+			.id)
+	}
+
+	pub(crate) fn canvas_mut(&mut self, presenter_id: usize) -> Option<&mut Canvas> {
+		Some(self.presenters.inner.get_mut(&presenter_id)?.canvas())
+	}
+
+	// shared by every window and popup, bound on first use
+	pub(crate) fn xdg_wm_base(&mut self) -> Result<Rl<XdgWmBase>, WaytinierError> {
+		if let Some(xdg_wm_base) = &self.xdg_wm_base {
+			return Ok(xdg_wm_base.clone());
+		}
+		let xdg_wm_base = XdgWmBase::new_registered_bound(&self.registry, &mut self.god)?;
+		self.xdg_wm_base = Some(xdg_wm_base.clone());
+		Ok(xdg_wm_base)
+	}
+
+	// only needed for popup grabs so far
+	pub(crate) fn seat(&mut self) -> Result<Rl<Seat>, WaytinierError> {
+		if let Some(seat) = &self.seat {
+			return Ok(seat.clone());
+		}
+		let seat = Seat::new_registered_bound(&mut self.god, &self.registry)?;
+		self.seat = Some(seat.clone());
+		Ok(seat)
+	}
+
+	pub fn set_window_title(
+		&mut self,
+		presenter_id: usize,
+		title: &str,
+	) -> Result<(), WaytinierError> {
+		let window = self
+			.window_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("window with this presenter id"))?;
+		if let Some(decorations) = &window.canvas.decorations {
+			decorations.state.borrow_mut().title = String::from(title);
+			window.canvas.dirty = true;
+		}
+		let toplevel = window.xdg_toplevel.clone();
+		toplevel.borrow_mut().set_title(&mut self.god, title);
+		Ok(())
+	}
+
+	// None for anything that isn't a window
+	pub fn window_state(&mut self, presenter_id: usize) -> Option<WindowState> {
+		self.presenters.inner.get(&presenter_id)?.window_state()
+	}
+
+	fn toplevel(&mut self, presenter_id: usize) -> Result<Rl<XdgTopLevel>, WaytinierError> {
+		Ok(self
+			.window_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("window with this presenter id"))?
+			.xdg_toplevel
+			.clone())
+	}
+
+	// keeps the window above its parent, like dialogs. None detaches it again
+	pub fn set_window_parent(
+		&mut self,
+		presenter_id: usize,
+		parent: Option<usize>,
+	) -> Result<(), WaytinierError> {
+		let parent_id = match parent {
+			Some(parent) => self.toplevel(parent)?.borrow().id,
+			None => Id::default(),
+		};
+		self.toplevel(presenter_id)?.borrow().set_parent(&mut self.god, parent_id);
+		Ok(())
+	}
+
+	// the compositor's own menu for the window, x and y are relative to the window.
+	// serial is the one of the button press that asked for it
+	pub fn show_window_menu(
+		&mut self,
+		presenter_id: usize,
+		serial: u32,
+		x: i32,
+		y: i32,
+	) -> Result<(), WaytinierError> {
+		let seat = self.seat()?;
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().show_window_menu(&mut self.god, seat.borrow().id, serial, x, y);
+		Ok(())
+	}
+
+	// starts an interactive move, serial is the one of the button press that started the drag
+	pub fn start_window_move(
+		&mut self,
+		presenter_id: usize,
+		serial: u32,
+	) -> Result<(), WaytinierError> {
+		let seat = self.seat()?;
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().start_move(&mut self.god, seat.borrow().id, serial);
+		Ok(())
+	}
+
+	pub fn start_window_resize(
+		&mut self,
+		presenter_id: usize,
+		serial: u32,
+		edges: ResizeEdge,
+	) -> Result<(), WaytinierError> {
+		let seat = self.seat()?;
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().start_resize(&mut self.god, seat.borrow().id, serial, edges);
+		Ok(())
+	}
+
+	// logical size, 0 means unlimited
+	pub fn set_window_max_size(
+		&mut self,
+		presenter_id: usize,
+		w: u32,
+		h: u32,
+	) -> Result<(), WaytinierError> {
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().set_max_size(&mut self.god, w as i32, h as i32);
+		Ok(())
+	}
+
+	pub fn set_window_min_size(
+		&mut self,
+		presenter_id: usize,
+		w: u32,
+		h: u32,
+	) -> Result<(), WaytinierError> {
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().set_min_size(&mut self.god, w as i32, h as i32);
+		Ok(())
+	}
+
+	pub fn set_window_maximized(
+		&mut self,
+		presenter_id: usize,
+		maximized: bool,
+	) -> Result<(), WaytinierError> {
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().set_maximized(&mut self.god, maximized);
+		Ok(())
+	}
+
+	// output is the registry name from OutputInfo::global, None lets the compositor choose
+	pub fn set_window_fullscreen(
+		&mut self,
+		presenter_id: usize,
+		output: Option<u32>,
+	) -> Result<(), WaytinierError> {
+		let output_id = self.output_id(output)?;
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().set_fullscreen(&mut self.god, output_id);
+		Ok(())
+	}
+
+	pub fn unset_window_fullscreen(&mut self, presenter_id: usize) -> Result<(), WaytinierError> {
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().unset_fullscreen(&mut self.god);
+		Ok(())
+	}
+
+	pub fn set_window_minimized(&mut self, presenter_id: usize) -> Result<(), WaytinierError> {
+		let toplevel = self.toplevel(presenter_id)?;
+		toplevel.borrow().set_minimized(&mut self.god);
+		Ok(())
+	}
+
+	// None if there's no seat with a pointer
+	pub(crate) fn pointer(&mut self) -> Result<Option<Rl<Pointer>>, WaytinierError> {
+		if let Some(pointer) = &self.pointer {
+			return Ok(Some(pointer.clone()));
+		}
+		if !self.registry.borrow().has(WaylandObjectKind::Seat) {
+			return Ok(None);
+		}
+		let seat = self.seat()?;
+		// capabilities come right after binding
+		if seat.borrow().capabilities == 0 {
+			wait_for_sync!(self.display, &mut self.god);
+		}
+		if !seat.borrow().has_pointer() {
+			return Ok(None);
+		}
+		let pointer = Pointer::new_registered_gotten(&mut self.god, &seat);
+		if self.registry.borrow().has(WaylandObjectKind::CursorShapeManager) {
+			let manager = CursorShapeManager::new_registered_bound(&mut self.god, &self.registry)?;
+			self.cursor_shape_device =
+				Some(CursorShapeDevice::new_registered_gotten(&mut self.god, &manager, &pointer));
+		}
+		self.pointer = Some(pointer.clone());
+		Ok(Some(pointer))
+	}
+
+	// picks the cursor for wherever the pointer is and feeds pointer input to the
+	// built-in decorations
+	fn dispatch_pointer(&mut self) -> Result<(), WaytinierError> {
+		let Some(pointer) = self.pointer.clone() else {
+			return Ok(());
+		};
+		loop {
+			let Some(event) = pointer.borrow_mut().next_event() else {
+				return Ok(());
+			};
+			let (focus, (x, y)) = {
+				let pointer = pointer.borrow();
+				(pointer.focus, pointer.position)
+			};
+			let surface = match event {
+				PointerEvent::Leave {
+					surface,
+					..
+				} => {
+					self.cursor = None;
+					surface
+				}
+				_ => match focus {
+					Some(surface) => surface,
+					None => continue,
+				},
 			};
-			if window.xdg_surface.borrow().is_configured {
-				let ready = match &cb.clone() {
-					Some(cb) => cb.borrow().done,
-					None => true,
-				};
-
-				let mut surf = window.surface.borrow_mut();
-				let surf_w = surf.w;
-				let surf_h = surf.h;
-				if surf.attached_buf.is_none() {
-					dbug!("no buf");
-					drop(surf);
-					let buf = window.backend.borrow_mut().make_buffer(
-						&mut self.god,
-						surf_w,
-						surf_h,
-						&window.surface,
-						&window.backend,
-						&self.registry,
-					)?;
-					let mut surf = window.surface.borrow_mut();
-					surf.attach_buffer_obj(&mut self.god, buf)?;
-					surf.commit(&mut self.god);
-					drop(surf);
-					self.god.handle_events()?;
-					continue;
+			// the surface is either a presenter's own or the frame around it
+			let Some((id, on_frame)) = self.presenters.inner.iter_mut().find_map(|(id, p)| {
+				let canvas = p.canvas();
+				if canvas.surface.borrow().id == surface {
+					return Some((*id, false));
 				}
+				let decorations = canvas.decorations.as_ref()?;
+				(decorations.layer.surface.borrow().id == surface).then_some((*id, true))
+			}) else {
+				continue;
+			};
+			let Some(canvas) = self.canvas_mut(id) else {
+				continue;
+			};
+			let hit = canvas.decorations.as_ref().and_then(|d| d.hit_test(x, y));
+			let shape = if on_frame {
+				hit.map(FrameHit::cursor_shape).unwrap_or_default()
+			} else {
+				canvas.cursor
+			};
+			if !matches!(event, PointerEvent::Leave { .. }) {
+				self.apply_cursor(shape)?;
+			}
+			if on_frame {
+				self.frame_pointer_event(id, event, hit, (x, y))?;
+			}
+		}
+	}
+
+	// only sends something if the shape changed since the last enter
+	fn apply_cursor(&mut self, shape: CursorShape) -> Result<(), WaytinierError> {
+		let Some(pointer) = &self.pointer else {
+			return Ok(());
+		};
+		let serial = pointer.borrow().enter_serial;
+		if self.cursor == Some((serial, shape)) {
+			return Ok(());
+		}
+		self.cursor = Some((serial, shape));
+		if let Some(device) = &self.cursor_shape_device {
+			device.borrow().set_shape(&mut self.god, serial, shape);
+			return Ok(());
+		}
+		if self.themed_cursor.is_none() {
+			self.themed_cursor = Some(ThemedCursor::new(self)?);
+		}
+		let scale = self.outputs.iter().map(|o| o.borrow().info.scale.max(1) as u32).max();
+		let (Some(cursor), Some(pointer)) = (&mut self.themed_cursor, &self.pointer) else {
+			return Ok(());
+		};
+		let pointer = pointer.borrow();
+		let scale = scale.unwrap_or(1);
+		// not every theme has every shape, the arrow is better than nothing
+		if !cursor.set(&mut self.god, &pointer, shape, scale)? {
+			cursor.set(&mut self.god, &pointer, CursorShape::Default, scale)?;
+		}
+		Ok(())
+	}
 
-				if ready {
-					let new_cb = surf.frame(&mut self.god)?;
-					*cb = Some(new_cb);
-					*frame = frame.wrapping_add(1);
-
-					{
-						// should be created above
-						let accessor = surf.attached_buf.as_ref().unwrap();
-						let mut accessor = accessor.borrow_mut();
-						let mut accessor = accessor
-							.accessor
-							.as_mut()
-							.ok_or(WaytinierError::ExpectedSomeValue("buffer accessor"))?;
-						let buf = surf.attached_buf.clone().ok_or("no buffer");
-
-						let ss = Snapshot {
-							buf: &mut accessor,
-							w: surf_w,
-							h: surf_h,
-							pf: surf.pf,
-							frame: *frame,
-							presenter_id: *id,
-						};
-
-						render_fun(state, ss);
+	// the cursor shown while the pointer is over the presenter, applied right away
+	// if it's there already
+	pub fn set_cursor_shape(
+		&mut self,
+		presenter_id: usize,
+		shape: CursorShape,
+	) -> Result<(), WaytinierError> {
+		let canvas = self
+			.canvas_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("presenter with this id"))?;
+		canvas.cursor = shape;
+		let surface = canvas.surface.borrow().id;
+		let focus = self.pointer.as_ref().and_then(|p| p.borrow().focus);
+		if focus == Some(surface) {
+			self.apply_cursor(shape)?;
+		}
+		Ok(())
+	}
+
+	fn frame_pointer_event(
+		&mut self,
+		presenter_id: usize,
+		event: PointerEvent,
+		hit: Option<FrameHit>,
+		(x, y): (f64, f64),
+	) -> Result<(), WaytinierError> {
+		let Some(window) = self.window_mut(presenter_id) else {
+			return Ok(());
+		};
+		let Some(decorations) = &window.canvas.decorations else {
+			return Ok(());
+		};
+		let frame = decorations.state.clone();
+		let mut frame = frame.borrow_mut();
+		let hovered = match event {
+			PointerEvent::Leave {
+				..
+			} => None,
+			_ => hit,
+		};
+		if frame.hovered != hovered {
+			frame.hovered = hovered;
+			window.canvas.dirty = true;
+		}
+		let PointerEvent::Button {
+			serial,
+			button,
+			pressed,
+		} = event
+		else {
+			return Ok(());
+		};
+		let toplevel = window.xdg_toplevel.clone();
+		let maximized = frame.window_state.maximized;
+		match (button, pressed, hit) {
+			(BTN_LEFT, true, Some(FrameHit::Titlebar)) => {
+				let seat = self.seat()?;
+				toplevel.borrow().start_move(&mut self.god, seat.borrow().id, serial);
+			}
+			(BTN_LEFT, true, Some(FrameHit::Edge(edge))) => {
+				let seat = self.seat()?;
+				toplevel.borrow().start_resize(&mut self.god, seat.borrow().id, serial, edge);
+			}
+			(BTN_LEFT, true, hit) => frame.pressed = hit,
+			(BTN_LEFT, false, hit) => {
+				if frame.pressed.take() != hit {
+					return Ok(());
+				}
+				match hit {
+					Some(FrameHit::Close) => toplevel.borrow_mut().close_requested = true,
+					Some(FrameHit::Maximize) => {
+						toplevel.borrow().set_maximized(&mut self.god, !maximized)
 					}
-					surf.attach_buffer(&mut self.god)?;
-					surf.repaint(&mut self.god)?;
-					surf.commit(&mut self.god);
+					Some(FrameHit::Minimize) => toplevel.borrow().set_minimized(&mut self.god),
+					_ => {}
 				}
 			}
+			(BTN_RIGHT, true, Some(FrameHit::Titlebar)) => {
+				// relative to the window geometry, which doesn't include the borders
+				let (wx, wy) = ((x - BORDER as f64) as i32, (y - BORDER as f64) as i32);
+				let seat = self.seat()?;
+				toplevel.borrow().show_window_menu(&mut self.god, seat.borrow().id, serial, wx, wy);
+			}
+			_ => {}
+		}
+		Ok(())
+	}
+
+	// shared by every layer surface, errors on compositors without wlr-layer-shell
+	pub(crate) fn layer_shell(&mut self) -> Result<Rl<WlrLayerShell>, WaytinierError> {
+		if let Some(shell) = &self.layer_shell {
+			return Ok(shell.clone());
+		}
+		let shell = WlrLayerShell::new_registered_bound(&mut self.god, &self.registry)?;
+		self.layer_shell = Some(shell.clone());
+		Ok(shell)
+	}
+
+	fn layer(&mut self, presenter_id: usize) -> Result<Rl<WlrLayerSurface>, WaytinierError> {
+		Ok(self
+			.layer_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("layer surface with this presenter id"))?
+			.layer_surface
+			.clone())
+	}
+
+	// layer surface state is double buffered, it gets applied with the next frame
+	pub fn set_layer(&mut self, presenter_id: usize, layer: Layer) -> Result<(), WaytinierError> {
+		let surface = self.layer(presenter_id)?;
+		surface.borrow().set_layer(&mut self.god, layer)?;
+		self.invalidate(presenter_id);
+		Ok(())
+	}
+
+	pub fn set_layer_anchor(
+		&mut self,
+		presenter_id: usize,
+		anchor: LayerAnchor,
+	) -> Result<(), WaytinierError> {
+		let surface = self.layer(presenter_id)?;
+		surface.borrow().set_anchor(&mut self.god, anchor);
+		self.invalidate(presenter_id);
+		Ok(())
+	}
+
+	// top, right, bottom, left
+	pub fn set_layer_margin(
+		&mut self,
+		presenter_id: usize,
+		margin: (i32, i32, i32, i32),
+	) -> Result<(), WaytinierError> {
+		let surface = self.layer(presenter_id)?;
+		surface.borrow().set_margin(&mut self.god, margin);
+		self.invalidate(presenter_id);
+		Ok(())
+	}
+
+	pub fn set_layer_exclusive_zone(
+		&mut self,
+		presenter_id: usize,
+		zone: i32,
+	) -> Result<(), WaytinierError> {
+		let surface = self.layer(presenter_id)?;
+		surface.borrow().set_exclusive_zone(&mut self.god, zone);
+		self.invalidate(presenter_id);
+		Ok(())
+	}
+
+	pub fn set_layer_keyboard_interactivity(
+		&mut self,
+		presenter_id: usize,
+		mode: KeyboardInteractivity,
+	) -> Result<(), WaytinierError> {
+		let surface = self.layer(presenter_id)?;
+		surface.borrow().set_keyboard_interactivity(&mut self.god, mode);
+		self.invalidate(presenter_id);
+		Ok(())
+	}
+
+	// a 0 stretches the surface between the edges it's anchored to
+	pub fn set_layer_size(
+		&mut self,
+		presenter_id: usize,
+		w: u32,
+		h: u32,
+	) -> Result<(), WaytinierError> {
+		let surface = self.layer(presenter_id)?;
+		surface.borrow().set_size(&mut self.god, w, h);
+		self.invalidate(presenter_id);
+		Ok(())
+	}
+
+	// None if the compositor doesn't do xdg-decoration, windows are client-side decorated then
+	pub(crate) fn decoration_manager(
+		&mut self,
+	) -> Result<Option<Rl<DecorationManager>>, WaytinierError> {
+		if let Some(manager) = &self.decoration_manager {
+			return Ok(Some(manager.clone()));
+		}
+		if !self.registry.borrow().has(WaylandObjectKind::DecorationManager) {
+			return Ok(None);
+		}
+		let manager = DecorationManager::new_registered_bound(&mut self.god, &self.registry)?;
+		self.decoration_manager = Some(manager.clone());
+		Ok(Some(manager))
+	}
+
+	// what the compositor chose for the window. client-side until it says otherwise,
+	// which is also the case if it doesn't support xdg-decoration at all
+	pub fn decoration_mode(&mut self, presenter_id: usize) -> Option<DecorationMode> {
+		let window = self.window_mut(presenter_id)?;
+		Some(match &window.decoration {
+			Some(decoration) => decoration.borrow().mode.unwrap_or_default(),
+			None => DecorationMode::ClientSide,
+		})
+	}
+
+	// asks for another mode at runtime, None leaves it to the compositor
+	pub fn set_decoration_mode(
+		&mut self,
+		presenter_id: usize,
+		mode: Option<DecorationMode>,
+	) -> Result<(), WaytinierError> {
+		let decoration = self
+			.window_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("window with this presenter id"))?
+			.decoration
+			.clone()
+			.ok_or(WaytinierError::ExpectedSomeValue("xdg-decoration support"))?;
+		let decoration = decoration.borrow();
+		match mode {
+			Some(mode) => decoration.set_mode(&mut self.god, mode),
+			None => decoration.unset_mode(&mut self.god),
+		}
+		Ok(())
+	}
+
+	// moves an open popup, needs xdg_wm_base v3. returns the token that
+	// App::popup_repositioned reports once the compositor applied it
+	pub fn reposition_popup(
+		&mut self,
+		presenter_id: usize,
+		positioner: &Positioner,
+	) -> Result<u32, WaytinierError> {
+		let wm_base = self.xdg_wm_base()?;
+		if wm_base.borrow().version < 3 {
+			return Err(WaytinierError::ExpectedSomeValue("xdg_wm_base v3 for repositioning"));
+		}
+		let xdg_popup = self
+			.popup_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("popup with this presenter id"))?
+			.xdg_popup
+			.clone();
+		let xdg_positioner =
+			XdgPositioner::new_registered_created(&mut self.god, &wm_base, positioner);
+		let token = xdg_popup.borrow_mut().reposition(&mut self.god, &xdg_positioner);
+		xdg_positioner.borrow().destroy(&mut self.god);
+		Ok(token)
+	}
+
+	// the token of the last reposition the compositor applied
+	pub fn popup_repositioned(&mut self, presenter_id: usize) -> Option<u32> {
+		self.popup_mut(presenter_id)?.xdg_popup.borrow().repositioned_token
+	}
+
+	// position relative to the parent and size the compositor gave the popup
+	pub fn popup_geometry(&mut self, presenter_id: usize) -> Option<(i32, i32, i32, i32)> {
+		Some(self.popup_mut(presenter_id)?.xdg_popup.borrow().geometry)
+	}
+
+	// takes the popup down together with every popup opened from it
+	pub fn close_popup(&mut self, presenter_id: usize) -> Result<(), WaytinierError> {
+		if self.popup_mut(presenter_id).is_none() {
+			return Err(WaytinierError::ExpectedSomeValue("popup with this presenter id"));
+		}
+		self.destroy_presenter(presenter_id);
+		Ok(())
+	}
+
+	// xdg_wm_base wants the topmost popup gone first, so the ones opened from this go before it
+	fn destroy_presenter(&mut self, presenter_id: usize) {
+		let Some(presenter) = self.presenters.inner.get(&presenter_id) else {
+			return;
+		};
+		if let Some(xdg_surface) = presenter.xdg_surface() {
+			let children: Vec<usize> = self
+				.presenters
+				.inner
+				.iter_mut()
+				.filter_map(|(id, p)| {
+					let popup = p.any().downcast_mut::<Popup>()?;
+					Rc::ptr_eq(&popup.parent, &xdg_surface).then_some(*id)
+				})
+				.collect();
+			for child in children {
+				self.destroy_presenter(child);
+			}
+		}
+		let Some(mut presenter) = self.presenters.inner.remove(&presenter_id) else {
+			return;
+		};
+		if let Some(popup) = presenter.any().downcast_mut::<Popup>() {
+			popup.destroy(&mut self.god);
+		} else if let Some(layer) = presenter.any().downcast_mut::<LayerSurface>() {
+			layer.destroy(&mut self.god);
+		}
+	}
+
+	// wl_subcompositor is only bound once someone asks for a subsurface
+	pub(crate) fn subcompositor(&mut self) -> Result<Rl<SubCompositor>, WaytinierError> {
+		if let Some(subcompositor) = &self.subcompositor {
+			return Ok(subcompositor.clone());
+		}
+		let subcompositor = SubCompositor::new_registered_bound(&mut self.god, &self.registry)?;
+		self.subcompositor = Some(subcompositor.clone());
+		Ok(subcompositor)
+	}
+
+	fn subsurface_layer(
+		&mut self,
+		presenter_id: usize,
+		sub_id: usize,
+	) -> Result<&mut SubSurfaceLayer, WaytinierError> {
+		self.canvas_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("presenter with this id"))?
+			.subsurfaces
+			.get_mut(sub_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("subsurface with this id"))
+	}
+
+	// positions are relative to the parent surface, applied on the parent's next commit
+	pub fn set_subsurface_position(
+		&mut self,
+		presenter_id: usize,
+		sub_id: usize,
+		x: i32,
+		y: i32,
+	) -> Result<(), WaytinierError> {
+		let subsurface = self.subsurface_layer(presenter_id, sub_id)?.subsurface.clone();
+		subsurface.borrow().set_position(&mut self.god, x, y);
+		Ok(())
+	}
+
+	// the sibling is another subsurface of the same window, None means the window itself
+	fn sibling_surface_id(
+		&mut self,
+		presenter_id: usize,
+		sibling: Option<usize>,
+	) -> Result<Id, WaytinierError> {
+		let canvas = self
+			.canvas_mut(presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("presenter with this id"))?;
+		let surface = match sibling {
+			Some(sibling) => {
+				&canvas
+					.subsurfaces
+					.get(sibling)
+					.ok_or(WaytinierError::ExpectedSomeValue("subsurface with this id"))?
+					.surface
+			}
+			None => &canvas.surface,
+		};
+		Ok(surface.borrow().id)
+	}
+
+	pub fn place_subsurface_above(
+		&mut self,
+		presenter_id: usize,
+		sub_id: usize,
+		sibling: Option<usize>,
+	) -> Result<(), WaytinierError> {
+		let sibling = self.sibling_surface_id(presenter_id, sibling)?;
+		let subsurface = self.subsurface_layer(presenter_id, sub_id)?.subsurface.clone();
+		subsurface.borrow().place_above(&mut self.god, sibling);
+		Ok(())
+	}
+
+	pub fn place_subsurface_below(
+		&mut self,
+		presenter_id: usize,
+		sub_id: usize,
+		sibling: Option<usize>,
+	) -> Result<(), WaytinierError> {
+		let sibling = self.sibling_surface_id(presenter_id, sibling)?;
+		let subsurface = self.subsurface_layer(presenter_id, sub_id)?.subsurface.clone();
+		subsurface.borrow().place_below(&mut self.god, sibling);
+		Ok(())
+	}
+
+	// a desynchronized subsurface shows its commits right away instead of waiting for the parent
+	pub fn set_subsurface_sync(
+		&mut self,
+		presenter_id: usize,
+		sub_id: usize,
+		sync: bool,
+	) -> Result<(), WaytinierError> {
+		let subsurface = self.subsurface_layer(presenter_id, sub_id)?.subsurface.clone();
+		subsurface.borrow_mut().set_sync(&mut self.god, sync);
+		Ok(())
+	}
+
+	// one step of drawing a presenter: fits the buffer, makes one if needed and renders
+	// once the previous frame is done
+	fn work_canvas<R>(
+		god: &mut God,
+		registry: &Rl<Registry>,
+		compositor: &Rl<Compositor>,
+		outputs: &[Rl<Output>],
+		presenter: &mut dyn PresenterObject,
+		presenter_id: usize,
+		render: R,
+	) -> Result<(), WaytinierError>
+	where
+		R: FnOnce(Snapshot),
+	{
+		let window_state = presenter.window_state();
+		let canvas = presenter.canvas();
+		let ready = match &canvas.frame_cb {
+			Some(cb) => cb.borrow().done,
+			None => true,
+		};
+
+		// the buffer has to match the new scale and transform before the next commit
+		if Self::refit_surface(god, outputs, &canvas.surface, canvas.buffer_transform) {
+			return god.handle_events();
+		}
+		let mut surf = canvas.surface.borrow_mut();
+		let (surf_w, surf_h) = surf.buffer_size();
+		if surf.attached_buf.is_none() {
+			dbug!("no buf");
+			drop(surf);
+			Self::acquire_buffer(god, registry, &canvas.surface, &canvas.backend)?;
+			let mut surf = canvas.surface.borrow_mut();
+			surf.attach_buffer(god)?;
+			surf.commit(god);
+			drop(surf);
+			return god.handle_events();
+		}
+
+		// nothing of the window is visible, events still get handled but no frames requested
+		if window_state.is_some_and(|s| s.suspended) {
+			return Ok(());
+		}
+		// a resized buffer has nothing useful in it
+		if canvas.rendered_size != Some((surf_w, surf_h)) {
+			canvas.dirty = true;
+		}
+		if canvas.render_mode == RenderMode::OnDemand && !canvas.dirty {
+			return Ok(());
+		}
+
+		if ready {
+			// drawing into a buffer the compositor still reads from tears
+			drop(surf);
+			if !Self::acquire_buffer(god, registry, &canvas.surface, &canvas.backend)? {
+				return Ok(());
+			}
+			let mut surf = canvas.surface.borrow_mut();
+			let new_cb = surf.frame(god)?;
+			canvas.frame_cb = Some(new_cb);
+			canvas.frame = canvas.frame.wrapping_add(1);
+			canvas.dirty = false;
+			canvas.rendered_size = Some((surf_w, surf_h));
+			drop(surf);
+
+			// synchronized subsurfaces cache their state until the parent commits,
+			// so they have to go first to show up in the same frame
+			if let Some(decorations) = &mut canvas.decorations {
+				decorations.update(god, &canvas.surface, window_state.unwrap_or_default())?;
+				Self::render_subsurface(
+					god,
+					registry,
+					&canvas.surface,
+					&mut decorations.layer,
+					canvas.frame,
+					presenter_id,
+					window_state,
+				)?;
+			}
+			for sub in &mut canvas.subsurfaces {
+				Self::render_subsurface(
+					god,
+					registry,
+					&canvas.surface,
+					sub,
+					canvas.frame,
+					presenter_id,
+					window_state,
+				)?;
+			}
+
+			Self::render_surface(
+				god,
+				&canvas.surface,
+				canvas.frame,
+				presenter_id,
+				window_state,
+				Some(&mut canvas.render_mode),
+				render,
+			)?;
+			let mut surf = canvas.surface.borrow_mut();
+			let opaque = if canvas.auto_opaque && !surf.pf.has_alpha() {
+				Some(Region::new().add(0, 0, surf.w as i32, surf.h as i32))
+			} else {
+				canvas.opaque_region.clone()
+			};
+			surf.set_opaque_region(god, compositor, opaque);
+			surf.set_input_region(god, compositor, canvas.input_region.clone());
+			surf.commit(god);
+		}
+		Ok(())
+	}
+
+	pub fn work<F, S>(&mut self, state: &mut S, mut render_fun: F) -> Result<bool, WaytinierError>
+	where
+		F: FnMut(&mut S, Snapshot),
+	{
+		self.god.handle_events()?;
+		self.dispatch_pointer()?;
+		if let Some(cursor) = &mut self.themed_cursor {
+			cursor.animate(&mut self.god)?;
+		}
+		for (id, presenter) in &mut self.presenters.inner {
+			self.god.handle_events();
+
+			presenter.poll_close();
+			if presenter.is_finished() || !presenter.is_configured() {
+				continue;
+			}
+			Self::work_canvas(
+				&mut self.god,
+				&self.registry,
+				&self.compositor,
+				&self.outputs,
+				presenter.as_mut(),
+				*id,
+				|ss| render_fun(state, ss),
+			)?;
+		}
+		self.sync_outputs()?;
+		let finished: Vec<usize> = self
+			.presenters
+			.inner
+			.iter()
+			.filter(|(_, p)| p.is_finished())
+			.map(|(id, _)| *id)
+			.collect();
+		for id in finished {
+			self.destroy_presenter(id);
 		}
-		self.presenters.inner.retain(|_, pres| !pres.is_finished());
 		if self.presenters.inner.iter().all(|(_, p)| p.is_finished()) {
 			self.finished = true;
 		};
@@ -148,10 +1305,57 @@ impl App {
 }
 
 pub struct Snapshot<'a> {
-	pub buf: &'a mut BufferAccessor,
+	pub(crate) buf: &'a mut BufferAccessor,
+	// physical size of the buffer
 	pub w: u32,
 	pub h: u32,
+	// bytes per row, not always w times the pixel size
+	pub stride: u32,
+	// size the compositor thinks of the window in
+	pub logical_w: u32,
+	pub logical_h: u32,
+	// exact, can be fractional like 1.25
+	pub scale: f64,
+	// the buffer gets shown with the inverse of this applied, so contents have to be
+	// drawn pre-rotated. w and h are already swapped for 90 and 270 degrees
+	pub transform: Transform,
 	pub pf: PixelFormat,
 	pub frame: usize,
 	pub presenter_id: usize,
+	// state of the window as of its last configure, None for popups and such
+	pub window_state: Option<WindowState>,
+	// frames since this buffer was last drawn into, 0 when its contents are garbage.
+	// only stale and whatever changes now have to be drawn over what's already there.
+	// like all damage it's in buffer pixels, the transform already applied
+	pub age: u32,
+	pub stale: Vec<Rect>,
+	pub(crate) damage: &'a mut Vec<Rect>,
+	pub(crate) render_mode: Option<&'a mut RenderMode>,
+}
+
+impl Snapshot<'_> {
+	// a safe view of the frame's pixels, None for dmabufs which aren't mapped
+	pub fn pixels(&mut self) -> Option<PixelBuffer<'_>> {
+		let data = self.buf.bytes()?;
+		Some(PixelBuffer::new(data, self.w, self.h, self.stride, self.pf))
+	}
+
+	// pixels() wrapped for drawing shapes and images in any format
+	pub fn painter(&mut self) -> Option<Painter<'_>> {
+		self.pixels().map(Painter::new)
+	}
+
+	// marks a rectangle in buffer pixels as changed this frame, it goes to the compositor
+	// as is since the contents are drawn pre-transformed. without any the whole buffer
+	// counts as changed
+	pub fn add_damage(&mut self, x: i32, y: i32, w: i32, h: i32) {
+		self.damage.push((x, y, w, h));
+	}
+
+	// takes effect after this frame, does nothing for subsurfaces
+	pub fn set_render_mode(&mut self, mode: RenderMode) {
+		if let Some(render_mode) = &mut self.render_mode {
+			**render_mode = mode;
+		}
+	}
 }
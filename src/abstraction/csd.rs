@@ -0,0 +1,284 @@
+use crate::{
+	Rl, ShmBackend,
+	abstraction::{
+		app::{App, Snapshot},
+		draw::Color,
+		presenter::SubSurfaceLayer,
+		text::Font,
+	},
+	rl,
+	wayland::{
+		God, PixelFormat, WaytinierError,
+		cursor_shape::CursorShape,
+		subcompositor::SubSurface,
+		surface::Surface,
+		viewporter::Viewport,
+		wire::Action,
+		xdg_shell::{
+			surface::XdgSurface,
+			toplevel::{ResizeEdge, WindowState},
+		},
+	},
+};
+
+// all in logical pixels
+pub(crate) const TITLEBAR_HEIGHT: u32 = 28;
+// invisible, only there to be grabbed for resizing
+pub(crate) const BORDER: u32 = 6;
+const BUTTON_WIDTH: u32 = 36;
+// how far along an edge still counts as the corner
+const CORNER: u32 = 16;
+// the builtin font's glyph height
+const GLYPH_H: u32 = 7;
+
+const TITLEBAR_ACTIVE: u32 = 0xff2b2b2b;
+const TITLEBAR_INACTIVE: u32 = 0xff3c3c3c;
+const TEXT_ACTIVE: u32 = 0xffe6e6e6;
+const TEXT_INACTIVE: u32 = 0xff9a9a9a;
+const BUTTON_HOVER: u32 = 0xff4d4d4d;
+const CLOSE_HOVER: u32 = 0xffc0392b;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameHit {
+	Titlebar,
+	Close,
+	Maximize,
+	Minimize,
+	Edge(ResizeEdge),
+}
+
+impl FrameHit {
+	pub(crate) fn cursor_shape(self) -> CursorShape {
+		match self {
+			Self::Edge(ResizeEdge::Top) => CursorShape::NResize,
+			Self::Edge(ResizeEdge::Bottom) => CursorShape::SResize,
+			Self::Edge(ResizeEdge::Left) => CursorShape::WResize,
+			Self::Edge(ResizeEdge::Right) => CursorShape::EResize,
+			Self::Edge(ResizeEdge::TopLeft) => CursorShape::NwResize,
+			Self::Edge(ResizeEdge::TopRight) => CursorShape::NeResize,
+			Self::Edge(ResizeEdge::BottomLeft) => CursorShape::SwResize,
+			Self::Edge(ResizeEdge::BottomRight) => CursorShape::SeResize,
+			_ => CursorShape::Default,
+		}
+	}
+}
+
+pub(crate) struct FrameState {
+	pub(crate) title: String,
+	pub(crate) hovered: Option<FrameHit>,
+	// button that got pressed, it only does something if released over the same one
+	pub(crate) pressed: Option<FrameHit>,
+	pub(crate) window_state: WindowState,
+}
+
+// titlebar and resize borders drawn into a subsurface below the window, so the
+// window's own buffer stays the user's
+pub(crate) struct Decorations {
+	pub(crate) layer: SubSurfaceLayer,
+	pub(crate) state: Rl<FrameState>,
+	pub(crate) xdg_surface: Rl<XdgSurface>,
+	// content size the window geometry was last set for
+	pub(crate) geometry: Option<(u32, u32)>,
+}
+
+impl Decorations {
+	pub(crate) fn new(
+		app: &mut App,
+		parent: &Rl<Surface>,
+		xdg_surface: &Rl<XdgSurface>,
+		title: &str,
+	) -> Result<Self, WaytinierError> {
+		let subcompositor = app.subcompositor()?;
+		let backend = ShmBackend::new(app)?;
+		let (w, h) = {
+			let parent = parent.borrow();
+			Self::frame_size(parent.w, parent.h)
+		};
+		let god = &mut app.god;
+		let surface =
+			Surface::new_registered_made(god, &app.compositor, w, h, PixelFormat::Argb888);
+		if let Some(viewporter) = &app.viewporter {
+			surface.borrow_mut().viewport =
+				Some(Viewport::new_registered_gotten(god, viewporter, &surface));
+		}
+		let subsurface = SubSurface::new_registered_gotten(god, &subcompositor, &surface, parent);
+		{
+			let sub = subsurface.borrow();
+			sub.set_position(god, -(BORDER as i32), -((TITLEBAR_HEIGHT + BORDER) as i32));
+			sub.place_below(god, parent.borrow().id);
+		}
+		let state = rl!(FrameState {
+			title: String::from(title),
+			hovered: None,
+			pressed: None,
+			window_state: WindowState::default(),
+		});
+		let render_state = state.clone();
+		let layer = SubSurfaceLayer {
+			subsurface,
+			surface,
+			backend,
+			render_cb: Box::new(move |ss| draw(ss, &render_state.borrow())),
+		};
+		Ok(Self {
+			layer,
+			state,
+			xdg_surface: xdg_surface.clone(),
+			geometry: None,
+		})
+	}
+
+	fn frame_size(w: u32, h: u32) -> (u32, u32) {
+		(w + 2 * BORDER, h + TITLEBAR_HEIGHT + 2 * BORDER)
+	}
+
+	// keeps the frame around the window's current size, called before every frame
+	pub(crate) fn update(
+		&mut self,
+		god: &mut God,
+		parent: &Rl<Surface>,
+		window_state: WindowState,
+	) -> Result<(), WaytinierError> {
+		let (w, h) = {
+			let parent = parent.borrow();
+			(parent.w, parent.h)
+		};
+		self.state.borrow_mut().window_state = window_state;
+		if self.geometry != Some((w, h)) {
+			self.geometry = Some((w, h));
+			// the titlebar is part of the window, the borders aren't
+			self.xdg_surface.borrow().set_window_geometry(
+				god,
+				0,
+				-(TITLEBAR_HEIGHT as i32),
+				w as i32,
+				(h + TITLEBAR_HEIGHT) as i32,
+			);
+		}
+		let (fw, fh) = Self::frame_size(w, h);
+		let mut surf = self.layer.surface.borrow_mut();
+		if (surf.w, surf.h) == (fw, fh) {
+			return Ok(());
+		}
+		if surf.attached_buf.is_some() {
+			drop(surf);
+			god.wlmm.queue(Action::Resize(fw, fh, self.layer.surface.clone()));
+			god.handle_events()?;
+		} else {
+			surf.w = fw;
+			surf.h = fh;
+		}
+		Ok(())
+	}
+
+	// x and y are relative to the frame surface
+	pub(crate) fn hit_test(&self, x: f64, y: f64) -> Option<FrameHit> {
+		let (fw, fh) = {
+			let surf = self.layer.surface.borrow();
+			(surf.w as f64, surf.h as f64)
+		};
+		let (border, corner) = (BORDER as f64, CORNER as f64);
+		if x < 0.0 || y < 0.0 || x >= fw || y >= fh {
+			return None;
+		}
+		let left = x < border || (x < corner && (y < border || y >= fh - border));
+		let right = x >= fw - border || (x >= fw - corner && (y < border || y >= fh - border));
+		let top = y < border || (y < corner && (x < border || x >= fw - border));
+		let bottom = y >= fh - border || (y >= fh - corner && (x < border || x >= fw - border));
+		let edge = match (top, bottom, left, right) {
+			(true, _, true, _) => Some(ResizeEdge::TopLeft),
+			(true, _, _, true) => Some(ResizeEdge::TopRight),
+			(_, true, true, _) => Some(ResizeEdge::BottomLeft),
+			(_, true, _, true) => Some(ResizeEdge::BottomRight),
+			(true, ..) => Some(ResizeEdge::Top),
+			(_, true, ..) => Some(ResizeEdge::Bottom),
+			(_, _, true, _) => Some(ResizeEdge::Left),
+			(.., true) => Some(ResizeEdge::Right),
+			_ => None,
+		};
+		if let Some(edge) = edge {
+			return Some(FrameHit::Edge(edge));
+		}
+		if y >= border + TITLEBAR_HEIGHT as f64 {
+			return None;
+		}
+		let from_right = fw - border - x;
+		Some(match (from_right / BUTTON_WIDTH as f64) as u32 {
+			0 => FrameHit::Close,
+			1 => FrameHit::Maximize,
+			2 => FrameHit::Minimize,
+			_ => FrameHit::Titlebar,
+		})
+	}
+}
+
+// physical pixels of a logical length, never less than one
+fn px(logical: f64, scale: f64) -> i32 {
+	((logical * scale).round() as i32).max(1)
+}
+
+fn draw(mut ss: Snapshot, state: &FrameState) {
+	let s = ss.scale;
+	let Some(mut painter) = ss.painter() else {
+		return;
+	};
+	painter.clear(Color::TRANSPARENT);
+
+	let activated = state.window_state.activated;
+	let border = px(BORDER as f64, s);
+	let bar_h = px(TITLEBAR_HEIGHT as f64, s);
+	let bar_w = painter.width() - 2 * border;
+	let background = Color::from_argb(if activated {
+		TITLEBAR_ACTIVE
+	} else {
+		TITLEBAR_INACTIVE
+	});
+	let foreground = Color::from_argb(if activated {
+		TEXT_ACTIVE
+	} else {
+		TEXT_INACTIVE
+	});
+	painter.fill_rect((border, border, bar_w, bar_h), background);
+
+	// buttons from the right: close, maximize, minimize
+	let button_w = px(BUTTON_WIDTH as f64, s);
+	let icon = px(10.0, s);
+	let line = px(1.0, s);
+	let buttons = [FrameHit::Close, FrameHit::Maximize, FrameHit::Minimize];
+	for (i, button) in buttons.iter().enumerate() {
+		let bx = border + bar_w - button_w * (i as i32 + 1);
+		if state.hovered == Some(*button) {
+			let color = match button {
+				FrameHit::Close => CLOSE_HOVER,
+				_ => BUTTON_HOVER,
+			};
+			painter.fill_rect((bx, border, button_w, bar_h), Color::from_argb(color));
+		}
+		let (ix, iy) = (bx + (button_w - icon) / 2, border + (bar_h - icon) / 2);
+		match button {
+			FrameHit::Close => {
+				let (x0, y0, x1, y1) =
+					(ix as f32, iy as f32, (ix + icon) as f32, (iy + icon) as f32);
+				painter.line((x0, y0), (x1, y1), line as f32, foreground);
+				painter.line((x1, y0), (x0, y1), line as f32, foreground);
+			}
+			FrameHit::Maximize => {
+				painter.stroke_rect((ix, iy, icon, icon), line, foreground);
+				if state.window_state.maximized {
+					painter.fill_rect((ix, iy + line, icon, line), foreground);
+				}
+			}
+			_ => painter.fill_rect((ix, iy + icon - line, icon, line), foreground),
+		}
+	}
+
+	let cell = px(TITLEBAR_HEIGHT as f64 / 14.0, s);
+	let text_y = border + (bar_h - GLYPH_H as i32 * cell) / 2;
+	let text_max = border + bar_w - button_w * buttons.len() as i32;
+	let text_x = border + px(10.0, s);
+	// whatever doesn't fit before the buttons gets cut off
+	painter.set_clip(Some((text_x, border, text_max - text_x, bar_h)));
+	let font = Font::builtin();
+	let title = font.layout(&state.title, (GLYPH_H as i32 * cell) as f32, None);
+	title.draw(&mut painter, (text_x as f32, text_y as f32), foreground);
+}
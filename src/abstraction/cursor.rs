@@ -0,0 +1,384 @@
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+
+use crate::{
+	Rl, ShmBackend,
+	abstraction::app::App,
+	dbug,
+	wayland::{
+		God, PixelFormat, WaytinierError,
+		buffer::{Buffer, BufferAccessor, BufferBackend},
+		cursor_shape::CursorShape,
+		pointer::Pointer,
+		shm::SharedMemoryPool,
+		surface::Surface,
+	},
+};
+
+// the xcursor file format, as described in libXcursor's Xcursor.h
+const XCURSOR_MAGIC: u32 = 0x72756358;
+const XCURSOR_IMAGE_TYPE: u32 = 0xfffd0002;
+const XCURSOR_DEFAULT_PATH: &str =
+	"~/.local/share/icons:~/.icons:/usr/share/icons:/usr/share/pixmaps:/usr/X11R6/lib/X11/icons";
+// themes inheriting from themes inheriting from... stop somewhere
+const MAX_INHERIT_DEPTH: usize = 16;
+
+#[derive(Debug, Clone)]
+pub(crate) struct XcursorImage {
+	// the nominal size it was drawn for
+	pub(crate) size: u32,
+	pub(crate) w: u32,
+	pub(crate) h: u32,
+	pub(crate) xhot: u32,
+	pub(crate) yhot: u32,
+	// in milliseconds, only matters for animated cursors
+	pub(crate) delay: u32,
+	// premultiplied argb, the same layout as wl_shm's argb8888
+	pub(crate) pixels: Vec<u8>,
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(data.get(at..at.checked_add(4)?)?.try_into().ok()?))
+}
+
+// every frame of the nominal size closest to the one asked for
+pub(crate) fn parse_xcursor(data: &[u8], size: u32) -> Option<Vec<XcursorImage>> {
+	if read_u32(data, 0)? != XCURSOR_MAGIC {
+		return None;
+	}
+	let header = read_u32(data, 4)? as usize;
+	let ntoc = read_u32(data, 12)? as usize;
+	// type, subtype (the nominal size for images) and position of each chunk
+	let toc = (0..ntoc)
+		.map(|i| {
+			let at = header + i * 12;
+			Some((read_u32(data, at)?, read_u32(data, at + 4)?, read_u32(data, at + 8)? as usize))
+		})
+		.collect::<Option<Vec<_>>>()?;
+	let best = toc
+		.iter()
+		.filter(|(kind, ..)| *kind == XCURSOR_IMAGE_TYPE)
+		.map(|(_, nominal, _)| *nominal)
+		.min_by_key(|nominal| nominal.abs_diff(size))?;
+	toc.iter()
+		.filter(|(kind, nominal, _)| *kind == XCURSOR_IMAGE_TYPE && *nominal == best)
+		.map(|(_, nominal, position)| parse_image(data, *nominal, *position))
+		.collect()
+}
+
+fn parse_image(data: &[u8], size: u32, position: usize) -> Option<XcursorImage> {
+	let header = read_u32(data, position)? as usize;
+	if read_u32(data, position + 4)? != XCURSOR_IMAGE_TYPE {
+		return None;
+	}
+	let w = read_u32(data, position + 16)?;
+	let h = read_u32(data, position + 20)?;
+	// the format's own limit
+	if w == 0 || h == 0 || w > 0x7fff || h > 0x7fff {
+		return None;
+	}
+	let start = position + header;
+	let pixels = data.get(start..start + (w * h * 4) as usize)?.to_vec();
+	Some(XcursorImage {
+		size,
+		w,
+		h,
+		xhot: read_u32(data, position + 24)?.min(w - 1),
+		yhot: read_u32(data, position + 28)?.min(h - 1),
+		delay: read_u32(data, position + 32)?,
+		pixels,
+	})
+}
+
+// the names cursor themes used before they agreed on the css ones
+fn legacy_names(shape: CursorShape) -> &'static [&'static str] {
+	match shape {
+		CursorShape::Default => &["left_ptr", "arrow"],
+		CursorShape::ContextMenu => &["left_ptr"],
+		CursorShape::Help => &["question_arrow", "help"],
+		CursorShape::Pointer => &["hand2", "hand1", "hand"],
+		CursorShape::Progress => &["left_ptr_watch"],
+		CursorShape::Wait => &["watch"],
+		CursorShape::Cell => &["plus"],
+		CursorShape::Crosshair => &["cross", "tcross"],
+		CursorShape::Text => &["xterm", "ibeam"],
+		CursorShape::VerticalText => &["xterm"],
+		CursorShape::Alias => &["link"],
+		CursorShape::Copy => &["copy"],
+		CursorShape::Move | CursorShape::AllScroll => &["fleur"],
+		CursorShape::NoDrop | CursorShape::NotAllowed => &["crossed_circle", "circle"],
+		CursorShape::Grab => &["openhand", "hand1"],
+		CursorShape::Grabbing => &["closedhand", "fleur"],
+		CursorShape::EResize => &["right_side"],
+		CursorShape::NResize => &["top_side"],
+		CursorShape::NeResize => &["top_right_corner"],
+		CursorShape::NwResize => &["top_left_corner"],
+		CursorShape::SResize => &["bottom_side"],
+		CursorShape::SeResize => &["bottom_right_corner"],
+		CursorShape::SwResize => &["bottom_left_corner"],
+		CursorShape::WResize => &["left_side"],
+		CursorShape::EwResize | CursorShape::ColResize => &["sb_h_double_arrow", "h_double_arrow"],
+		CursorShape::NsResize | CursorShape::RowResize => &["sb_v_double_arrow", "v_double_arrow"],
+		CursorShape::NeswResize => &["fd_double_arrow"],
+		CursorShape::NwseResize => &["bd_double_arrow"],
+		CursorShape::ZoomIn => &["zoom-in"],
+		CursorShape::ZoomOut => &["zoom-out"],
+	}
+}
+
+// finds cursors the way libXcursor does: XCURSOR_THEME in every directory of
+// XCURSOR_PATH, then whatever its index.theme inherits from
+pub(crate) struct CursorTheme {
+	pub(crate) name: String,
+	// nominal size at scale 1
+	pub(crate) size: u32,
+	pub(crate) search_path: Vec<PathBuf>,
+	pub(crate) cache: HashMap<(CursorShape, u32), Option<Vec<XcursorImage>>>,
+}
+
+impl CursorTheme {
+	pub(crate) fn from_env() -> Self {
+		let name = std::env::var("XCURSOR_THEME").unwrap_or(String::from("default"));
+		let size = std::env::var("XCURSOR_SIZE").ok().and_then(|s| s.parse().ok()).unwrap_or(24);
+		let path = std::env::var("XCURSOR_PATH").unwrap_or(String::from(XCURSOR_DEFAULT_PATH));
+		let home = std::env::var("HOME").ok();
+		let search_path = path
+			.split(':')
+			.filter(|dir| !dir.is_empty())
+			.filter_map(|dir| match dir.strip_prefix("~/") {
+				Some(rest) => home.as_ref().map(|home| Path::new(home).join(rest)),
+				None => Some(PathBuf::from(dir)),
+			})
+			.collect();
+		Self {
+			name,
+			size,
+			search_path,
+			cache: HashMap::new(),
+		}
+	}
+
+	fn inherits(index: &Path) -> Vec<String> {
+		let Ok(contents) = fs::read_to_string(index) else {
+			return vec![];
+		};
+		let mut in_section = false;
+		for line in contents.lines() {
+			let line = line.trim();
+			if line.starts_with('[') {
+				in_section = line == "[Icon Theme]";
+				continue;
+			}
+			let Some((key, value)) = line.split_once('=') else {
+				continue;
+			};
+			if in_section && key.trim() == "Inherits" {
+				return value
+					.split([',', ';'])
+					.map(str::trim)
+					.filter(|theme| !theme.is_empty())
+					.map(String::from)
+					.collect();
+			}
+		}
+		vec![]
+	}
+
+	fn find_file(&self, theme: &str, name: &str, seen: &mut Vec<String>) -> Option<PathBuf> {
+		if seen.len() >= MAX_INHERIT_DEPTH || seen.iter().any(|s| s == theme) {
+			return None;
+		}
+		seen.push(String::from(theme));
+		for dir in &self.search_path {
+			let file = dir.join(theme).join("cursors").join(name);
+			if file.is_file() {
+				return Some(file);
+			}
+		}
+		for dir in &self.search_path {
+			for parent in Self::inherits(&dir.join(theme).join("index.theme")) {
+				if let Some(file) = self.find_file(&parent, name, seen) {
+					return Some(file);
+				}
+			}
+		}
+		None
+	}
+
+	// size is the nominal size wanted, already multiplied by the scale
+	pub(crate) fn load(&mut self, shape: CursorShape, size: u32) -> Option<&Vec<XcursorImage>> {
+		if !self.cache.contains_key(&(shape, size)) {
+			let images = std::iter::once(shape.name())
+				.chain(legacy_names(shape).iter().copied())
+				.find_map(|name| {
+					[self.name.as_str(), "default"].iter().find_map(|theme| {
+						let file = self.find_file(theme, name, &mut vec![])?;
+						parse_xcursor(&fs::read(file).ok()?, size)
+					})
+				});
+			self.cache.insert((shape, size), images);
+		}
+		self.cache.get(&(shape, size))?.as_ref()
+	}
+}
+
+struct LoadedCursor {
+	shape: CursorShape,
+	// the output scale it was loaded for
+	scale: u32,
+	// what the images were drawn for, not always the one asked for
+	buffer_scale: u32,
+	images: Vec<XcursorImage>,
+	buffers: Vec<Rl<Buffer>>,
+}
+
+// a cursor from the xcursor theme on its own surface and shm pool, for compositors
+// without wp_cursor_shape_v1
+pub(crate) struct ThemedCursor {
+	pub(crate) surface: Rl<Surface>,
+	pub(crate) backend: Rl<BufferBackend>,
+	pub(crate) pool: Rl<SharedMemoryPool>,
+	pub(crate) theme: CursorTheme,
+	current: Option<LoadedCursor>,
+	frame: usize,
+	frame_shown: Instant,
+}
+
+impl ThemedCursor {
+	pub(crate) fn new(app: &mut App) -> Result<Self, WaytinierError> {
+		let backend = ShmBackend::new(app)?;
+		let pool = match &*backend.borrow() {
+			BufferBackend::Shm(shm) => shm.pool.clone(),
+			_ => unreachable!(),
+		};
+		let surface =
+			Surface::new_registered_made(&mut app.god, &app.compositor, 1, 1, PixelFormat::Argb888);
+		Ok(Self {
+			surface,
+			backend,
+			pool,
+			theme: CursorTheme::from_env(),
+			current: None,
+			frame: 0,
+			frame_shown: Instant::now(),
+		})
+	}
+
+	// copies every frame into the pool, one wl_buffer each
+	fn upload(
+		&mut self,
+		god: &mut God,
+		images: &[XcursorImage],
+	) -> Result<Vec<Rl<Buffer>>, WaytinierError> {
+		let mut pool = self.pool.borrow_mut();
+		let mut buffers = vec![];
+		for image in images {
+			let buffer = pool.make_buffer(god, (image.w, image.h), &self.surface, &self.backend)?;
+			if let Some(BufferAccessor::ShmSlice(slice)) = &buffer.borrow().accessor {
+				unsafe { &mut **slice }.copy_from_slice(&image.pixels);
+			}
+			buffers.push(buffer);
+		}
+		Ok(buffers)
+	}
+
+	fn show(&mut self, god: &mut God) -> Result<(), WaytinierError> {
+		let Some(current) = &self.current else {
+			return Ok(());
+		};
+		let image = &current.images[self.frame];
+		let mut surf = self.surface.borrow_mut();
+		surf.set_buffer_scale(god, current.buffer_scale);
+		surf.attach_buffer_obj(god, current.buffers[self.frame].clone())?;
+		surf.damage_buffer(god, (0, 0), (image.w as i32, image.h as i32));
+		surf.commit(god);
+		self.frame_shown = Instant::now();
+		Ok(())
+	}
+
+	// false if the theme has nothing for the shape
+	pub(crate) fn set(
+		&mut self,
+		god: &mut God,
+		pointer: &Pointer,
+		shape: CursorShape,
+		scale: u32,
+	) -> Result<bool, WaytinierError> {
+		// without set_buffer_scale a bigger image would just show up bigger
+		let scale = if self.surface.borrow().version < 3 {
+			1
+		} else {
+			scale
+		};
+		let loaded = self.current.as_ref().is_some_and(|c| c.shape == shape && c.scale == scale);
+		let mut old = None;
+		if !loaded {
+			let size = self.theme.size * scale;
+			let Some(images) = self.theme.load(shape, size).cloned() else {
+				dbug!(format!("no cursor for {} in the theme", shape.name()));
+				return Ok(false);
+			};
+			let buffers = self.upload(god, &images)?;
+			old = self.current.replace(LoadedCursor {
+				shape,
+				scale,
+				buffer_scale: Self::buffer_scale(&images, self.theme.size),
+				images,
+				buffers,
+			});
+		}
+		self.frame = 0;
+		self.show(god)?;
+		// the surface shows the new buffer now, the old ones can go
+		if let Some(old) = old {
+			let mut pool = self.pool.borrow_mut();
+			for buffer in &old.buffers {
+				pool.destroy_buffer(god, buffer);
+			}
+		}
+		let Some(current) = &self.current else {
+			return Ok(false);
+		};
+		let image = &current.images[0];
+		// what the surface really got, 1 if the compositor can't scale
+		let buffer_scale = self.surface.borrow().scale;
+		pointer.set_cursor(
+			god,
+			self.surface.borrow().id,
+			(image.xhot / buffer_scale) as i32,
+			(image.yhot / buffer_scale) as i32,
+		);
+		Ok(true)
+	}
+
+	// the scale the chosen size was drawn for, compositors reject buffers that
+	// don't divide by it so anything odd stays at 1
+	fn buffer_scale(images: &[XcursorImage], size: u32) -> u32 {
+		let scale = (images[0].size / size.max(1)).max(1);
+		if images.iter().all(|i| i.w % scale == 0 && i.h % scale == 0) {
+			scale
+		} else {
+			1
+		}
+	}
+
+	// steps animated cursors along, called every App::work
+	pub(crate) fn animate(&mut self, god: &mut God) -> Result<(), WaytinierError> {
+		let Some(current) = &self.current else {
+			return Ok(());
+		};
+		if current.images.len() < 2 {
+			return Ok(());
+		}
+		let delay = Duration::from_millis(current.images[self.frame].delay as u64);
+		if self.frame_shown.elapsed() < delay {
+			return Ok(());
+		}
+		self.frame = (self.frame + 1) % current.images.len();
+		self.show(god)
+	}
+}
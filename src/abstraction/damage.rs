@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+
+// (x, y, w, h) in buffer pixels
+pub type Rect = (i32, i32, i32, i32);
+
+// more rects than this cost the compositor more than they save
+const MAX_RECTS: usize = 16;
+// a buffer older than this gets redrawn whole, more than the swapchain can ever need
+pub(crate) const MAX_AGE: usize = 4;
+
+fn clip((x, y, w, h): Rect, (bw, bh): (i32, i32)) -> Option<Rect> {
+	let (x0, y0) = (x.max(0), y.max(0));
+	let (x1, y1) = ((x + w).min(bw), (y + h).min(bh));
+	(x1 > x0 && y1 > y0).then_some((x0, y0, x1 - x0, y1 - y0))
+}
+
+fn bounds(a: Rect, b: Rect) -> Rect {
+	let (x0, y0) = (a.0.min(b.0), a.1.min(b.1));
+	let (x1, y1) = ((a.0 + a.2).max(b.0 + b.2), (a.1 + a.3).max(b.1 + b.3));
+	(x0, y0, x1 - x0, y1 - y0)
+}
+
+fn area(r: Rect) -> i64 {
+	r.2 as i64 * r.3 as i64
+}
+
+// worth joining if the bounding box wastes nothing over drawing both,
+// which covers overlapping and touching rects lined up along an edge
+fn joinable(a: Rect, b: Rect) -> bool {
+	let joined = bounds(a, b);
+	area(joined) <= area(a) + area(b)
+}
+
+// clips to the buffer and joins rects until none are worth joining anymore
+pub(crate) fn merge(rects: &[Rect], size: (i32, i32)) -> Vec<Rect> {
+	let mut merged: Vec<Rect> = rects.iter().filter_map(|r| clip(*r, size)).collect();
+	let mut changed = true;
+	while changed {
+		changed = false;
+		'outer: for i in 0..merged.len() {
+			for j in i + 1..merged.len() {
+				if joinable(merged[i], merged[j]) {
+					merged[i] = bounds(merged[i], merged[j]);
+					merged.swap_remove(j);
+					changed = true;
+					break 'outer;
+				}
+			}
+		}
+	}
+	if merged.len() > MAX_RECTS {
+		let all = merged.iter().copied().reduce(bounds);
+		merged = all.into_iter().collect();
+	}
+	merged
+}
+
+// what got drawn in each of the last few frames of a surface, newest at the back
+#[derive(Default)]
+pub(crate) struct DamageHistory {
+	pub(crate) frames: VecDeque<Vec<Rect>>,
+}
+
+impl DamageHistory {
+	pub(crate) fn push(&mut self, damage: Vec<Rect>) {
+		if self.frames.len() == MAX_AGE {
+			self.frames.pop_front();
+		}
+		self.frames.push_back(damage);
+	}
+
+	// what changed in the frames a buffer of this age missed, None if that's unknown
+	pub(crate) fn since(&self, age: usize, size: (i32, i32)) -> Option<Vec<Rect>> {
+		if age == 0 || age > self.frames.len() + 1 {
+			return None;
+		}
+		let missed: Vec<Rect> = self.frames.iter().rev().take(age - 1).flatten().copied().collect();
+		Some(merge(&missed, size))
+	}
+
+	pub(crate) fn clear(&mut self) {
+		self.frames.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clips_to_the_buffer() {
+		assert_eq!(merge(&[(-5, -5, 10, 10)], (20, 20)), [(0, 0, 5, 5)]);
+		assert_eq!(merge(&[(15, 10, 10, 20)], (20, 20)), [(15, 10, 5, 10)]);
+		// entirely outside or empty
+		assert_eq!(merge(&[(20, 0, 5, 5), (-5, 0, 5, 5), (3, 3, 0, 4)], (20, 20)), []);
+	}
+
+	#[test]
+	fn joins() {
+		// touching along an edge
+		assert_eq!(merge(&[(0, 0, 10, 10), (10, 0, 5, 10)], (50, 50)), [(0, 0, 15, 10)]);
+		assert_eq!(merge(&[(0, 0, 10, 10), (0, 10, 10, 3)], (50, 50)), [(0, 0, 10, 13)]);
+		// one inside the other
+		assert_eq!(merge(&[(0, 0, 10, 10), (2, 2, 3, 3)], (50, 50)), [(0, 0, 10, 10)]);
+		// overlapping, but the bounding box would draw more than both
+		let apart = [(0, 0, 10, 10), (5, 5, 10, 10)];
+		assert_eq!(merge(&apart, (50, 50)), apart);
+		// only touching at a corner
+		let corner = [(0, 0, 10, 10), (10, 10, 10, 10)];
+		assert_eq!(merge(&corner, (50, 50)), corner);
+	}
+
+	#[test]
+	fn collapses_too_many() {
+		let rects: Vec<Rect> = (0..MAX_RECTS as i32 + 1).map(|i| (i * 10, i * 10, 5, 5)).collect();
+		assert_eq!(merge(&rects[..MAX_RECTS], (200, 200)).len(), MAX_RECTS);
+		assert_eq!(merge(&rects, (200, 200)), [(0, 0, 165, 165)]);
+	}
+
+	#[test]
+	fn since() {
+		let mut history = DamageHistory::default();
+		history.push(vec![(0, 0, 1, 1)]);
+		history.push(vec![(4, 4, 1, 1)]);
+		// never drawn into, or older than what's remembered
+		assert_eq!(history.since(0, (10, 10)), None);
+		assert_eq!(history.since(4, (10, 10)), None);
+		assert_eq!(history.since(1, (10, 10)), Some(vec![]));
+		assert_eq!(history.since(2, (10, 10)), Some(vec![(4, 4, 1, 1)]));
+		assert_eq!(history.since(3, (10, 10)), Some(vec![(4, 4, 1, 1), (0, 0, 1, 1)]));
+		for _ in 0..MAX_AGE {
+			history.push(vec![]);
+		}
+		assert_eq!(history.frames.len(), MAX_AGE);
+		assert_eq!(history.since(MAX_AGE + 2, (10, 10)), None);
+	}
+}
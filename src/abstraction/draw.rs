@@ -0,0 +1,521 @@
+pub use crate::abstraction::damage::Rect;
+use crate::{
+	abstraction::{image::Image, pixels::PixelBuffer},
+	wayland::PixelFormat,
+};
+
+// straight (not premultiplied) alpha, like css colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Color {
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+	pub a: u8,
+}
+
+impl Color {
+	pub const BLACK: Self = Self::rgb(0, 0, 0);
+	pub const WHITE: Self = Self::rgb(255, 255, 255);
+	pub const TRANSPARENT: Self = Self::rgba(0, 0, 0, 0);
+
+	pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+		Self::rgba(r, g, b, 255)
+	}
+
+	pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+		Self {
+			r,
+			g,
+			b,
+			a,
+		}
+	}
+
+	// 0xAARRGGBB
+	pub const fn from_argb(argb: u32) -> Self {
+		let [b, g, r, a] = argb.to_le_bytes();
+		Self::rgba(r, g, b, a)
+	}
+
+	pub(crate) fn premultiplied(&self) -> [f32; 4] {
+		let a = self.a as f32 / 255.0;
+		[self.r as f32 / 255.0 * a, self.g as f32 / 255.0 * a, self.b as f32 / 255.0 * a, a]
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+	#[default]
+	Nearest,
+	Bilinear,
+}
+
+pub(crate) fn to_u8(c: f32) -> u8 {
+	(c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn to_bits(c: f32, bits: u32) -> u32 {
+	let max = ((1 << bits) - 1) as f32;
+	(c.clamp(0.0, 1.0) * max).round() as u32
+}
+
+fn from_bits(v: u32, bits: u32) -> f32 {
+	v as f32 / ((1 << bits) - 1) as f32
+}
+
+fn f16_to_f32(h: u16) -> f32 {
+	let exp = ((h >> 10) & 0x1f) as i32;
+	let frac = (h & 0x3ff) as f32;
+	let mag = match exp {
+		0 => frac * 2f32.powi(-24),
+		31 => f32::INFINITY,
+		e => (1.0 + frac / 1024.0) * 2f32.powi(e - 15),
+	};
+	if h & 0x8000 != 0 {
+		-mag
+	} else {
+		mag
+	}
+}
+
+// only ever sees colors, so no negatives, nans or infinities
+fn f32_to_f16(v: f32) -> u16 {
+	let v = v.clamp(0.0, 65504.0);
+	if v < 2f32.powi(-14) {
+		return (v * 2f32.powi(24)).round() as u16;
+	}
+	// straight from the bits, log2 can round up to the next power just below one
+	let exp = ((v.to_bits() >> 23) & 0xff) as i32 - 127;
+	let frac = ((v / 2f32.powi(exp) - 1.0) * 1024.0).round() as u16;
+	// a rounded up fraction carries into the exponent, which is what adding does
+	(((exp + 15) as u16) << 10) + frac
+}
+
+// premultiplied rgba out of one pixel's bytes
+pub(crate) fn load(pf: PixelFormat, px: &[u8]) -> [f32; 4] {
+	let word = |n: usize| px[..n].iter().rev().fold(0u64, |acc, b| acc << 8 | *b as u64);
+	let c = |v: u8| v as f32 / 255.0;
+	match pf {
+		PixelFormat::Argb888 => [c(px[2]), c(px[1]), c(px[0]), c(px[3])],
+		PixelFormat::Xrgb888 => [c(px[2]), c(px[1]), c(px[0]), 1.0],
+		PixelFormat::Abgr8888 => [c(px[0]), c(px[1]), c(px[2]), c(px[3])],
+		PixelFormat::Xbgr8888 => [c(px[0]), c(px[1]), c(px[2]), 1.0],
+		PixelFormat::Rgb565 => {
+			let v = word(2) as u32;
+			[from_bits(v >> 11, 5), from_bits(v >> 5 & 0x3f, 6), from_bits(v & 0x1f, 5), 1.0]
+		}
+		PixelFormat::Argb2101010 | PixelFormat::Xrgb2101010 => {
+			let v = word(4) as u32;
+			let a = match pf {
+				PixelFormat::Argb2101010 => from_bits(v >> 30, 2),
+				_ => 1.0,
+			};
+			[
+				from_bits(v >> 20 & 0x3ff, 10),
+				from_bits(v >> 10 & 0x3ff, 10),
+				from_bits(v & 0x3ff, 10),
+				a,
+			]
+		}
+		PixelFormat::Abgr16161616f => {
+			let v = word(8);
+			let h = |shift: u32| f16_to_f32((v >> shift) as u16);
+			[h(0), h(16), h(32), h(48)]
+		}
+		// little endian, so red is the last byte
+		PixelFormat::Rgb888 => [c(px[2]), c(px[1]), c(px[0]), 1.0],
+		PixelFormat::Bgr888 => [c(px[0]), c(px[1]), c(px[2]), 1.0],
+	}
+}
+
+fn store(pf: PixelFormat, px: &mut [u8], [r, g, b, a]: [f32; 4]) {
+	let mut put = |v: u64, n: usize| px[..n].copy_from_slice(&v.to_le_bytes()[..n]);
+	match pf {
+		PixelFormat::Argb888 | PixelFormat::Xrgb888 => {
+			let a = match pf {
+				PixelFormat::Argb888 => to_u8(a),
+				_ => 255,
+			};
+			put(u32::from_le_bytes([to_u8(b), to_u8(g), to_u8(r), a]) as u64, 4)
+		}
+		PixelFormat::Abgr8888 | PixelFormat::Xbgr8888 => {
+			let a = match pf {
+				PixelFormat::Abgr8888 => to_u8(a),
+				_ => 255,
+			};
+			put(u32::from_le_bytes([to_u8(r), to_u8(g), to_u8(b), a]) as u64, 4)
+		}
+		PixelFormat::Rgb565 => {
+			put((to_bits(r, 5) << 11 | to_bits(g, 6) << 5 | to_bits(b, 5)) as u64, 2)
+		}
+		PixelFormat::Argb2101010 | PixelFormat::Xrgb2101010 => {
+			let a = match pf {
+				PixelFormat::Argb2101010 => to_bits(a, 2),
+				_ => 3,
+			};
+			put((a << 30 | to_bits(r, 10) << 20 | to_bits(g, 10) << 10 | to_bits(b, 10)) as u64, 4)
+		}
+		PixelFormat::Abgr16161616f => {
+			let h = |c: f32| f32_to_f16(c) as u64;
+			put(h(a) << 48 | h(b) << 32 | h(g) << 16 | h(r), 8)
+		}
+		PixelFormat::Rgb888 => put(u32::from_le_bytes([to_u8(b), to_u8(g), to_u8(r), 0]) as u64, 3),
+		PixelFormat::Bgr888 => put(u32::from_le_bytes([to_u8(r), to_u8(g), to_u8(b), 0]) as u64, 3),
+	}
+}
+
+// sub-scanlines per pixel row when filling polygons
+const POLY_SAMPLES: usize = 4;
+
+// draws into a frame, whatever its format. every color gets blended over what's there
+pub struct Painter<'a> {
+	buf: PixelBuffer<'a>,
+	// x0, y0, x1, y1, always within the buffer
+	clip: (i32, i32, i32, i32),
+}
+
+impl<'a> Painter<'a> {
+	pub fn new(buf: PixelBuffer<'a>) -> Self {
+		let clip = (0, 0, buf.width() as i32, buf.height() as i32);
+		Self {
+			buf,
+			clip,
+		}
+	}
+
+	pub fn width(&self) -> i32 {
+		self.buf.width() as i32
+	}
+
+	pub fn height(&self) -> i32 {
+		self.buf.height() as i32
+	}
+
+	// nothing outside of it gets touched, None goes back to the whole buffer
+	pub fn set_clip(&mut self, clip: Option<Rect>) {
+		let (w, h) = (self.width(), self.height());
+		self.clip = match clip {
+			Some((x, y, cw, ch)) => {
+				let (x0, y0) = (x.clamp(0, w), y.clamp(0, h));
+				(x0, y0, (x + cw).clamp(x0, w), (y + ch).clamp(y0, h))
+			}
+			None => (0, 0, w, h),
+		};
+	}
+
+	// the clip rectangle cut down to the pixels a shape could touch
+	fn bounds(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> (i32, i32, i32, i32) {
+		let (cx0, cy0, cx1, cy1) = self.clip;
+		(
+			(x0.floor() as i32).clamp(cx0, cx1),
+			(y0.floor() as i32).clamp(cy0, cy1),
+			(x1.ceil() as i32).clamp(cx0, cx1),
+			(y1.ceil() as i32).clamp(cy0, cy1),
+		)
+	}
+
+	// source over, with src premultiplied and scaled by coverage. x and y have to be in the clip
+	fn blend(&mut self, x: i32, y: i32, src: [f32; 4], coverage: f32) {
+		if coverage <= 0.0 || src[3] <= 0.0 && src[..3].iter().all(|c| *c <= 0.0) {
+			return;
+		}
+		let pf = self.buf.format();
+		let bpp = pf.width() as usize;
+		let Some(row) = self.buf.row(y as u32) else {
+			return;
+		};
+		let px = &mut row[x as usize * bpp..(x as usize + 1) * bpp];
+		let coverage = coverage.min(1.0);
+		let src = src.map(|c| c * coverage);
+		let out = if src[3] >= 1.0 {
+			src
+		} else {
+			let dst = load(pf, px);
+			[0, 1, 2, 3].map(|i| src[i] + dst[i] * (1.0 - src[3]))
+		};
+		store(pf, px, out);
+	}
+
+	// every pixel, ignoring the clip and without blending
+	pub fn clear(&mut self, color: Color) {
+		let pf = self.buf.format();
+		let bpp = pf.width() as usize;
+		let mut px = vec![0; bpp];
+		store(pf, &mut px, color.premultiplied());
+		for row in self.buf.rows() {
+			row.chunks_exact_mut(bpp).for_each(|p| p.copy_from_slice(&px));
+		}
+	}
+
+	pub fn fill_rect(&mut self, (x, y, w, h): Rect, color: Color) {
+		let src = color.premultiplied();
+		let (x0, y0, x1, y1) = self.bounds(x as f32, y as f32, (x + w) as f32, (y + h) as f32);
+		for py in y0..y1 {
+			for px in x0..x1 {
+				self.blend(px, py, src, 1.0);
+			}
+		}
+	}
+
+	// the border grows inwards
+	pub fn stroke_rect(&mut self, (x, y, w, h): Rect, width: i32, color: Color) {
+		let width = width.min(w / 2).min(h / 2).max(1);
+		self.fill_rect((x, y, w, width), color);
+		self.fill_rect((x, y + h - width, w, width), color);
+		self.fill_rect((x, y + width, width, h - 2 * width), color);
+		self.fill_rect((x + w - width, y + width, width, h - 2 * width), color);
+	}
+
+	pub fn fill_rounded_rect(&mut self, (x, y, w, h): Rect, radius: f32, color: Color) {
+		let src = color.premultiplied();
+		let (hw, hh) = (w as f32 / 2.0, h as f32 / 2.0);
+		let (cx, cy) = (x as f32 + hw, y as f32 + hh);
+		let radius = radius.clamp(0.0, hw.min(hh));
+		let (x0, y0, x1, y1) = self.bounds(x as f32, y as f32, (x + w) as f32, (y + h) as f32);
+		for py in y0..y1 {
+			for px in x0..x1 {
+				// signed distance to the rounded box from the pixel center
+				let qx = (px as f32 + 0.5 - cx).abs() - (hw - radius);
+				let qy = (py as f32 + 0.5 - cy).abs() - (hh - radius);
+				let outside = qx.max(0.0).hypot(qy.max(0.0));
+				let dist = outside + qx.max(qy).min(0.0) - radius;
+				self.blend(px, py, src, (0.5 - dist).clamp(0.0, 1.0));
+			}
+		}
+	}
+
+	// anti-aliased, with round caps
+	pub fn line(&mut self, (ax, ay): (f32, f32), (bx, by): (f32, f32), width: f32, color: Color) {
+		let src = color.premultiplied();
+		let half = width / 2.0;
+		let (x0, y0, x1, y1) = self.bounds(
+			ax.min(bx) - half - 1.0,
+			ay.min(by) - half - 1.0,
+			ax.max(bx) + half + 1.0,
+			ay.max(by) + half + 1.0,
+		);
+		let (dx, dy) = (bx - ax, by - ay);
+		let len2 = dx * dx + dy * dy;
+		for py in y0..y1 {
+			for px in x0..x1 {
+				let (cx, cy) = (px as f32 + 0.5, py as f32 + 0.5);
+				let t = match len2 {
+					0.0 => 0.0,
+					_ => (((cx - ax) * dx + (cy - ay) * dy) / len2).clamp(0.0, 1.0),
+				};
+				let dist = (cx - ax - t * dx).hypot(cy - ay - t * dy);
+				self.blend(px, py, src, (half + 0.5 - dist).clamp(0.0, 1.0));
+			}
+		}
+	}
+
+	pub fn fill_circle(&mut self, (cx, cy): (f32, f32), radius: f32, color: Color) {
+		self.circle((cx, cy), radius, None, color);
+	}
+
+	// the stroke is centered on the radius
+	pub fn stroke_circle(&mut self, (cx, cy): (f32, f32), radius: f32, width: f32, color: Color) {
+		self.circle((cx, cy), radius, Some(width), color);
+	}
+
+	fn circle(&mut self, (cx, cy): (f32, f32), radius: f32, stroke: Option<f32>, color: Color) {
+		let src = color.premultiplied();
+		let reach = radius + stroke.unwrap_or(0.0) / 2.0 + 1.0;
+		let (x0, y0, x1, y1) = self.bounds(cx - reach, cy - reach, cx + reach, cy + reach);
+		for py in y0..y1 {
+			for px in x0..x1 {
+				let dist = (px as f32 + 0.5 - cx).hypot(py as f32 + 0.5 - cy);
+				let coverage = match stroke {
+					Some(width) => width / 2.0 + 0.5 - (dist - radius).abs(),
+					None => radius + 0.5 - dist,
+				};
+				self.blend(px, py, src, coverage.clamp(0.0, 1.0));
+			}
+		}
+	}
+
+	// non-zero winding, the last point connects back to the first
+	pub fn fill_polygon(&mut self, points: &[(f32, f32)], color: Color) {
+		self.fill_path(&[points], color);
+	}
+
+	// several closed contours filled as one shape, so holes wound the other way stay empty
+	pub fn fill_path<P: AsRef<[(f32, f32)]>>(&mut self, contours: &[P], color: Color) {
+		let src = color.premultiplied();
+		let edges: Vec<_> = contours
+			.iter()
+			.map(AsRef::as_ref)
+			.filter(|points| points.len() >= 3)
+			.flat_map(|points| points.iter().zip(points.iter().cycle().skip(1)))
+			.collect();
+		if edges.is_empty() {
+			return;
+		}
+		let (min_x, max_x) =
+			edges.iter().fold((f32::MAX, f32::MIN), |(lo, hi), (p, _)| (lo.min(p.0), hi.max(p.0)));
+		let (min_y, max_y) =
+			edges.iter().fold((f32::MAX, f32::MIN), |(lo, hi), (p, _)| (lo.min(p.1), hi.max(p.1)));
+		let (x0, y0, x1, y1) = self.bounds(min_x, min_y, max_x, max_y);
+		if x1 <= x0 {
+			return;
+		}
+		let mut coverage = vec![0.0f32; (x1 - x0) as usize];
+		let mut crossings: Vec<(f32, i32)> = vec![];
+		for py in y0..y1 {
+			coverage.fill(0.0);
+			for sample in 0..POLY_SAMPLES {
+				let sy = py as f32 + (sample as f32 + 0.5) / POLY_SAMPLES as f32;
+				crossings.clear();
+				for ((ax, ay), (bx, by)) in &edges {
+					// half open so shared vertices count once
+					let (dir, top, bottom) = if ay <= by {
+						(1, ay, by)
+					} else {
+						(-1, by, ay)
+					};
+					if sy < *top || sy >= *bottom {
+						continue;
+					}
+					crossings.push((ax + (sy - ay) / (by - ay) * (bx - ax), dir));
+				}
+				crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+				let mut winding = 0;
+				for pair in crossings.windows(2) {
+					winding += pair[0].1;
+					if winding == 0 {
+						continue;
+					}
+					let (start, end) = (pair[0].0.max(x0 as f32), pair[1].0.min(x1 as f32));
+					let mut x = start;
+					while x < end {
+						let cell = x.floor();
+						let next = (cell + 1.0).min(end);
+						coverage[(cell as i32 - x0) as usize] += (next - x) / POLY_SAMPLES as f32;
+						x = next;
+					}
+				}
+			}
+			for (i, c) in coverage.iter().enumerate() {
+				self.blend(x0 + i as i32, py, src, *c);
+			}
+		}
+	}
+
+	// scales image onto the destination rectangle
+	pub fn blit(&mut self, image: &Image, (x, y, w, h): Rect, filter: Filter) {
+		if image.w == 0 || image.h == 0 || w <= 0 || h <= 0 {
+			return;
+		}
+		let (sx, sy) = (image.w as f32 / w as f32, image.h as f32 / h as f32);
+		let (x0, y0, x1, y1) = self.bounds(x as f32, y as f32, (x + w) as f32, (y + h) as f32);
+		for py in y0..y1 {
+			for px in x0..x1 {
+				// source position of the pixel center
+				let u = (px - x) as f32 * sx + sx / 2.0 - 0.5;
+				let v = (py - y) as f32 * sy + sy / 2.0 - 0.5;
+				let src = match filter {
+					Filter::Nearest => image.sample(u.round() as i64, v.round() as i64),
+					Filter::Bilinear => {
+						let (fu, fv) = (u.floor(), v.floor());
+						let (tu, tv) = (u - fu, v - fv);
+						let (iu, iv) = (fu as i64, fv as i64);
+						let [a, b, c, d] = [
+							image.sample(iu, iv),
+							image.sample(iu + 1, iv),
+							image.sample(iu, iv + 1),
+							image.sample(iu + 1, iv + 1),
+						];
+						[0, 1, 2, 3].map(|i| {
+							let top = a[i] + (b[i] - a[i]) * tu;
+							let bottom = c[i] + (d[i] - c[i]) * tu;
+							top + (bottom - top) * tv
+						})
+					}
+				};
+				self.blend(px, py, src, 1.0);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn f16_round_trip() {
+		for h in 0..0x7c00 {
+			assert_eq!(f32_to_f16(f16_to_f32(h)), h, "{h:#x}");
+		}
+		for exp in -14..=15 {
+			assert_eq!(f32_to_f16(2f32.powi(exp)), ((exp + 15) as u16) << 10);
+			// just below a power of two still rounds up to it
+			let below = f32::from_bits(2f32.powi(exp).to_bits() - 1);
+			assert_eq!(f32_to_f16(below), ((exp + 15) as u16) << 10);
+		}
+		// around the smallest normal number
+		assert_eq!(f32_to_f16(2f32.powi(-14) - 2f32.powi(-24)), 0x3ff);
+		assert_eq!(f32_to_f16(2f32.powi(-14) - 2f32.powi(-26)), 0x400);
+		assert_eq!(f32_to_f16(2f32.powi(-24)), 1);
+		assert_eq!(f32_to_f16(1.0 + 1.0 / 1024.0), 0x3c01);
+		assert_eq!(f32_to_f16(70000.0), 0x7bff);
+	}
+
+	#[test]
+	fn load_store_round_trip() {
+		let colors = [[0.0, 0.0, 0.0, 0.0], [0.2, 0.4, 0.6, 1.0], [0.1, 0.2, 0.3, 2.0 / 3.0]];
+		for pf in PixelFormat::ALL {
+			let tolerance = match pf {
+				PixelFormat::Rgb565 => 0.5 / 31.0,
+				_ => 0.5 / 255.0,
+			};
+			for color in colors {
+				let mut px = [0; 8];
+				store(pf, &mut px, color);
+				let loaded = load(pf, &px);
+				let alpha = if pf.has_alpha() {
+					color[3]
+				} else {
+					1.0
+				};
+				let expected = [color[0], color[1], color[2], alpha];
+				for (l, e) in loaded.iter().zip(expected) {
+					assert!((l - e).abs() <= tolerance, "{pf:?}: {loaded:?} vs {expected:?}");
+				}
+			}
+		}
+	}
+
+	// the alpha of every pixel of an 8 by 8 argb buffer the contours were filled into
+	fn coverage(contours: &[Vec<(f32, f32)>]) -> Vec<u8> {
+		let mut data = vec![0; 8 * 8 * 4];
+		let mut painter = Painter::new(PixelBuffer::new(&mut data, 8, 8, 32, PixelFormat::Argb888));
+		painter.fill_path(contours, Color::WHITE);
+		data.chunks(4).map(|px| px[3]).collect()
+	}
+
+	#[test]
+	fn polygon_coverage() {
+		let square = coverage(&[vec![(1.5, 1.5), (4.5, 1.5), (4.5, 4.5), (1.5, 4.5)]]);
+		assert_eq!(square[2 * 8 + 2], 255);
+		assert_eq!(square[2 * 8 + 1], 128);
+		assert_eq!(square[4 * 8 + 3], 128);
+		assert_eq!(square[8 + 1], 64);
+		assert_eq!(square[5 * 8 + 5], 0);
+		// 3 by 3 pixels worth, the halves and quarters rounded up
+		assert_eq!(square.iter().map(|a| *a as u32).sum::<u32>(), 4 * 255 + 8 * 128 + 4 * 64);
+
+		let outer = vec![(0.0, 0.0), (8.0, 0.0), (8.0, 8.0), (0.0, 8.0)];
+		let hole = vec![(2.0, 2.0), (2.0, 6.0), (6.0, 6.0), (6.0, 2.0)];
+		let ring = coverage(&[outer.clone(), hole.clone()]);
+		assert_eq!(ring[8 + 1], 255);
+		assert_eq!(ring[4 * 8 + 4], 0);
+		assert_eq!(ring[7 * 8 + 7], 255);
+		assert_eq!(ring.iter().filter(|a| **a == 255).count(), 64 - 16);
+		// wound the same way it adds up instead of cutting out
+		let mut inner = hole;
+		inner.reverse();
+		let filled = coverage(&[outer, inner]);
+		assert!(filled.iter().all(|a| *a == 255));
+	}
+}
@@ -0,0 +1,385 @@
+use std::{collections::BTreeMap, fs, path::PathBuf, rc::Rc};
+
+use crate::{
+	Rl,
+	abstraction::{
+		app::{App, Snapshot},
+		image::Image,
+		pixels::PixelBuffer,
+	},
+	rl,
+	wayland::{
+		ExpectRc, Id, PixelFormat, WaytinierError,
+		buffer::{Buffer, BufferAccessor, BufferBackend},
+		surface::Surface,
+		xdg_shell::toplevel::WindowState,
+	},
+};
+
+// buffers in plain memory, for rendering without a compositor through Headless.
+// a real App can't show them and errors out when it tries to make one
+pub struct MemoryBackend {
+	// how many buffers a surface may cycle through, like a swapchain on screen
+	pub(crate) buffers: usize,
+}
+
+impl MemoryBackend {
+	#[allow(clippy::new_ret_no_self)]
+	pub fn new(buffers: usize) -> Rl<BufferBackend> {
+		rl!(BufferBackend::Memory(MemoryBackend {
+			buffers: buffers.max(1),
+		}))
+	}
+
+	fn accessor(pf: PixelFormat, w: u32, h: u32) -> BufferAccessor {
+		let len = pf.stride(w) as usize * h as usize;
+		BufferAccessor::Memory(vec![0; len.div_ceil(8)])
+	}
+
+	pub(crate) fn make_buffer(
+		&mut self,
+		w: u32,
+		h: u32,
+		surface: &Rl<Surface>,
+		backend: &Rl<BufferBackend>,
+	) -> Result<Rl<Buffer>, WaytinierError> {
+		let accessor = Self::accessor(surface.borrow().pf, w, h);
+		Ok(Buffer::new(Id(0), (0, w, h), surface, backend, Some(accessor)))
+	}
+
+	// a resized buffer is blank, same as a new one
+	pub(crate) fn resize(
+		&mut self,
+		buf: &Rl<Buffer>,
+		w: u32,
+		h: u32,
+	) -> Result<(), WaytinierError> {
+		let mut buffer = buf.borrow_mut();
+		let pf = buffer.master.upgrade().to_wl_err()?.borrow().pf;
+		buffer.w = w;
+		buffer.h = h;
+		buffer.accessor = Some(Self::accessor(pf, w, h));
+		buffer.drawn_at = None;
+		Ok(())
+	}
+}
+
+// window id, frame number and what got drawn
+pub type FrameCallback = Box<dyn FnMut(usize, usize, &Image)>;
+
+// where each frame goes once it's drawn
+pub enum FrameSink {
+	// nowhere, for when the render closure checks things itself
+	Discard,
+	// one <window>-<frame>.ppm per frame into this directory
+	Ppm(PathBuf),
+	Png(PathBuf),
+	Callback(FrameCallback),
+}
+
+struct HeadlessWindow {
+	// never registered, nothing is sent anywhere. the scale is always fractional
+	surface: Rl<Surface>,
+	window_state: WindowState,
+}
+
+// what a configure from a compositor does: the new size, old damage dropped and every
+// buffer resized to match
+fn resize(surface: &Rl<Surface>, (w, h): (u32, u32), scale: u32) -> Result<(), WaytinierError> {
+	let (bufs, (bw, bh)) = {
+		let mut surf = surface.borrow_mut();
+		(surf.w, surf.h, surf.fractional_scale) = (w.max(1), h.max(1), Some(scale));
+		surf.damage_history.clear();
+		(surf.swapchain.clone(), surf.buffer_size())
+	};
+	for buf in &bufs {
+		let backend = buf.borrow().backend.clone();
+		let BufferBackend::Memory(memory) = &mut *backend.borrow_mut() else {
+			return Err(WaytinierError::InvalidEnumVariant("BufferBackend"));
+		};
+		memory.resize(buf, bw, bh)?;
+	}
+	Ok(())
+}
+
+// attaches a buffer the pretend compositor is done with, adding one to the swapchain
+// if it has room. with no room left the attached one gets drawn over
+fn acquire(backend: &Rl<BufferBackend>, surface: &Rl<Surface>) -> Result<(), WaytinierError> {
+	let released = surface.borrow().swapchain.iter().find(|b| !b.borrow().in_use).cloned();
+	if let Some(buf) = released {
+		surface.borrow_mut().attached_buf = Some(buf);
+		return Ok(());
+	}
+	let BufferBackend::Memory(memory) = &mut *backend.borrow_mut() else {
+		return Err(WaytinierError::InvalidEnumVariant("BufferBackend"));
+	};
+	if surface.borrow().swapchain.len() >= memory.buffers {
+		return Ok(());
+	}
+	let (w, h) = surface.borrow().buffer_size();
+	let buf = memory.make_buffer(w, h, surface, backend)?;
+	let mut surf = surface.borrow_mut();
+	surf.swapchain.push(buf.clone());
+	surf.attached_buf = Some(buf);
+	Ok(())
+}
+
+// an App without a compositor: windows are only memory, every work() is a frame
+// callback for all of them, and what they draw goes to the sink
+pub struct Headless {
+	windows: BTreeMap<usize, HeadlessWindow>,
+	next_id: usize,
+	backend: Rl<BufferBackend>,
+	sink: FrameSink,
+	frame: usize,
+	frame_limit: Option<usize>,
+	pub finished: bool,
+}
+
+impl Default for Headless {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Headless {
+	// double buffered and discarding frames until told otherwise
+	pub fn new() -> Self {
+		Self {
+			windows: BTreeMap::new(),
+			next_id: 0,
+			backend: MemoryBackend::new(2),
+			sink: FrameSink::Discard,
+			frame: 0,
+			frame_limit: None,
+			finished: false,
+		}
+	}
+
+	// anything but a MemoryBackend gets turned down
+	pub fn set_backend(&mut self, backend: &Rl<BufferBackend>) -> Result<(), WaytinierError> {
+		if !matches!(&*backend.borrow(), BufferBackend::Memory(_)) {
+			return Err(WaytinierError::InvalidEnumVariant("BufferBackend"));
+		}
+		self.backend = backend.clone();
+		for window in self.windows.values() {
+			let mut surf = window.surface.borrow_mut();
+			surf.swapchain.clear();
+			surf.attached_buf = None;
+			surf.damage_history.clear();
+		}
+		Ok(())
+	}
+
+	pub fn set_sink(&mut self, sink: FrameSink) {
+		self.sink = sink;
+	}
+
+	// finished after this many frames, None to go on until stopped
+	pub fn set_frame_limit(&mut self, frames: Option<usize>) {
+		self.frame_limit = frames;
+	}
+
+	// a window that's configured right away, returns its id
+	pub fn add_window(&mut self, w: u32, h: u32, pf: PixelFormat) -> usize {
+		let id = self.next_id;
+		self.next_id += 1;
+		let pf = self.backend.borrow().choose_format(pf);
+		let surface = Surface::new(Id(0), w.max(1), h.max(1), pf);
+		surface.borrow_mut().fractional_scale = Some(120);
+		self.windows.insert(
+			id,
+			HeadlessWindow {
+				surface,
+				window_state: WindowState::default(),
+			},
+		);
+		id
+	}
+
+	// finished once the last one is gone
+	pub fn close_window(&mut self, id: usize) {
+		self.windows.remove(&id);
+	}
+
+	// what a compositor resizing the window would send, seen from the next frame on
+	pub fn configure(&mut self, id: usize, w: u32, h: u32) -> Result<(), WaytinierError> {
+		let Some(window) = self.windows.get(&id) else {
+			return Ok(());
+		};
+		let scale = window.surface.borrow().fractional_scale.unwrap_or(120);
+		resize(&window.surface, (w, h), scale)
+	}
+
+	pub fn set_window_state(&mut self, id: usize, state: WindowState) {
+		if let Some(window) = self.windows.get_mut(&id) {
+			window.window_state = state;
+		}
+	}
+
+	// fractional scales like 1.25 work too
+	pub fn set_scale(&mut self, id: usize, scale: f64) -> Result<(), WaytinierError> {
+		let Some(window) = self.windows.get(&id) else {
+			return Ok(());
+		};
+		let size = {
+			let surf = window.surface.borrow();
+			(surf.w, surf.h)
+		};
+		resize(&window.surface, size, ((scale * 120.0).round() as u32).max(1))
+	}
+
+	// one frame for every window, true once finished
+	pub fn work<F, S>(&mut self, state: &mut S, mut render_fun: F) -> Result<bool, WaytinierError>
+	where
+		F: FnMut(&mut S, Snapshot),
+	{
+		if self.finished {
+			return Ok(true);
+		}
+		for (id, window) in &self.windows {
+			acquire(&self.backend, &window.surface)?;
+			let mut surf = window.surface.borrow_mut();
+			let frame = self.frame;
+			let state_now = Some(window.window_state);
+			App::draw_frame(&mut surf, frame, *id, state_now, None, |snapshot| {
+				render_fun(state, snapshot)
+			})?;
+			let shown = surf
+				.attached_buf
+				.clone()
+				.ok_or(WaytinierError::ExpectedSomeValue("no buffer attached to surface"))?;
+			// held until the next frame replaces it, like a compositor would
+			for buf in &surf.swapchain {
+				buf.borrow_mut().in_use = Rc::ptr_eq(buf, &shown);
+			}
+			let ((w, h), pf) = (surf.buffer_size(), surf.pf);
+			drop(surf);
+
+			if matches!(self.sink, FrameSink::Discard) {
+				continue;
+			}
+			let mut buf = shown.borrow_mut();
+			let Some(data) = buf.accessor.as_mut().and_then(BufferAccessor::bytes) else {
+				continue;
+			};
+			let image = Image::from_pixels(&mut PixelBuffer::new(data, w, h, pf.stride(w), pf));
+			match &mut self.sink {
+				FrameSink::Discard => {}
+				FrameSink::Ppm(dir) => {
+					fs::write(dir.join(format!("{id}-{frame:05}.ppm")), image.encode_ppm())?
+				}
+				FrameSink::Png(dir) => {
+					fs::write(dir.join(format!("{id}-{frame:05}.png")), image.encode_png())?
+				}
+				FrameSink::Callback(cb) => cb(*id, frame, &image),
+			}
+		}
+		self.frame += 1;
+		if self.windows.is_empty() || self.frame_limit.is_some_and(|limit| self.frame >= limit) {
+			self.finished = true;
+		}
+		Ok(self.finished)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::RefCell;
+
+	use super::*;
+	use crate::abstraction::{damage::Rect, draw::Color};
+
+	#[test]
+	fn frames_reach_the_callback() {
+		let frames = Rc::new(RefCell::new(vec![]));
+		let mut headless = Headless::new();
+		let argb = headless.add_window(4, 3, PixelFormat::Argb888);
+		let rgb565 = headless.add_window(2, 1, PixelFormat::Rgb565);
+		let sink = frames.clone();
+		headless.set_sink(FrameSink::Callback(Box::new(move |id, frame, image| {
+			sink.borrow_mut().push((id, frame, image.clone()))
+		})));
+		headless.set_frame_limit(Some(1));
+		let finished = headless
+			.work(&mut (), |_, mut snapshot| {
+				let mut painter = snapshot.painter().unwrap();
+				painter.clear(Color::rgb(255, 0, 0));
+				painter.fill_rect((1, 1, 2, 1), Color::rgb(0, 0, 255));
+			})
+			.unwrap();
+		assert!(finished);
+		let frames = frames.borrow();
+		assert_eq!(frames.len(), 2);
+		let (id, frame, image) = &frames[0];
+		assert_eq!((*id, *frame, image.w, image.h), (argb, 0, 4, 3));
+		let (red, blue) = (0xffff0000, 0xff0000ff);
+		assert_eq!(image.pixels, [red, red, red, red, red, blue, blue, red, red, red, red, red]);
+		let (id, _, image) = &frames[1];
+		assert_eq!((*id, image.w, image.h), (rgb565, 2, 1));
+		assert_eq!(image.pixels, [red, red]);
+	}
+
+	#[test]
+	fn typed_access_to_memory_buffers() {
+		let mut headless = Headless::new();
+		headless.add_window(3, 2, PixelFormat::Abgr16161616f);
+		headless.add_window(3, 3, PixelFormat::Argb888);
+		headless.set_frame_limit(Some(1));
+		let mut filled = vec![];
+		headless
+			.work(&mut filled, |filled, mut snapshot| {
+				let mut pixels = snapshot.pixels().unwrap();
+				filled.push(match pixels.format() {
+					PixelFormat::Abgr16161616f => pixels.fill(0x3c00_3c00_3c00_3c00u64),
+					_ => pixels.fill(0xffffffffu32),
+				});
+			})
+			.unwrap();
+		assert_eq!(filled, [true, true]);
+	}
+
+	#[test]
+	fn ages_and_stale_rects() {
+		let mut headless = Headless::new();
+		let id = headless.add_window(8, 8, PixelFormat::Argb888);
+		// buffer size, age and stale rects the render closure saw
+		let mut seen: Vec<((u32, u32), u32, Vec<Rect>)> = vec![];
+		let mut frame = |headless: &mut Headless, damage: Rect| {
+			headless
+				.work(&mut seen, |seen, mut snapshot| {
+					seen.push(((snapshot.w, snapshot.h), snapshot.age, snapshot.stale.clone()));
+					snapshot.add_damage(damage.0, damage.1, damage.2, damage.3);
+				})
+				.unwrap();
+		};
+		// two new buffers, then the first again, which missed only the second's damage
+		frame(&mut headless, (0, 0, 2, 2));
+		frame(&mut headless, (4, 4, 2, 2));
+		frame(&mut headless, (0, 0, 1, 1));
+		// a resize makes both garbage
+		headless.configure(id, 4, 4).unwrap();
+		frame(&mut headless, (1, 1, 1, 1));
+		frame(&mut headless, (2, 2, 1, 1));
+		frame(&mut headless, (0, 0, 1, 1));
+		// and so does a new scale, 4 * 1.5 pixels across
+		headless.set_scale(id, 1.5).unwrap();
+		frame(&mut headless, (0, 0, 1, 1));
+		frame(&mut headless, (5, 5, 1, 1));
+		frame(&mut headless, (0, 0, 1, 1));
+		assert_eq!(
+			seen,
+			[
+				((8, 8), 0, vec![(0, 0, 8, 8)]),
+				((8, 8), 0, vec![(0, 0, 8, 8)]),
+				((8, 8), 2, vec![(4, 4, 2, 2)]),
+				((4, 4), 0, vec![(0, 0, 4, 4)]),
+				((4, 4), 0, vec![(0, 0, 4, 4)]),
+				((4, 4), 2, vec![(2, 2, 1, 1)]),
+				((6, 6), 0, vec![(0, 0, 6, 6)]),
+				((6, 6), 0, vec![(0, 0, 6, 6)]),
+				((6, 6), 2, vec![(5, 5, 1, 1)]),
+			]
+		);
+	}
+}
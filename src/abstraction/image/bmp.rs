@@ -0,0 +1,187 @@
+use crate::{
+	abstraction::image::{Image, err, premultiply, scale},
+	wayland::WaytinierError,
+};
+
+const FILE_HEADER: usize = 14;
+// windows 2.0 / os/2 header, everything after it is at least 40
+const CORE_HEADER: usize = 12;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16, WaytinierError> {
+	match data.get(at..at + 2) {
+		Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+		None => err("truncated header"),
+	}
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<u32, WaytinierError> {
+	match data.get(at..at + 4) {
+		Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+		None => err("truncated header"),
+	}
+}
+
+// the bits of a channel mask pulled out and stretched to 0..255, None without a mask
+fn channel(px: u32, mask: u32) -> Option<u8> {
+	if mask == 0 {
+		return None;
+	}
+	let max = mask >> mask.trailing_zeros();
+	Some(scale((px & mask) >> mask.trailing_zeros(), max))
+}
+
+// uncompressed bmps of any bit depth, rle compression isn't supported
+pub(crate) fn decode(data: &[u8]) -> Result<Image, WaytinierError> {
+	let offset = u32_at(data, 10)? as usize;
+	let header = u32_at(data, FILE_HEADER)? as usize;
+	let core = header == CORE_HEADER;
+	let (w, h, bpp, compression, colors) = if core {
+		let (w, h) = (u16_at(data, 18)? as i32, u16_at(data, 20)? as i32);
+		(w, h, u16_at(data, 24)?, BI_RGB, 0)
+	} else {
+		(
+			u32_at(data, 18)? as i32,
+			u32_at(data, 22)? as i32,
+			u16_at(data, 28)?,
+			u32_at(data, 30)?,
+			u32_at(data, 46)? as usize,
+		)
+	};
+	if w <= 0 || h == 0 {
+		return err("bad size");
+	}
+	// negative heights are stored top down
+	let (top_down, h) = (h < 0, h.unsigned_abs());
+	let w = w as u32;
+
+	let masks = match (compression, bpp) {
+		(BI_BITFIELDS | BI_ALPHABITFIELDS, 16 | 32) => {
+			let alpha = if compression == BI_ALPHABITFIELDS || header >= 56 {
+				u32_at(data, 66)?
+			} else {
+				0
+			};
+			[u32_at(data, 54)?, u32_at(data, 58)?, u32_at(data, 62)?, alpha]
+		}
+		(BI_RGB, 16) => [0x7c00, 0x3e0, 0x1f, 0],
+		(BI_RGB, 24 | 32) => [0xff0000, 0xff00, 0xff, 0],
+		(BI_RGB, 1 | 4 | 8) => [0; 4],
+		_ => return err("unsupported compression"),
+	};
+
+	let palette: Vec<[u8; 4]> = match bpp {
+		1 | 4 | 8 => {
+			let entry = 4 - core as usize;
+			let start = FILE_HEADER + header;
+			let count = match colors {
+				0 => 1 << bpp,
+				n => n.min(1 << bpp),
+			};
+			(0..count)
+				.map(|i| {
+					let at = start + i * entry;
+					match data.get(at..at + 3) {
+						Some(c) => Ok([c[2], c[1], c[0], 255]),
+						None => err("truncated palette"),
+					}
+				})
+				.collect::<Result<_, _>>()?
+		}
+		_ => vec![],
+	};
+
+	let stride = (w as u64 * bpp as u64).div_ceil(32) * 4;
+	if offset as u64 + stride * h as u64 > data.len() as u64 {
+		return err("truncated data");
+	}
+	let stride = stride as usize;
+	let mut image = Image::checked(w, h)?;
+	for y in 0..h as usize {
+		let row = if top_down {
+			y
+		} else {
+			h as usize - 1 - y
+		};
+		let start = offset + row * stride;
+		let Some(bytes) = data.get(start..start + stride) else {
+			return err("truncated data");
+		};
+		for x in 0..w as usize {
+			let rgba = match bpp {
+				1 | 4 | 8 => {
+					let bit = x * bpp as usize;
+					let byte = bytes[bit / 8];
+					let shift = 8 - bpp as usize - bit % 8;
+					let i = (byte >> shift) as usize & ((1 << bpp) - 1);
+					*palette.get(i).ok_or(WaytinierError::ImageDecode("bad palette index"))?
+				}
+				16 | 24 | 32 => {
+					let n = bpp as usize / 8;
+					let px = bytes[x * n..x * n + n]
+						.iter()
+						.rev()
+						.fold(0u32, |acc, b| acc << 8 | *b as u32);
+					let [r, g, b, a] = masks.map(|mask| channel(px, mask));
+					[r.unwrap_or(0), g.unwrap_or(0), b.unwrap_or(0), a.unwrap_or(255)]
+				}
+				_ => return err("unsupported bit depth"),
+			};
+			image.pixels[y * w as usize + x] = premultiply(rgba);
+		}
+	}
+	Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// file and info header, then the palette and pixel data as given
+	fn bmp(w: i32, h: i32, bpp: u16, palette: &[u8], pixels: &[u8]) -> Vec<u8> {
+		let offset = (FILE_HEADER + 40 + palette.len()) as u32;
+		let mut data = b"BM".to_vec();
+		data.extend((offset + pixels.len() as u32).to_le_bytes());
+		data.extend([0; 4]);
+		data.extend(offset.to_le_bytes());
+		data.extend(40u32.to_le_bytes());
+		data.extend(w.to_le_bytes());
+		data.extend(h.to_le_bytes());
+		data.extend(1u16.to_le_bytes());
+		data.extend(bpp.to_le_bytes());
+		data.extend([0; 24]);
+		data.extend(palette);
+		data.extend(pixels);
+		data
+	}
+
+	#[test]
+	fn bottom_up_24bpp() {
+		// bgr, rows padded to 4 bytes, the bottom row first
+		let pixels = [255, 0, 0, 0, 255, 0, 0, 0, 0, 0, 255, 255, 255, 255, 0, 0];
+		let image = decode(&bmp(2, 2, 24, &[], &pixels)).unwrap();
+		assert_eq!(image.pixels, [0xffff0000, 0xffffffff, 0xff0000ff, 0xff00ff00]);
+	}
+
+	#[test]
+	fn top_down_1bpp() {
+		let palette = [0, 0, 0, 0, 0x30, 0x20, 0x10, 0];
+		let image = decode(&bmp(3, -2, 1, &palette, &[0xa0, 0, 0, 0, 0x60, 0, 0, 0])).unwrap();
+		assert_eq!(
+			image.pixels,
+			[0xff102030, 0xff000000, 0xff102030, 0xff000000, 0xff102030, 0xff102030]
+		);
+	}
+
+	#[test]
+	fn rejects_bad_headers() {
+		assert!(decode(&bmp(0, 2, 24, &[], &[])).is_err());
+		assert!(decode(&bmp(2, 2, 24, &[], &[0; 8])).is_err());
+		assert!(decode(&bmp(1, 1, 24, &[], &[0; 4])[..30]).is_err());
+		// a size the data can't hold
+		assert!(decode(&bmp(16384, -16384, 32, &[], &[0; 16])).is_err());
+	}
+}
@@ -0,0 +1,137 @@
+use crate::abstraction::image::inflate::{
+	DIST_BASE, DIST_EXTRA, LENGTH_BASE, LENGTH_EXTRA, adler32,
+};
+
+const WINDOW: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+// how many earlier positions with the same hash get tried, more is smaller but slower
+const MAX_CHAIN: usize = 64;
+
+struct BitWriter {
+	out: Vec<u8>,
+	buf: u32,
+	len: u32,
+}
+
+impl BitWriter {
+	fn put(&mut self, v: u32, n: u32) {
+		self.buf |= v << self.len;
+		self.len += n;
+		while self.len >= 8 {
+			self.out.push(self.buf as u8);
+			self.buf >>= 8;
+			self.len -= 8;
+		}
+	}
+
+	// huffman codes go out most significant bit first, unlike everything else
+	fn put_code(&mut self, code: u32, n: u32) {
+		self.put(code.reverse_bits() >> (32 - n), n);
+	}
+
+	fn finish(mut self) -> Vec<u8> {
+		if self.len > 0 {
+			self.out.push(self.buf as u8);
+		}
+		self.out
+	}
+}
+
+// the fixed literal/length code, rfc 1951 3.2.6
+fn literal(w: &mut BitWriter, symbol: u32) {
+	match symbol {
+		0..=143 => w.put_code(0x30 + symbol, 8),
+		144..=255 => w.put_code(0x190 + symbol - 144, 9),
+		256..=279 => w.put_code(symbol - 256, 7),
+		_ => w.put_code(0xc0 + symbol - 280, 8),
+	}
+}
+
+fn pair(w: &mut BitWriter, len: usize, dist: usize) {
+	let l = LENGTH_BASE.iter().rposition(|base| *base as usize <= len).unwrap_or(0);
+	literal(w, 257 + l as u32);
+	w.put((len - LENGTH_BASE[l] as usize) as u32, LENGTH_EXTRA[l] as u32);
+	let d = DIST_BASE.iter().rposition(|base| *base as usize <= dist).unwrap_or(0);
+	w.put_code(d as u32, 5);
+	w.put((dist - DIST_BASE[d] as usize) as u32, DIST_EXTRA[d] as u32);
+}
+
+fn hash(data: &[u8]) -> usize {
+	let v = u32::from_le_bytes([data[0], data[1], data[2], 0]);
+	(v.wrapping_mul(0x9e3779b1) >> (32 - HASH_BITS)) as usize
+}
+
+// one fixed huffman block with greedy lz77 matching, plenty for screenshots
+fn deflate(data: &[u8]) -> Vec<u8> {
+	let mut w = BitWriter {
+		out: vec![],
+		buf: 0,
+		len: 0,
+	};
+	// final block, fixed codes
+	w.put(1, 1);
+	w.put(1, 2);
+	let mut head = vec![usize::MAX; 1 << HASH_BITS];
+	let mut prev = vec![usize::MAX; WINDOW];
+	let mut pos = 0;
+	while pos < data.len() {
+		let (mut best_len, mut best_dist) = (0, 0);
+		if pos + MIN_MATCH <= data.len() {
+			let max = MAX_MATCH.min(data.len() - pos);
+			let mut candidate = head[hash(&data[pos..])];
+			for _ in 0..MAX_CHAIN {
+				if candidate == usize::MAX || pos - candidate > WINDOW - 1 {
+					break;
+				}
+				let len = data[candidate..]
+					.iter()
+					.zip(&data[pos..pos + max])
+					.take_while(|(a, b)| a == b)
+					.count();
+				if len > best_len {
+					(best_len, best_dist) = (len, pos - candidate);
+					if len == max {
+						break;
+					}
+				}
+				// slots get reused once the window moves on, older is the only way back
+				match prev[candidate % WINDOW] {
+					next if next < candidate => candidate = next,
+					_ => break,
+				}
+			}
+		}
+		if best_len >= MIN_MATCH {
+			pair(&mut w, best_len, best_dist);
+			for p in pos..pos + best_len {
+				insert(data, p, &mut head, &mut prev);
+			}
+			pos += best_len;
+		} else {
+			literal(&mut w, data[pos] as u32);
+			insert(data, pos, &mut head, &mut prev);
+			pos += 1;
+		}
+	}
+	literal(&mut w, 256);
+	w.finish()
+}
+
+// chains pos in front of the earlier positions with the same hash
+fn insert(data: &[u8], pos: usize, head: &mut [usize], prev: &mut [usize]) {
+	if pos + MIN_MATCH <= data.len() {
+		let h = hash(&data[pos..]);
+		prev[pos % WINDOW] = head[h];
+		head[h] = pos;
+	}
+}
+
+pub(crate) fn zlib_compress(data: &[u8]) -> Vec<u8> {
+	// deflate with a 32k window, default level
+	let mut out = vec![0x78, 0x9c];
+	out.extend(deflate(data));
+	out.extend(adler32(data).to_be_bytes());
+	out
+}
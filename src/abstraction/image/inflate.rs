@@ -0,0 +1,314 @@
+use crate::{abstraction::image::err, wayland::WaytinierError};
+
+const MAX_BITS: usize = 15;
+
+// base and extra bits of length symbols 257..285 and distance symbols 0..29, rfc 1951 3.2.5
+pub(crate) const LENGTH_BASE: [u16; 29] = [
+	3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+	163, 195, 227, 258,
+];
+pub(crate) const LENGTH_EXTRA: [u8; 29] =
+	[0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+pub(crate) const DIST_BASE: [u16; 30] = [
+	1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+	2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+pub(crate) const DIST_EXTRA: [u8; 30] = [
+	0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+	13,
+];
+// order the code length code lengths come in
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct Bits<'a> {
+	data: &'a [u8],
+	pos: usize,
+	buf: u32,
+	len: u32,
+}
+
+impl Bits<'_> {
+	fn take(&mut self, n: u32) -> Result<u32, WaytinierError> {
+		while self.len < n {
+			let Some(byte) = self.data.get(self.pos) else {
+				return err("truncated deflate stream");
+			};
+			self.buf |= (*byte as u32) << self.len;
+			self.len += 8;
+			self.pos += 1;
+		}
+		let v = self.buf & ((1 << n) - 1);
+		self.buf >>= n;
+		self.len -= n;
+		Ok(v)
+	}
+
+	// never more than 7 bits are left over, those get dropped
+	fn align(&mut self) {
+		self.buf = 0;
+		self.len = 0;
+	}
+}
+
+// canonical huffman code as symbol counts per length and symbols ordered by code
+struct Huffman {
+	counts: [u16; MAX_BITS + 1],
+	symbols: Vec<u16>,
+}
+
+impl Huffman {
+	fn new(lengths: &[u8]) -> Self {
+		let mut counts = [0u16; MAX_BITS + 1];
+		lengths.iter().for_each(|l| counts[*l as usize] += 1);
+		counts[0] = 0;
+		let mut offsets = [0u16; MAX_BITS + 2];
+		for len in 1..=MAX_BITS {
+			offsets[len + 1] = offsets[len] + counts[len];
+		}
+		let mut symbols = vec![0; offsets[MAX_BITS + 1] as usize];
+		for (symbol, len) in lengths.iter().enumerate().filter(|(_, l)| **l != 0) {
+			symbols[offsets[*len as usize] as usize] = symbol as u16;
+			offsets[*len as usize] += 1;
+		}
+		Self {
+			counts,
+			symbols,
+		}
+	}
+
+	// walks the code a bit at a time, codes of each length are consecutive
+	fn decode(&self, bits: &mut Bits) -> Result<u16, WaytinierError> {
+		let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+		for len in 1..=MAX_BITS {
+			code |= bits.take(1)? as i32;
+			let count = self.counts[len] as i32;
+			if code - first < count {
+				return Ok(self.symbols[(index + code - first) as usize]);
+			}
+			index += count;
+			first = (first + count) << 1;
+			code <<= 1;
+		}
+		err("bad huffman code")
+	}
+}
+
+fn fixed() -> (Huffman, Huffman) {
+	let mut lengths = [8u8; 288];
+	lengths[144..256].fill(9);
+	lengths[256..280].fill(7);
+	(Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic(bits: &mut Bits) -> Result<(Huffman, Huffman), WaytinierError> {
+	let nlen = bits.take(5)? as usize + 257;
+	let ndist = bits.take(5)? as usize + 1;
+	let ncode = bits.take(4)? as usize + 4;
+	let mut clens = [0u8; 19];
+	for i in CLEN_ORDER.iter().take(ncode) {
+		clens[*i] = bits.take(3)? as u8;
+	}
+	let clen = Huffman::new(&clens);
+	let mut lengths = vec![];
+	while lengths.len() < nlen + ndist {
+		let (value, repeat) = match clen.decode(bits)? {
+			symbol @ 0..=15 => (symbol as u8, 1),
+			16 => match lengths.last() {
+				Some(last) => (*last, 3 + bits.take(2)?),
+				None => return err("repeat without a length"),
+			},
+			17 => (0, 3 + bits.take(3)?),
+			_ => (0, 11 + bits.take(7)?),
+		};
+		lengths.extend(std::iter::repeat_n(value, repeat as usize));
+	}
+	if lengths.len() > nlen + ndist {
+		return err("too many code lengths");
+	}
+	Ok((Huffman::new(&lengths[..nlen]), Huffman::new(&lengths[nlen..])))
+}
+
+// max is the most output that makes sense, so a few bytes can't unpack to gigabytes
+fn inflate(bits: &mut Bits, max: usize) -> Result<Vec<u8>, WaytinierError> {
+	let mut out = vec![];
+	loop {
+		let last = bits.take(1)? == 1;
+		let (lit, dist) = match bits.take(2)? {
+			0 => {
+				bits.align();
+				let at = bits.pos;
+				let Some(header) = bits.data.get(at..at + 4) else {
+					return err("truncated stored block");
+				};
+				let len = u16::from_le_bytes([header[0], header[1]]);
+				if u16::from_le_bytes([header[2], header[3]]) != !len {
+					return err("stored block length mismatch");
+				}
+				let len = len as usize;
+				if out.len() + len > max {
+					return err("more data than expected");
+				}
+				let Some(stored) = bits.data.get(at + 4..at + 4 + len) else {
+					return err("truncated stored block");
+				};
+				out.extend_from_slice(stored);
+				bits.pos = at + 4 + len;
+				if last {
+					return Ok(out);
+				}
+				continue;
+			}
+			1 => fixed(),
+			2 => dynamic(bits)?,
+			_ => return err("bad block type"),
+		};
+		loop {
+			let symbol = lit.decode(bits)? as usize;
+			if symbol < 256 {
+				if out.len() == max {
+					return err("more data than expected");
+				}
+				out.push(symbol as u8);
+				continue;
+			}
+			if symbol == 256 {
+				break;
+			}
+			let i = symbol - 257;
+			if i >= LENGTH_BASE.len() {
+				return err("bad length symbol");
+			}
+			let len = LENGTH_BASE[i] as usize + bits.take(LENGTH_EXTRA[i] as u32)? as usize;
+			let d = dist.decode(bits)? as usize;
+			if d >= DIST_BASE.len() {
+				return err("bad distance symbol");
+			}
+			let back = DIST_BASE[d] as usize + bits.take(DIST_EXTRA[d] as u32)? as usize;
+			if back > out.len() {
+				return err("distance too far back");
+			}
+			if out.len() + len > max {
+				return err("more data than expected");
+			}
+			// copies can overlap what they produce, so byte by byte
+			let start = out.len() - back;
+			for k in 0..len {
+				out.push(out[start + k]);
+			}
+		}
+		if last {
+			return Ok(out);
+		}
+	}
+}
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+	let (mut a, mut b) = (1u32, 0u32);
+	// 5552 is the most bytes before b can overflow
+	for chunk in data.chunks(5552) {
+		for byte in chunk {
+			a += *byte as u32;
+			b += a;
+		}
+		a %= 65521;
+		b %= 65521;
+	}
+	b << 16 | a
+}
+
+pub(crate) fn zlib_decompress(data: &[u8], max: usize) -> Result<Vec<u8>, WaytinierError> {
+	let [cmf, flg, ..] = *data else {
+		return err("truncated zlib header");
+	};
+	if cmf & 0xf != 8 || !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) || flg & 0x20 != 0 {
+		return err("bad zlib header");
+	}
+	let mut bits = Bits {
+		data,
+		pos: 2,
+		buf: 0,
+		len: 0,
+	};
+	let out = inflate(&mut bits, max)?;
+	let at = bits.pos;
+	match data.get(at..at + 4) {
+		Some(sum) if u32::from_be_bytes([sum[0], sum[1], sum[2], sum[3]]) != adler32(&out) => {
+			err("zlib checksum mismatch")
+		}
+		_ => Ok(out),
+	}
+}
+
+// gzip as console fonts come in, the trailing crc isn't checked
+pub(crate) fn gunzip(data: &[u8], max: usize) -> Result<Vec<u8>, WaytinierError> {
+	let [0x1f, 0x8b, 8, flags, ..] = *data else {
+		return err("bad gzip header");
+	};
+	let mut pos = 10;
+	// FEXTRA
+	if flags & 4 != 0 {
+		let Some(len) = data.get(pos..pos + 2) else {
+			return err("truncated gzip header");
+		};
+		pos += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+	}
+	// FNAME and FCOMMENT, both zero terminated
+	for flag in [8, 16] {
+		if flags & flag != 0 {
+			let Some(len) = data.get(pos..).and_then(|rest| rest.iter().position(|b| *b == 0))
+			else {
+				return err("truncated gzip header");
+			};
+			pos += len + 1;
+		}
+	}
+	// FHCRC
+	if flags & 2 != 0 {
+		pos += 2;
+	}
+	let mut bits = Bits {
+		data,
+		pos,
+		buf: 0,
+		len: 0,
+	};
+	inflate(&mut bits, max)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::abstraction::image::deflate::zlib_compress;
+
+	// zlib header, one final stored block of "abc" and its checksum
+	fn stored(nlen: [u8; 2]) -> Vec<u8> {
+		let mut data = vec![0x78, 0x01, 0x01, 3, 0, nlen[0], nlen[1], b'a', b'b', b'c'];
+		data.extend(adler32(b"abc").to_be_bytes());
+		data
+	}
+
+	#[test]
+	fn round_trip() {
+		let data: Vec<u8> =
+			(0..20000u32).map(|i| (i * i / 7 % 251) as u8).chain(*b"hello hello hello").collect();
+		let packed = zlib_compress(&data);
+		assert!(packed.len() < data.len());
+		assert_eq!(zlib_decompress(&packed, data.len()).unwrap(), data);
+		assert_eq!(zlib_decompress(&zlib_compress(&[]), 0).unwrap(), []);
+	}
+
+	#[test]
+	fn stops_past_max() {
+		// a long run packs down to a few bytes
+		let data = vec![0; 1 << 16];
+		let packed = zlib_compress(&data);
+		assert!(zlib_decompress(&packed, data.len() - 1).is_err());
+		assert!(zlib_decompress(&stored([0xfc, 0xff]), 2).is_err());
+	}
+
+	#[test]
+	fn stored_block_checks_nlen() {
+		assert_eq!(zlib_decompress(&stored([0xfc, 0xff]), 3).unwrap(), b"abc");
+		assert!(zlib_decompress(&stored([0xfd, 0xff]), 3).is_err());
+	}
+}
@@ -0,0 +1,161 @@
+use std::{fs, path::Path};
+
+use crate::{
+	abstraction::{
+		draw::{self, Color},
+		pixels::PixelBuffer,
+	},
+	wayland::WaytinierError,
+};
+
+mod bmp;
+mod deflate;
+pub(crate) mod inflate;
+mod png;
+mod ppm;
+mod qoi;
+
+// anything bigger is a broken or malicious header rather than a picture
+const MAX_PIXELS: u64 = 1 << 28;
+
+// an in memory picture to blit from, premultiplied 0xAARRGGBB like argb8888
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+	pub w: u32,
+	pub h: u32,
+	pub pixels: Vec<u32>,
+}
+
+impl Image {
+	pub fn new(w: u32, h: u32) -> Self {
+		Self {
+			w,
+			h,
+			pixels: vec![0; w as usize * h as usize],
+		}
+	}
+
+	// the pixel count of a size read out of a file, if it's believable at all
+	pub(crate) fn check_size(w: u32, h: u32) -> Result<usize, WaytinierError> {
+		if w as u64 * h as u64 > MAX_PIXELS {
+			return Err(WaytinierError::ImageDecode("image too large"));
+		}
+		Ok(w as usize * h as usize)
+	}
+
+	// same as new, but for sizes read out of a file. decoders check the size
+	// against what the data can hold first, the cap alone still allows a gigabyte
+	pub(crate) fn checked(w: u32, h: u32) -> Result<Self, WaytinierError> {
+		Self::check_size(w, h)?;
+		Ok(Self::new(w, h))
+	}
+
+	// ppm, pgm, pbm, bmp, qoi or png, told apart by their magic
+	pub fn decode(data: &[u8]) -> Result<Self, WaytinierError> {
+		match data {
+			[0x89, b'P', b'N', b'G', ..] => png::decode(data),
+			[b'q', b'o', b'i', b'f', ..] => qoi::decode(data),
+			[b'B', b'M', ..] => bmp::decode(data),
+			[b'P', b'1'..=b'6', ..] => ppm::decode(data),
+			_ => Err(WaytinierError::ImageDecode("unknown format")),
+		}
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, WaytinierError> {
+		Self::decode(&fs::read(path)?)
+	}
+
+	// binary ppm, alpha is dropped as if drawn over black
+	pub fn encode_ppm(&self) -> Vec<u8> {
+		ppm::encode(self)
+	}
+
+	pub fn encode_png(&self) -> Vec<u8> {
+		png::encode(self)
+	}
+
+	// a copy of what's in a frame, e.g. to save window contents
+	pub fn from_pixels(pixels: &mut PixelBuffer) -> Self {
+		let pf = pixels.format();
+		let bpp = pf.width() as usize;
+		let mut image = Self::new(pixels.width(), pixels.height());
+		for (row, out) in pixels.rows().zip(image.pixels.chunks_exact_mut(image.w.max(1) as usize))
+		{
+			for (px, out) in row.chunks_exact(bpp).zip(out) {
+				let [r, g, b, a] = draw::load(pf, px).map(draw::to_u8);
+				*out = u32::from_le_bytes([b, g, r, a]);
+			}
+		}
+		image
+	}
+
+	pub fn get(&self, x: u32, y: u32) -> Option<u32> {
+		if x >= self.w {
+			return None;
+		}
+		self.pixels.get(y as usize * self.w as usize + x as usize).copied()
+	}
+
+	pub fn put(&mut self, x: u32, y: u32, color: Color) {
+		if x >= self.w {
+			return;
+		}
+		let [r, g, b, a] = color.premultiplied().map(draw::to_u8);
+		if let Some(px) = self.pixels.get_mut(y as usize * self.w as usize + x as usize) {
+			*px = u32::from_le_bytes([b, g, r, a]);
+		}
+	}
+
+	// premultiplied rgba in 0..1, coordinates past the edges repeat the edge
+	pub(crate) fn sample(&self, x: i64, y: i64) -> [f32; 4] {
+		// no edge to repeat
+		if self.w == 0 || self.h == 0 {
+			return [0.0; 4];
+		}
+		let x = x.clamp(0, self.w as i64 - 1) as usize;
+		let y = y.clamp(0, self.h as i64 - 1) as usize;
+		let [b, g, r, a] = self.pixels[y * self.w as usize + x].to_le_bytes();
+		[r, g, b, a].map(|c| c as f32 / 255.0)
+	}
+}
+
+// straight rgba, as the formats store it, to a pixel of an Image
+pub(crate) fn premultiply([r, g, b, a]: [u8; 4]) -> u32 {
+	let mul = |c: u8| ((c as u32 * a as u32 + 127) / 255) as u8;
+	u32::from_le_bytes([mul(b), mul(g), mul(r), a])
+}
+
+pub(crate) fn unpremultiply(px: u32) -> [u8; 4] {
+	let [b, g, r, a] = px.to_le_bytes();
+	let div = |c: u8| match a {
+		0 => 0,
+		a => ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8,
+	};
+	[div(r), div(g), div(b), a]
+}
+
+// a sample of any bit depth stretched to 0..255
+pub(crate) fn scale(v: u32, max: u32) -> u8 {
+	((v * 255 + max / 2) / max.max(1)) as u8
+}
+
+pub(crate) fn err<T>(what: &'static str) -> Result<T, WaytinierError> {
+	Err(WaytinierError::ImageDecode(what))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn edges() {
+		assert_eq!(Image::new(0, 0).sample(3, -1), [0.0; 4]);
+		let mut image = Image::new(2, 2);
+		image.put(1, u32::MAX, Color::WHITE);
+		assert_eq!(image.get(1, u32::MAX), None);
+		assert_eq!(image.get(2, 0), None);
+		image.put(1, 1, Color::WHITE);
+		assert_eq!(image.get(1, 1), Some(0xffffffff));
+		assert_eq!(image.sample(5, 5), [1.0; 4]);
+	}
+}
@@ -0,0 +1,341 @@
+use crate::{
+	abstraction::image::{
+		Image, deflate::zlib_compress, err, inflate::zlib_decompress, premultiply, scale,
+		unpremultiply,
+	},
+	wayland::WaytinierError,
+};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+// x, y, dx, dy of each adam7 pass
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+	(0, 0, 8, 8),
+	(4, 0, 8, 8),
+	(0, 4, 4, 8),
+	(2, 0, 4, 4),
+	(0, 2, 2, 4),
+	(1, 0, 2, 2),
+	(0, 1, 1, 2),
+];
+
+fn crc32(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for byte in bytes {
+		crc ^= *byte as u32;
+		for _ in 0..8 {
+			crc = (crc >> 1) ^ (0xedb88320 & (crc & 1).wrapping_neg());
+		}
+	}
+	!crc
+}
+
+fn be32(b: &[u8]) -> u32 {
+	u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+	let p = a as i16 + b as i16 - c as i16;
+	let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+	match (pa <= pb && pa <= pc, pb <= pc) {
+		(true, _) => a,
+		(false, true) => b,
+		(false, false) => c,
+	}
+}
+
+// undoes a row's filter in place, prev is the row above, already unfiltered
+fn unfilter(filter: u8, row: &mut [u8], prev: &[u8], bpp: usize) -> Result<(), WaytinierError> {
+	for i in 0..row.len() {
+		let a = i.checked_sub(bpp).map_or(0, |j| row[j]);
+		let b = prev[i];
+		let c = i.checked_sub(bpp).map_or(0, |j| prev[j]);
+		row[i] = row[i].wrapping_add(match filter {
+			0 => 0,
+			1 => a,
+			2 => b,
+			3 => ((a as u16 + b as u16) / 2) as u8,
+			4 => paeth(a, b, c),
+			_ => return err("bad filter type"),
+		});
+	}
+	Ok(())
+}
+
+struct Header {
+	w: u32,
+	h: u32,
+	depth: u8,
+	color: u8,
+}
+
+impl Header {
+	fn channels(&self) -> usize {
+		match self.color {
+			2 => 3,
+			4 => 2,
+			6 => 4,
+			_ => 1,
+		}
+	}
+
+	// whole bytes per pixel, at least one, what filters look back by
+	fn bpp(&self) -> usize {
+		(self.channels() * self.depth as usize).div_ceil(8)
+	}
+
+	fn row_len(&self, w: usize) -> usize {
+		(w * self.channels() * self.depth as usize).div_ceil(8)
+	}
+}
+
+// the i-th sample of a row at any bit depth
+fn sample(row: &[u8], i: usize, depth: u8) -> u32 {
+	match depth {
+		16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]) as u32,
+		8 => row[i] as u32,
+		d => {
+			let bit = i * d as usize;
+			(row[bit / 8] >> (8 - d as usize - bit % 8)) as u32 & ((1 << d) - 1)
+		}
+	}
+}
+
+pub(crate) fn decode(data: &[u8]) -> Result<Image, WaytinierError> {
+	if data.get(..8) != Some(&SIGNATURE) {
+		return err("bad png signature");
+	}
+	let mut header = None;
+	let mut palette: Vec<[u8; 4]> = vec![];
+	let mut trns: Option<&[u8]> = None;
+	let mut idat = vec![];
+	let mut interlaced = false;
+	let mut pos = 8;
+	loop {
+		let Some(len) = data.get(pos..pos + 4).map(be32) else {
+			return err("truncated chunk");
+		};
+		let len = len as usize;
+		let Some(chunk) = data.get(pos + 4..pos + 8 + len) else {
+			return err("truncated chunk");
+		};
+		let Some(crc) = data.get(pos + 8 + len..pos + 12 + len).map(be32) else {
+			return err("truncated chunk");
+		};
+		if crc32(chunk) != crc {
+			return err("chunk checksum mismatch");
+		}
+		let (kind, body) = chunk.split_at(4);
+		match kind {
+			b"IHDR" if body.len() >= 13 => {
+				let h = Header {
+					w: be32(&body[0..4]),
+					h: be32(&body[4..8]),
+					depth: body[8],
+					color: body[9],
+				};
+				let valid = match h.color {
+					0 => matches!(h.depth, 1 | 2 | 4 | 8 | 16),
+					3 => matches!(h.depth, 1 | 2 | 4 | 8),
+					2 | 4 | 6 => matches!(h.depth, 8 | 16),
+					_ => false,
+				};
+				if !valid || body[10] != 0 || body[11] != 0 {
+					return err("unsupported png header");
+				}
+				interlaced = body[12] == 1;
+				header = Some(h);
+			}
+			b"PLTE" => palette = body.chunks_exact(3).map(|c| [c[0], c[1], c[2], 255]).collect(),
+			b"tRNS" => trns = Some(body),
+			b"IDAT" => idat.extend_from_slice(body),
+			b"IEND" => break,
+			// everything else is ancillary or something we can't make sense of anyway
+			_ => {}
+		}
+		pos += 12 + len;
+	}
+	let Some(header) = header else {
+		return err("missing IHDR");
+	};
+	if header.color == 3 {
+		for (entry, alpha) in palette.iter_mut().zip(trns.unwrap_or_default()) {
+			entry[3] = *alpha;
+		}
+	}
+	// gray or rgb that tRNS marks as fully transparent, in raw sample values
+	let key: Option<Vec<u32>> = match header.color {
+		0 | 2 => trns
+			.map(|t| t.chunks_exact(2).map(|s| u16::from_be_bytes([s[0], s[1]]) as u32).collect()),
+		_ => None,
+	};
+
+	Image::check_size(header.w, header.h)?;
+	let (w, h) = (header.w as usize, header.h as usize);
+	let passes = if interlaced {
+		&ADAM7[..]
+	} else {
+		&[(0, 0, 1, 1)][..]
+	};
+	// the passes that aren't empty, with their width and height
+	let passes: Vec<_> = passes
+		.iter()
+		.map(|&(x0, y0, dx, dy)| {
+			((x0, y0, dx, dy), ((w + dx - 1 - x0.min(w)) / dx, (h + dy - 1 - y0.min(h)) / dy))
+		})
+		.filter(|(_, (pw, ph))| *pw > 0 && *ph > 0)
+		.collect();
+	// a filter byte in front of every row
+	let expected = passes.iter().map(|(_, (pw, ph))| ph * (1 + header.row_len(*pw))).sum();
+	// inflating grows its output as it goes, so only a size the data really
+	// fills gets its pixels allocated
+	let raw = zlib_decompress(&idat, expected)?;
+	if raw.len() < expected {
+		return err("truncated image data");
+	}
+	let mut image = Image::new(header.w, header.h);
+	let max = (1u32 << header.depth) - 1;
+	let bpp = header.bpp();
+	let channels = header.channels();
+	let mut at = 0;
+	for ((x0, y0, dx, dy), (pw, ph)) in passes {
+		let len = header.row_len(pw);
+		let mut prev = vec![0u8; len];
+		for py in 0..ph {
+			let Some(filtered) = raw.get(at..at + 1 + len) else {
+				return err("truncated image data");
+			};
+			let mut row = filtered[1..].to_vec();
+			unfilter(filtered[0], &mut row, &prev, bpp)?;
+			at += 1 + len;
+			for px in 0..pw {
+				let s = |c: usize| sample(&row, px * channels + c, header.depth);
+				let rgba = match header.color {
+					3 => *palette
+						.get(s(0) as usize)
+						.ok_or(WaytinierError::ImageDecode("bad palette index"))?,
+					0 | 4 => {
+						let v = scale(s(0), max);
+						let a = match header.color {
+							4 => scale(s(1), max),
+							_ if key.as_deref() == Some(&[s(0)]) => 0,
+							_ => 255,
+						};
+						[v, v, v, a]
+					}
+					_ => {
+						let rgb = [s(0), s(1), s(2)];
+						let a = match header.color {
+							6 => scale(s(3), max),
+							_ if key.as_deref() == Some(&rgb) => 0,
+							_ => 255,
+						};
+						[scale(rgb[0], max), scale(rgb[1], max), scale(rgb[2], max), a]
+					}
+				};
+				image.pixels[(y0 + py * dy) * w + x0 + px * dx] = premultiply(rgba);
+			}
+			prev = row;
+		}
+	}
+	Ok(image)
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+	out.extend((body.len() as u32).to_be_bytes());
+	let start = out.len();
+	out.extend(kind);
+	out.extend(body);
+	let crc = crc32(&out[start..]);
+	out.extend(crc.to_be_bytes());
+}
+
+// 8 bit rgba, or rgb when nothing is see through
+pub(crate) fn encode(image: &Image) -> Vec<u8> {
+	let opaque = image.pixels.iter().all(|px| px >> 24 == 0xff);
+	let (color, bpp) = if opaque {
+		(2, 3)
+	} else {
+		(6, 4)
+	};
+	let mut ihdr = vec![];
+	ihdr.extend(image.w.to_be_bytes());
+	ihdr.extend(image.h.to_be_bytes());
+	ihdr.extend([8, color, 0, 0, 0]);
+
+	let len = image.w as usize * bpp;
+	let mut raw = Vec::with_capacity((len + 1) * image.h as usize);
+	let mut prev = vec![0u8; len];
+	let mut candidate = vec![0u8; len];
+	for row in image.pixels.chunks_exact(image.w.max(1) as usize).take(image.h as usize) {
+		let row: Vec<u8> = row.iter().flat_map(|px| unpremultiply(*px)[..bpp].to_vec()).collect();
+		// the filter whose output looks smallest, the usual heuristic
+		let mut best = (u64::MAX, 0u8, vec![]);
+		for filter in 0..5u8 {
+			for i in 0..len {
+				let a = i.checked_sub(bpp).map_or(0, |j| row[j]);
+				let b = prev[i];
+				let c = i.checked_sub(bpp).map_or(0, |j| prev[j]);
+				candidate[i] = row[i].wrapping_sub(match filter {
+					0 => 0,
+					1 => a,
+					2 => b,
+					3 => ((a as u16 + b as u16) / 2) as u8,
+					_ => paeth(a, b, c),
+				});
+			}
+			let cost = candidate.iter().map(|v| (*v as i8).unsigned_abs() as u64).sum();
+			if cost < best.0 {
+				best = (cost, filter, candidate.clone());
+			}
+		}
+		raw.push(best.1);
+		raw.extend(best.2);
+		prev = row;
+	}
+
+	let mut out = SIGNATURE.to_vec();
+	chunk(&mut out, b"IHDR", &ihdr);
+	chunk(&mut out, b"IDAT", &zlib_compress(&raw));
+	chunk(&mut out, b"IEND", &[]);
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let mut image = Image::new(5, 3);
+		for (i, px) in image.pixels.iter_mut().enumerate() {
+			*px = 0xff000000 | (i as u32 * 0x0d1f29);
+		}
+		let decoded = decode(&encode(&image)).unwrap();
+		assert_eq!(decoded, image);
+		// with alpha, kept to the values premultiplying leaves alone
+		image.pixels[0] = 0;
+		image.pixels[7] = 0x80800000;
+		let decoded = decode(&encode(&image)).unwrap();
+		assert_eq!(decoded, image);
+	}
+
+	#[test]
+	fn rejects_corrupt_chunks() {
+		let mut data = encode(&Image::new(2, 2));
+		assert!(decode(&data[..data.len() - 13]).is_err());
+		// inside IHDR's width
+		data[18] ^= 1;
+		assert!(decode(&data).is_err());
+	}
+
+	#[test]
+	fn rejects_sizes_the_data_cant_hold() {
+		let mut data = encode(&Image::new(2, 2));
+		// 16384 by 16384 with a valid crc, the pixel data is still for 2 by 2
+		data[16..24].copy_from_slice(&[0, 0, 0x40, 0, 0, 0, 0x40, 0]);
+		let crc = crc32(&data[12..29]);
+		data[29..33].copy_from_slice(&crc.to_be_bytes());
+		assert!(decode(&data).is_err());
+	}
+}
@@ -0,0 +1,163 @@
+use crate::{
+	abstraction::image::{Image, err, premultiply, scale},
+	wayland::WaytinierError,
+};
+
+struct Header<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl Header<'_> {
+	fn skip_space(&mut self) {
+		while let Some(c) = self.data.get(self.pos) {
+			match c {
+				b'#' => {
+					while self.data.get(self.pos).is_some_and(|c| *c != b'\n') {
+						self.pos += 1;
+					}
+				}
+				c if c.is_ascii_whitespace() => self.pos += 1,
+				_ => break,
+			}
+		}
+	}
+
+	fn number(&mut self) -> Result<u32, WaytinierError> {
+		self.skip_space();
+		let start = self.pos;
+		while self.data.get(self.pos).is_some_and(u8::is_ascii_digit) {
+			self.pos += 1;
+		}
+		let digits = std::str::from_utf8(&self.data[start..self.pos]).unwrap_or_default();
+		digits.parse().or(err("bad number in header"))
+	}
+
+	// the ascii formats can run their digits together, pbm's 0s and 1s always do
+	fn bit(&mut self) -> Result<u32, WaytinierError> {
+		self.skip_space();
+		let bit = match self.data.get(self.pos) {
+			Some(b'0') => 0,
+			Some(b'1') => 1,
+			_ => return err("bad pbm bit"),
+		};
+		self.pos += 1;
+		Ok(bit)
+	}
+}
+
+// P1 to P6, ascii and binary bitmaps, graymaps and pixmaps
+pub(crate) fn decode(data: &[u8]) -> Result<Image, WaytinierError> {
+	let kind = data[1];
+	let mut header = Header {
+		data,
+		pos: 2,
+	};
+	let (w, h) = (header.number()?, header.number()?);
+	let max = match kind {
+		b'1' | b'4' => 1,
+		_ => header.number()?,
+	};
+	if max == 0 || max > 65535 {
+		return err("bad maxval");
+	}
+	let channels = match kind {
+		b'3' | b'6' => 3,
+		_ => 1,
+	};
+	// one whitespace byte separates the header from binary data
+	let mut pos = header.pos + 1;
+	let wide = max > 255;
+	// the least the pixels can take up, ascii samples need at least a digit each
+	let samples = w as u64 * h as u64 * channels;
+	let needed = match kind {
+		b'4' => (w as u64).div_ceil(8) * h as u64,
+		b'5' | b'6' if wide => samples * 2,
+		_ => samples,
+	};
+	if needed > data.len().saturating_sub(header.pos) as u64 {
+		return Err(truncated());
+	}
+	let mut image = Image::checked(w, h)?;
+	for y in 0..h as usize {
+		for x in 0..w as usize {
+			let mut sample = [0u32; 3];
+			for s in sample.iter_mut().take(channels as usize) {
+				*s = match kind {
+					b'1' => 1 - header.bit()?,
+					b'2' | b'3' => header.number()?.min(max),
+					// rows of bits, each row padded to a byte, 1 is black
+					b'4' => {
+						let row = y * (w as usize).div_ceil(8);
+						let byte = *data.get(pos + row + x / 8).ok_or(truncated())?;
+						1 - (byte >> (7 - x % 8) & 1) as u32
+					}
+					_ if wide => {
+						let v = data.get(pos..pos + 2).ok_or(truncated())?;
+						pos += 2;
+						(u16::from_be_bytes([v[0], v[1]]) as u32).min(max)
+					}
+					_ => {
+						let v = *data.get(pos).ok_or(truncated())?;
+						pos += 1;
+						(v as u32).min(max)
+					}
+				};
+			}
+			let [r, g, b] = match channels {
+				3 => sample.map(|s| scale(s, max)),
+				_ => [scale(sample[0], max); 3],
+			};
+			image.pixels[y * w as usize + x] = premultiply([r, g, b, 255]);
+		}
+	}
+	Ok(image)
+}
+
+fn truncated() -> WaytinierError {
+	WaytinierError::ImageDecode("truncated data")
+}
+
+pub(crate) fn encode(image: &Image) -> Vec<u8> {
+	let mut out = format!("P6\n{} {}\n255\n", image.w, image.h).into_bytes();
+	for px in &image.pixels {
+		// premultiplied color already is the color over black
+		let [b, g, r, _] = px.to_le_bytes();
+		out.extend_from_slice(&[r, g, b]);
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decodes_samples() {
+		// ascii pixmap with a comment and a maxval of 15
+		let image = decode(b"P3\n# two pixels\n2 1\n15\n15 0 0  0 15 5\n").unwrap();
+		assert_eq!((image.w, image.h), (2, 1));
+		assert_eq!(image.pixels, [0xffff0000, 0xff00ff55]);
+		// binary graymap
+		let image = decode(b"P5 3 1 255\n\x00\x80\xff").unwrap();
+		assert_eq!(image.pixels, [0xff000000, 0xff808080, 0xffffffff]);
+		// binary bitmap, 1 is black and rows are padded to a byte
+		let image = decode(b"P4 2 2\n\x80\x40").unwrap();
+		assert_eq!(image.pixels, [0xff000000, 0xffffffff, 0xffffffff, 0xff000000]);
+		assert!(decode(b"P6 2 2 255\n\x00\x00\x00").is_err());
+	}
+
+	#[test]
+	fn rejects_sizes_the_data_cant_hold() {
+		assert!(decode(b"P6 65535 4096 255\n\x00\x00\x00").is_err());
+		assert!(decode(b"P4 16384 16384\n\x00").is_err());
+		assert!(decode(b"P2 16384 16384 255\n0 0 0").is_err());
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut image = Image::new(3, 2);
+		image.pixels = vec![0xff102030, 0xff405060, 0xff708090, 0xffa0b0c0, 0xffd0e0f0, 0xff000000];
+		assert_eq!(decode(&encode(&image)).unwrap(), image);
+	}
+}
@@ -0,0 +1,118 @@
+use crate::{
+	abstraction::image::{Image, err, premultiply},
+	wayland::WaytinierError,
+};
+
+const HEADER: usize = 14;
+
+fn hash([r, g, b, a]: [u8; 4]) -> usize {
+	(r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64
+}
+
+pub(crate) fn decode(data: &[u8]) -> Result<Image, WaytinierError> {
+	if data.len() < HEADER {
+		return err("truncated header");
+	}
+	let w = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+	let h = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+	let total = Image::check_size(w, h)?;
+	// grown as the data comes, a bogus size runs out of data before it runs out of memory
+	let mut pixels = vec![];
+	let mut index = [[0u8; 4]; 64];
+	let mut px = [0, 0, 0, 255];
+	let mut pos = HEADER;
+	let mut run = 0;
+	while pixels.len() < total {
+		if run > 0 {
+			run -= 1;
+		} else {
+			let Some(&op) = data.get(pos) else {
+				return err("truncated data");
+			};
+			let arg = |n: usize| {
+				data.get(pos + n).copied().ok_or(WaytinierError::ImageDecode("truncated data"))
+			};
+			match op {
+				// QOI_OP_RGB
+				0xfe => {
+					px = [arg(1)?, arg(2)?, arg(3)?, px[3]];
+					pos += 4;
+				}
+				// QOI_OP_RGBA
+				0xff => {
+					px = [arg(1)?, arg(2)?, arg(3)?, arg(4)?];
+					pos += 5;
+				}
+				_ => {
+					pos += 1;
+					match op >> 6 {
+						// QOI_OP_INDEX
+						0 => px = index[op as usize],
+						// QOI_OP_DIFF
+						1 => {
+							px[0] = px[0].wrapping_add((op >> 4 & 3).wrapping_sub(2));
+							px[1] = px[1].wrapping_add((op >> 2 & 3).wrapping_sub(2));
+							px[2] = px[2].wrapping_add((op & 3).wrapping_sub(2));
+						}
+						// QOI_OP_LUMA
+						2 => {
+							let next = data
+								.get(pos)
+								.copied()
+								.ok_or(WaytinierError::ImageDecode("truncated data"))?;
+							pos += 1;
+							let dg = (op & 0x3f).wrapping_sub(32);
+							px[0] = px[0].wrapping_add(dg.wrapping_sub(8).wrapping_add(next >> 4));
+							px[1] = px[1].wrapping_add(dg);
+							px[2] = px[2].wrapping_add(dg.wrapping_sub(8).wrapping_add(next & 0xf));
+						}
+						// QOI_OP_RUN, this pixel plus the rest of the run
+						_ => run = op & 0x3f,
+					}
+				}
+			}
+			index[hash(px)] = px;
+		}
+		pixels.push(premultiply(px));
+	}
+	Ok(Image {
+		w,
+		h,
+		pixels,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decodes_every_op() {
+		let mut data = b"qoif".to_vec();
+		data.extend(4u32.to_be_bytes());
+		data.extend(2u32.to_be_bytes());
+		data.extend([4, 0]);
+		data.extend([
+			// rgb, then +1 -1 0 as a diff
+			0xfe, 10, 20, 30, 0x76,
+			// back to the first through the index, then half transparent
+			0x09, 0xff, 200, 100, 50, 128,
+			// luma, dg +8 dr-dg +1 db-dg -2, and a run for the rest of the row
+			0xa8, 0x96, 0xc2,
+		]);
+		data.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+		let image = decode(&data).unwrap();
+		assert_eq!(image.w, 4);
+		assert_eq!(
+			image.pixels,
+			[
+				0xff0a141e, 0xff0b131e, 0xff0a141e, 0x80643219, 0x8069361c, 0x8069361c, 0x8069361c,
+				0x8069361c
+			]
+		);
+		assert!(decode(&data[..20]).is_err());
+		// a huge size with a few bytes behind it
+		data[4..12].copy_from_slice(&[0, 0, 0x40, 0, 0, 0, 0x40, 0]);
+		assert!(decode(&data).is_err());
+	}
+}
@@ -1,3 +1,11 @@
 pub mod app;
+pub(crate) mod csd;
+pub(crate) mod cursor;
+pub(crate) mod damage;
+pub mod draw;
+pub mod headless;
+pub mod image;
+pub mod pixels;
 pub mod presenter;
+pub mod text;
 pub mod wizard;
@@ -0,0 +1,158 @@
+use crate::wayland::PixelFormat;
+
+mod sealed {
+	pub trait Sealed {}
+}
+
+// what a pixel of a format can be read as, one value per pixel. any bit pattern is valid
+pub trait Pixel: Copy + 'static + sealed::Sealed {}
+
+impl sealed::Sealed for u16 {}
+impl sealed::Sealed for u32 {}
+impl sealed::Sealed for u64 {}
+impl sealed::Sealed for [u8; 3] {}
+impl Pixel for u16 {}
+impl Pixel for u32 {}
+impl Pixel for u64 {}
+impl Pixel for [u8; 3] {}
+
+// the pixels of one frame, borrowed from the buffer for as long as the Snapshot lives.
+// rows are stride bytes apart, only the first w * format width bytes of each are pixels
+pub struct PixelBuffer<'a> {
+	data: &'a mut [u8],
+	w: u32,
+	h: u32,
+	stride: u32,
+	pf: PixelFormat,
+	// where the first row sits in the whole buffer, non zero for chunks
+	y: u32,
+}
+
+impl<'a> PixelBuffer<'a> {
+	pub(crate) fn new(data: &'a mut [u8], w: u32, h: u32, stride: u32, pf: PixelFormat) -> Self {
+		// a buffer that's smaller than it claims just gets fewer rows
+		let h = h.min((data.len() / stride.max(1) as usize) as u32);
+		Self {
+			data,
+			w,
+			h,
+			stride,
+			pf,
+			y: 0,
+		}
+	}
+
+	pub fn width(&self) -> u32 {
+		self.w
+	}
+
+	pub fn height(&self) -> u32 {
+		self.h
+	}
+
+	pub fn stride(&self) -> u32 {
+		self.stride
+	}
+
+	pub fn format(&self) -> PixelFormat {
+		self.pf
+	}
+
+	// the row this view starts at in the frame
+	pub fn y_offset(&self) -> u32 {
+		self.y
+	}
+
+	fn row_len(&self) -> usize {
+		(self.w * self.pf.width()) as usize
+	}
+
+	// bytes of one row, without the padding
+	pub fn row(&mut self, y: u32) -> Option<&mut [u8]> {
+		if y >= self.h {
+			return None;
+		}
+		let start = (y * self.stride) as usize;
+		let len = self.row_len();
+		self.data.get_mut(start..start + len)
+	}
+
+	pub fn rows(&mut self) -> impl Iterator<Item = &mut [u8]> {
+		let len = self.row_len();
+		self.data
+			.chunks_mut(self.stride.max(1) as usize)
+			.take(self.h as usize)
+			.map(move |row| &mut row[..len])
+	}
+
+	// the pixels of one row as T, None if T isn't the size of a pixel in this format
+	pub fn typed_row<T: Pixel>(&mut self, y: u32) -> Option<&mut [T]> {
+		if size_of::<T>() != self.pf.width() as usize {
+			return None;
+		}
+		cast(self.row(y)?)
+	}
+
+	pub fn typed_rows<T: Pixel>(&mut self) -> Option<impl Iterator<Item = &mut [T]>> {
+		if size_of::<T>() != self.pf.width() as usize {
+			return None;
+		}
+		let rows = self.rows().map(|row| cast(row)).collect::<Option<Vec<_>>>()?;
+		Some(rows.into_iter())
+	}
+
+	pub fn get<T: Pixel>(&mut self, x: u32, y: u32) -> Option<T> {
+		self.typed_row::<T>(y)?.get(x as usize).copied()
+	}
+
+	// false if out of bounds or T doesn't fit the format
+	pub fn set<T: Pixel>(&mut self, x: u32, y: u32, pixel: T) -> bool {
+		match self.typed_row::<T>(y).and_then(|row| row.get_mut(x as usize)) {
+			Some(px) => {
+				*px = pixel;
+				true
+			}
+			None => false,
+		}
+	}
+
+	pub fn fill<T: Pixel>(&mut self, pixel: T) -> bool {
+		let Some(rows) = self.typed_rows::<T>() else {
+			return false;
+		};
+		rows.for_each(|row| row.fill(pixel));
+		true
+	}
+
+	// splits the frame into views of at most rows_per_chunk rows each, which can be
+	// handed to different threads
+	pub fn row_chunks(&mut self, rows_per_chunk: u32) -> impl Iterator<Item = PixelBuffer<'_>> {
+		let (w, stride, pf, y, h) = (self.w, self.stride, self.pf, self.y, self.h);
+		// more than h is one chunk anyway, and keeps the byte count from overflowing
+		let rows_per_chunk = rows_per_chunk.min(h).max(1);
+		let chunk_len = (rows_per_chunk as usize * stride as usize).max(1);
+		let used = h as usize * stride as usize;
+		self.data[..used].chunks_mut(chunk_len).enumerate().map(move |(i, data)| {
+			let first = i as u32 * rows_per_chunk;
+			PixelBuffer {
+				data,
+				w,
+				h: rows_per_chunk.min(h - first),
+				stride,
+				pf,
+				y: y + first,
+			}
+		})
+	}
+
+	// the raw bytes, padding included
+	pub fn bytes(&mut self) -> &mut [u8] {
+		self.data
+	}
+}
+
+fn cast<T: Pixel>(bytes: &mut [u8]) -> Option<&mut [T]> {
+	// every Pixel is plain bytes, any pattern is a valid value
+	let (head, pixels, tail) = unsafe { bytes.align_to_mut::<T>() };
+	(head.is_empty() && tail.is_empty()).then_some(pixels)
+}
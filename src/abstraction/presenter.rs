@@ -2,16 +2,31 @@ use std::{any::Any, collections::HashMap};
 
 use crate::{
 	Rl,
+	abstraction::{app::Snapshot, csd::Decorations},
 	wayland::{
+		God,
 		buffer::BufferBackend,
 		callback::Callback,
+		cursor_shape::CursorShape,
+		decoration::ToplevelDecoration,
+		layer_shell::WlrLayerSurface,
+		output::Transform,
+		region::Region,
+		subcompositor::SubSurface,
 		surface::Surface,
-		xdg_shell::{surface::XdgSurface, toplevel::XdgTopLevel, wm_base::XdgWmBase},
+		xdg_shell::{
+			popup::XdgPopup,
+			surface::XdgSurface,
+			toplevel::{WindowState, XdgTopLevel},
+			wm_base::XdgWmBase,
+		},
 	},
 };
 
 pub enum Presenter {
 	TopLevelWindow(TopLevelWindow),
+	Popup(Popup),
+	LayerSurface(LayerSurface),
 }
 
 #[derive(Default)]
@@ -21,9 +36,11 @@ pub(crate) struct PresenterMap {
 }
 
 impl PresenterMap {
-	pub(crate) fn push(&mut self, to_push: Box<dyn PresenterObject>) {
-		self.inner.insert(self.last_id, to_push);
+	pub(crate) fn push(&mut self, to_push: Box<dyn PresenterObject>) -> usize {
+		let id = self.last_id;
+		self.inner.insert(id, to_push);
 		self.last_id += 1;
+		id
 	}
 }
 
@@ -31,17 +48,123 @@ pub trait PresenterObject {
 	fn is_finished(&self) -> bool;
 	fn set_finished(&mut self, finished: bool);
 	fn any(&mut self) -> &mut dyn Any;
+	fn canvas(&mut self) -> &mut Canvas;
+	// nothing gets drawn before the compositor's first configure
+	fn is_configured(&self) -> bool;
+	// what popups can be attached to
+	fn xdg_surface(&self) -> Option<Rl<XdgSurface>>;
+	// called once per App::work before drawing, to react to close requests and such
+	fn poll_close(&mut self) {}
+	// only toplevels have one
+	fn window_state(&self) -> Option<WindowState> {
+		None
+	}
+}
+
+// everything needed to draw into a presenter's wl_surface
+pub struct Canvas {
+	pub(crate) surface: Rl<Surface>,
+	pub(crate) backend: Rl<BufferBackend>,
+	pub(crate) frame: usize,
+	pub(crate) frame_cb: Option<Rl<Callback>>,
+	pub(crate) buffer_transform: BufferTransform,
+	pub(crate) subsurfaces: Vec<SubSurfaceLayer>,
+	pub(crate) opaque_region: Option<Region>,
+	// opaque formats get their whole surface marked opaque until told otherwise
+	pub(crate) auto_opaque: bool,
+	pub(crate) input_region: Option<Region>,
+	pub(crate) render_mode: RenderMode,
+	pub(crate) dirty: bool,
+	// buffer size of the last drawn frame
+	pub(crate) rendered_size: Option<(u32, u32)>,
+	pub(crate) decorations: Option<Decorations>,
+	pub(crate) cursor: CursorShape,
+}
+
+impl Canvas {
+	pub(crate) fn new(surface: Rl<Surface>, backend: Rl<BufferBackend>) -> Self {
+		Self {
+			surface,
+			backend,
+			frame: 0,
+			frame_cb: None,
+			buffer_transform: BufferTransform::default(),
+			subsurfaces: vec![],
+			opaque_region: None,
+			auto_opaque: true,
+			input_region: None,
+			render_mode: RenderMode::default(),
+			dirty: true,
+			rendered_size: None,
+			decorations: None,
+			cursor: CursorShape::default(),
+		}
+	}
+
+	// applied on the next frame
+	pub fn set_buffer_transform(&mut self, transform: BufferTransform) {
+		self.buffer_transform = transform;
+	}
+
+	// lets the compositor skip blending below these parts, None means nothing is opaque
+	pub fn set_opaque_region(&mut self, region: Option<Region>) {
+		self.opaque_region = region;
+		self.auto_opaque = false;
+	}
+
+	// None means the whole surface takes input, an empty region makes it click-through
+	pub fn set_input_region(&mut self, region: Option<Region>) {
+		self.input_region = region;
+	}
+
+	// the role object has to go between the subsurfaces and the surface itself
+	pub(crate) fn destroy_subsurfaces(&mut self, god: &mut God) {
+		for layer in self.subsurfaces.drain(..) {
+			layer.subsurface.borrow().destroy(god);
+			layer.surface.borrow_mut().destroy(god);
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+	// draw every time the compositor asks for a frame
+	#[default]
+	Continuous,
+	// only draw after App::invalidate or a resize
+	OnDemand,
+}
+
+pub(crate) type RenderCallback = Box<dyn FnMut(Snapshot)>;
+
+// a child surface drawn by the compositor on top of (or below) its parent
+pub(crate) struct SubSurfaceLayer {
+	pub(crate) subsurface: Rl<SubSurface>,
+	pub(crate) surface: Rl<Surface>,
+	pub(crate) backend: Rl<BufferBackend>,
+	pub(crate) render_cb: RenderCallback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferTransform {
+	Fixed(Transform),
+	// follow wl_surface.preferred_buffer_transform, usually the transform of the output
+	Preferred,
+}
+
+impl Default for BufferTransform {
+	fn default() -> Self {
+		Self::Fixed(Transform::Normal)
+	}
 }
 
 pub struct TopLevelWindow {
 	pub(crate) xdg_toplevel: Rl<XdgTopLevel>,
 	pub(crate) xdg_surface: Rl<XdgSurface>,
 	pub(crate) _xdg_wm_base: Rl<XdgWmBase>,
-	pub(crate) backend: Rl<BufferBackend>,
-	pub(crate) surface: Rl<Surface>,
+	pub(crate) decoration: Option<Rl<ToplevelDecoration>>,
+	pub(crate) canvas: Canvas,
 	pub(crate) close_cb: Box<dyn FnMut() -> bool>,
-	pub(crate) frame: usize,
-	pub(crate) frame_cb: Option<Rl<Callback>>,
 	pub(crate) finished: bool,
 }
 
@@ -57,4 +180,128 @@ impl PresenterObject for TopLevelWindow {
 	fn any(&mut self) -> &mut dyn Any {
 		self
 	}
+
+	fn canvas(&mut self) -> &mut Canvas {
+		&mut self.canvas
+	}
+
+	fn is_configured(&self) -> bool {
+		self.xdg_surface.borrow().is_configured
+	}
+
+	fn xdg_surface(&self) -> Option<Rl<XdgSurface>> {
+		Some(self.xdg_surface.clone())
+	}
+
+	// check if user wants to close window - the cb might not be a good idea
+	fn poll_close(&mut self) {
+		if self.xdg_toplevel.borrow().close_requested && (self.close_cb)() {
+			self.finished = true;
+		}
+	}
+
+	fn window_state(&self) -> Option<WindowState> {
+		Some(self.xdg_toplevel.borrow().state)
+	}
+}
+
+pub struct Popup {
+	pub(crate) xdg_popup: Rl<XdgPopup>,
+	pub(crate) xdg_surface: Rl<XdgSurface>,
+	// of the presenter it was opened from
+	pub(crate) parent: Rl<XdgSurface>,
+	pub(crate) canvas: Canvas,
+	pub(crate) finished: bool,
+}
+
+impl PresenterObject for Popup {
+	fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	fn set_finished(&mut self, finished: bool) {
+		self.finished = finished
+	}
+
+	fn any(&mut self) -> &mut dyn Any {
+		self
+	}
+
+	fn canvas(&mut self) -> &mut Canvas {
+		&mut self.canvas
+	}
+
+	fn is_configured(&self) -> bool {
+		self.xdg_surface.borrow().is_configured
+	}
+
+	fn xdg_surface(&self) -> Option<Rl<XdgSurface>> {
+		Some(self.xdg_surface.clone())
+	}
+
+	// the compositor dismissed the popup, e.g. the user clicked outside of it
+	fn poll_close(&mut self) {
+		if self.xdg_popup.borrow().done {
+			self.finished = true;
+		}
+	}
+}
+
+impl Popup {
+	// children before their parents, the popup role before the xdg_surface before the wl_surface
+	pub(crate) fn destroy(&mut self, god: &mut God) {
+		self.canvas.destroy_subsurfaces(god);
+		self.xdg_popup.borrow().destroy(god);
+		self.xdg_surface.borrow().destroy(god);
+		self.canvas.surface.borrow_mut().destroy(god);
+	}
+}
+
+// panels, docks, wallpapers and overlays, placed by the compositor relative to an output
+pub struct LayerSurface {
+	pub(crate) layer_surface: Rl<WlrLayerSurface>,
+	pub(crate) canvas: Canvas,
+	pub(crate) finished: bool,
+}
+
+impl PresenterObject for LayerSurface {
+	fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	fn set_finished(&mut self, finished: bool) {
+		self.finished = finished
+	}
+
+	fn any(&mut self) -> &mut dyn Any {
+		self
+	}
+
+	fn canvas(&mut self) -> &mut Canvas {
+		&mut self.canvas
+	}
+
+	fn is_configured(&self) -> bool {
+		self.layer_surface.borrow().is_configured
+	}
+
+	fn xdg_surface(&self) -> Option<Rl<XdgSurface>> {
+		None
+	}
+
+	// the compositor is done with it, e.g. its output went away
+	fn poll_close(&mut self) {
+		if self.layer_surface.borrow().closed {
+			self.finished = true;
+		}
+	}
+}
+
+impl LayerSurface {
+	// same order as for popups, the layer surface role before the wl_surface
+	pub(crate) fn destroy(&mut self, god: &mut God) {
+		self.canvas.destroy_subsurfaces(god);
+		self.layer_surface.borrow().destroy(god);
+		self.canvas.surface.borrow_mut().destroy(god);
+	}
 }
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use crate::{
+	abstraction::{
+		draw::{Color, Painter},
+		text::err,
+	},
+	wayland::WaytinierError,
+};
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+// the fixed part, newer versions may add fields after it
+const PSF2_HEADER: usize = 32;
+const PSF2_HAS_UNICODE_TABLE: usize = 1;
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_START_SEQUENCE: u8 = 0xfe;
+
+// 5x7, one byte per column with the top row in the lowest bit, ascii 0x20 to 0x7e
+const FONT: [[u8; 5]; 95] = [
+	[0x00, 0x00, 0x00, 0x00, 0x00],
+	[0x00, 0x00, 0x5f, 0x00, 0x00],
+	[0x00, 0x07, 0x00, 0x07, 0x00],
+	[0x14, 0x7f, 0x14, 0x7f, 0x14],
+	[0x24, 0x2a, 0x7f, 0x2a, 0x12],
+	[0x23, 0x13, 0x08, 0x64, 0x62],
+	[0x36, 0x49, 0x55, 0x22, 0x50],
+	[0x00, 0x05, 0x03, 0x00, 0x00],
+	[0x00, 0x1c, 0x22, 0x41, 0x00],
+	[0x00, 0x41, 0x22, 0x1c, 0x00],
+	[0x08, 0x2a, 0x1c, 0x2a, 0x08],
+	[0x08, 0x08, 0x3e, 0x08, 0x08],
+	[0x00, 0x50, 0x30, 0x00, 0x00],
+	[0x08, 0x08, 0x08, 0x08, 0x08],
+	[0x00, 0x60, 0x60, 0x00, 0x00],
+	[0x20, 0x10, 0x08, 0x04, 0x02],
+	[0x3e, 0x51, 0x49, 0x45, 0x3e],
+	[0x00, 0x42, 0x7f, 0x40, 0x00],
+	[0x42, 0x61, 0x51, 0x49, 0x46],
+	[0x21, 0x41, 0x45, 0x4b, 0x31],
+	[0x18, 0x14, 0x12, 0x7f, 0x10],
+	[0x27, 0x45, 0x45, 0x45, 0x39],
+	[0x3c, 0x4a, 0x49, 0x49, 0x30],
+	[0x01, 0x71, 0x09, 0x05, 0x03],
+	[0x36, 0x49, 0x49, 0x49, 0x36],
+	[0x06, 0x49, 0x49, 0x29, 0x1e],
+	[0x00, 0x36, 0x36, 0x00, 0x00],
+	[0x00, 0x56, 0x36, 0x00, 0x00],
+	[0x08, 0x14, 0x22, 0x41, 0x00],
+	[0x14, 0x14, 0x14, 0x14, 0x14],
+	[0x00, 0x41, 0x22, 0x14, 0x08],
+	[0x02, 0x01, 0x51, 0x09, 0x06],
+	[0x32, 0x49, 0x79, 0x41, 0x3e],
+	[0x7e, 0x11, 0x11, 0x11, 0x7e],
+	[0x7f, 0x49, 0x49, 0x49, 0x36],
+	[0x3e, 0x41, 0x41, 0x41, 0x22],
+	[0x7f, 0x41, 0x41, 0x22, 0x1c],
+	[0x7f, 0x49, 0x49, 0x49, 0x41],
+	[0x7f, 0x09, 0x09, 0x09, 0x01],
+	[0x3e, 0x41, 0x49, 0x49, 0x7a],
+	[0x7f, 0x08, 0x08, 0x08, 0x7f],
+	[0x00, 0x41, 0x7f, 0x41, 0x00],
+	[0x20, 0x40, 0x41, 0x3f, 0x01],
+	[0x7f, 0x08, 0x14, 0x22, 0x41],
+	[0x7f, 0x40, 0x40, 0x40, 0x40],
+	[0x7f, 0x02, 0x0c, 0x02, 0x7f],
+	[0x7f, 0x04, 0x08, 0x10, 0x7f],
+	[0x3e, 0x41, 0x41, 0x41, 0x3e],
+	[0x7f, 0x09, 0x09, 0x09, 0x06],
+	[0x3e, 0x41, 0x51, 0x21, 0x5e],
+	[0x7f, 0x09, 0x19, 0x29, 0x46],
+	[0x46, 0x49, 0x49, 0x49, 0x31],
+	[0x01, 0x01, 0x7f, 0x01, 0x01],
+	[0x3f, 0x40, 0x40, 0x40, 0x3f],
+	[0x1f, 0x20, 0x40, 0x20, 0x1f],
+	[0x3f, 0x40, 0x38, 0x40, 0x3f],
+	[0x63, 0x14, 0x08, 0x14, 0x63],
+	[0x07, 0x08, 0x70, 0x08, 0x07],
+	[0x61, 0x51, 0x49, 0x45, 0x43],
+	[0x00, 0x7f, 0x41, 0x41, 0x00],
+	[0x02, 0x04, 0x08, 0x10, 0x20],
+	[0x00, 0x41, 0x41, 0x7f, 0x00],
+	[0x04, 0x02, 0x01, 0x02, 0x04],
+	[0x40, 0x40, 0x40, 0x40, 0x40],
+	[0x00, 0x01, 0x02, 0x04, 0x00],
+	[0x20, 0x54, 0x54, 0x54, 0x78],
+	[0x7f, 0x48, 0x44, 0x44, 0x38],
+	[0x38, 0x44, 0x44, 0x44, 0x20],
+	[0x38, 0x44, 0x44, 0x48, 0x7f],
+	[0x38, 0x54, 0x54, 0x54, 0x18],
+	[0x08, 0x7e, 0x09, 0x01, 0x02],
+	[0x0c, 0x52, 0x52, 0x52, 0x3e],
+	[0x7f, 0x08, 0x04, 0x04, 0x78],
+	[0x00, 0x44, 0x7d, 0x40, 0x00],
+	[0x20, 0x40, 0x44, 0x3d, 0x00],
+	[0x7f, 0x10, 0x28, 0x44, 0x00],
+	[0x00, 0x41, 0x7f, 0x40, 0x00],
+	[0x7c, 0x04, 0x18, 0x04, 0x78],
+	[0x7c, 0x08, 0x04, 0x04, 0x78],
+	[0x38, 0x44, 0x44, 0x44, 0x38],
+	[0x7c, 0x14, 0x14, 0x14, 0x08],
+	[0x08, 0x14, 0x14, 0x18, 0x7c],
+	[0x7c, 0x08, 0x04, 0x04, 0x08],
+	[0x48, 0x54, 0x54, 0x54, 0x20],
+	[0x04, 0x3f, 0x44, 0x40, 0x20],
+	[0x3c, 0x40, 0x40, 0x20, 0x7c],
+	[0x1c, 0x20, 0x40, 0x20, 0x1c],
+	[0x3c, 0x40, 0x30, 0x40, 0x3c],
+	[0x44, 0x28, 0x10, 0x28, 0x44],
+	[0x0c, 0x50, 0x50, 0x50, 0x3c],
+	[0x44, 0x64, 0x54, 0x4c, 0x44],
+	[0x00, 0x08, 0x36, 0x41, 0x00],
+	[0x00, 0x00, 0x7f, 0x00, 0x00],
+	[0x00, 0x41, 0x36, 0x08, 0x00],
+	[0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+// one bit per pixel glyphs, rows top to bottom with the leftmost pixel in the highest bit
+pub(crate) struct BitmapFont {
+	pub(crate) w: u32,
+	pub(crate) h: u32,
+	pub(crate) advance: u32,
+	pub(crate) line_height: u32,
+	row_bytes: usize,
+	glyphs: Vec<u8>,
+	map: HashMap<char, u32>,
+	fallback: u32,
+}
+
+impl BitmapFont {
+	fn new(w: u32, h: u32, glyphs: Vec<u8>, map: HashMap<char, u32>) -> Self {
+		let fallback = map.get(&'?').copied().unwrap_or(0);
+		Self {
+			w,
+			h,
+			advance: w,
+			line_height: h,
+			row_bytes: w.div_ceil(8) as usize,
+			glyphs,
+			map,
+			fallback,
+		}
+	}
+
+	// the 5x7 ascii font, with a pixel between glyphs and two between lines
+	pub(crate) fn builtin() -> Self {
+		let glyphs = FONT
+			.iter()
+			.flat_map(|columns| {
+				(0..7).map(move |row| {
+					columns
+						.iter()
+						.enumerate()
+						.fold(0u8, |byte, (col, bits)| byte | ((bits >> row & 1) << (7 - col)))
+				})
+			})
+			.collect();
+		let map = (' '..='~').zip(0..).collect();
+		Self {
+			advance: 6,
+			line_height: 9,
+			..Self::new(5, 7, glyphs, map)
+		}
+	}
+
+	pub(crate) fn is_psf2(data: &[u8]) -> bool {
+		data.starts_with(&PSF2_MAGIC)
+	}
+
+	pub(crate) fn psf2(data: &[u8]) -> Result<Self, WaytinierError> {
+		let field = |i: usize| match data.get(i * 4..i * 4 + 4) {
+			Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize),
+			None => err("truncated psf2 header"),
+		};
+		let (header, flags, count, charsize, h, w) =
+			(field(2)?, field(3)?, field(4)?, field(5)?, field(6)?, field(7)?);
+		if header < PSF2_HEADER {
+			return err("bad psf2 header size");
+		}
+		if w == 0 || h == 0 || charsize < w.div_ceil(8) * h {
+			return err("bad psf2 glyph size");
+		}
+		let end = count.checked_mul(charsize).and_then(|len| len.checked_add(header));
+		let Some(end) = end.filter(|end| *end <= data.len()) else {
+			return err("truncated psf2 glyphs");
+		};
+		// glyphs are charsize apart, which may be more than the rows take
+		let rows = w.div_ceil(8) * h;
+		let glyphs =
+			data[header..end].chunks_exact(charsize).flat_map(|g| &g[..rows]).copied().collect();
+
+		let mut map = HashMap::new();
+		if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+			// per glyph, utf-8 chars then sequences that each start with 0xfe, until 0xff
+			let mut entries = data[end..].split(|b| *b == PSF2_SEPARATOR);
+			for glyph in 0..count as u32 {
+				let Some(entry) = entries.next() else {
+					break;
+				};
+				let singles = entry.split(|b| *b == PSF2_START_SEQUENCE).next().unwrap_or_default();
+				for c in String::from_utf8_lossy(singles).chars() {
+					map.entry(c).or_insert(glyph);
+				}
+			}
+		} else {
+			map = (0..count as u32).filter_map(|i| Some((char::from_u32(i)?, i))).collect();
+		}
+		Ok(Self::new(w as u32, h as u32, glyphs, map))
+	}
+
+	pub(crate) fn glyph(&self, c: char) -> u32 {
+		self.map.get(&c).copied().unwrap_or(self.fallback)
+	}
+
+	// whole pixels only, anything else would smear the glyphs
+	pub(crate) fn scale(&self, size: f32) -> f32 {
+		(size / self.h as f32).round().max(1.0)
+	}
+
+	pub(crate) fn draw(
+		&self,
+		painter: &mut Painter,
+		glyph: u32,
+		(x, top): (f32, f32),
+		scale: f32,
+		color: Color,
+	) {
+		let start = glyph as usize * self.row_bytes * self.h as usize;
+		let Some(rows) = self.glyphs.get(start..start + self.row_bytes * self.h as usize) else {
+			return;
+		};
+		let (x, top, cell) = (x.round() as i32, top.round() as i32, scale as i32);
+		for (row, bytes) in rows.chunks_exact(self.row_bytes).enumerate() {
+			for col in 0..self.w as usize {
+				if bytes[col / 8] & (0x80 >> (col % 8)) != 0 {
+					painter.fill_rect(
+						(x + col as i32 * cell, top + row as i32 * cell, cell, cell),
+						color,
+					);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// 10x2 glyphs, two bytes a row, padded to charsize
+	fn psf2(count: u32, charsize: u32, flags: u32, glyphs: &[u8], table: &[u8]) -> Vec<u8> {
+		let mut data = PSF2_MAGIC.to_vec();
+		for field in [0, PSF2_HEADER as u32, flags, count, charsize, 2, 10] {
+			data.extend(field.to_le_bytes());
+		}
+		data.extend(glyphs);
+		data.extend(table);
+		data
+	}
+
+	#[test]
+	fn reads_glyphs_and_unicode_table() {
+		let glyphs = [1, 2, 3, 4, 0, 0, 5, 6, 7, 8, 0, 0];
+		// glyph 0 is a, plus a with a combining accent as a sequence, which doesn't map
+		// the accent on its own. glyph 1 is ? and é
+		let table = [b'a', 0xfe, b'a', 0xcc, 0x81, 0xff, b'?', 0xc3, 0xa9, 0xff];
+		let font = BitmapFont::psf2(&psf2(2, 6, 1, &glyphs, &table)).unwrap();
+		assert_eq!((font.w, font.h, font.row_bytes), (10, 2, 2));
+		assert_eq!(font.glyphs, [1, 2, 3, 4, 5, 6, 7, 8]);
+		assert_eq!(font.glyph('a'), 0);
+		assert_eq!(font.glyph('é'), 1);
+		assert_eq!(font.glyph('\u{301}'), 1);
+		assert_eq!(font.glyph('z'), 1);
+	}
+
+	#[test]
+	fn maps_codepoints_without_a_table() {
+		let font = BitmapFont::psf2(&psf2(2, 4, 0, &[0; 8], &[])).unwrap();
+		assert_eq!(font.glyph('\u{1}'), 1);
+		assert_eq!(font.glyph('A'), 0);
+	}
+
+	#[test]
+	fn rejects_bad_headers() {
+		assert!(BitmapFont::psf2(&psf2(2, 4, 0, &[0; 8], &[])[..20]).is_err());
+		assert!(BitmapFont::psf2(&psf2(3, 4, 0, &[0; 8], &[])).is_err());
+		assert!(BitmapFont::psf2(&psf2(1, 3, 0, &[0; 8], &[])).is_err());
+		assert!(BitmapFont::psf2(&psf2(u32::MAX, u32::MAX, 0, &[0; 8], &[])).is_err());
+		let mut data = psf2(1, 4, 0, &[0; 4], &[]);
+		data[8] = 16;
+		assert!(BitmapFont::psf2(&data).is_err());
+	}
+}
@@ -0,0 +1,215 @@
+use std::{fs, path::Path};
+
+use crate::{
+	abstraction::{
+		draw::{Color, Painter},
+		image::inflate::gunzip,
+		text::{bitmap::BitmapFont, truetype::OutlineFont},
+	},
+	wayland::WaytinierError,
+};
+
+mod bitmap;
+mod truetype;
+
+// far more than any real font unpacks to
+const MAX_FONT: usize = 1 << 26;
+
+enum FontKind {
+	Bitmap(BitmapFont),
+	Outline(OutlineFont),
+}
+
+// a bitmap font (the builtin one or a psf2 console font) or a truetype font
+pub struct Font {
+	kind: FontKind,
+}
+
+impl Font {
+	// 5x7 ascii, always there
+	pub fn builtin() -> Self {
+		Self {
+			kind: FontKind::Bitmap(BitmapFont::builtin()),
+		}
+	}
+
+	// psf2 or truetype, either may be gzipped like the console fonts
+	pub fn decode(data: &[u8]) -> Result<Self, WaytinierError> {
+		if data.starts_with(&[0x1f, 0x8b]) {
+			let data = gunzip(data, MAX_FONT).or(err("bad gzip data"))?;
+			return Self::decode(&data);
+		}
+		let kind = if BitmapFont::is_psf2(data) {
+			FontKind::Bitmap(BitmapFont::psf2(data)?)
+		} else if OutlineFont::is_truetype(data) {
+			match OutlineFont::parse(data.to_vec()) {
+				Some(font) => FontKind::Outline(font),
+				None => return err("malformed or cff truetype font"),
+			}
+		} else {
+			return err("unknown format");
+		};
+		Ok(Self {
+			kind,
+		})
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, WaytinierError> {
+		Self::decode(&fs::read(path)?)
+	}
+
+	fn glyph(&self, c: char) -> u32 {
+		match &self.kind {
+			FontKind::Bitmap(font) => font.glyph(c),
+			FontKind::Outline(font) => font.glyph(c),
+		}
+	}
+
+	// everything below is in font units, pixels for bitmap fonts, times scale for pixels
+	fn scale(&self, size: f32) -> f32 {
+		match &self.kind {
+			FontKind::Bitmap(font) => font.scale(size),
+			FontKind::Outline(font) => size / font.units_per_em,
+		}
+	}
+
+	fn advance(&self, glyph: u32) -> f32 {
+		match &self.kind {
+			FontKind::Bitmap(font) => font.advance as f32,
+			FontKind::Outline(font) => font.advance(glyph),
+		}
+	}
+
+	fn kern(&self, left: u32, right: u32) -> f32 {
+		match &self.kind {
+			FontKind::Bitmap(_) => 0.0,
+			FontKind::Outline(font) => font.kern(left, right),
+		}
+	}
+
+	fn ascent(&self) -> f32 {
+		match &self.kind {
+			FontKind::Bitmap(font) => font.h as f32,
+			FontKind::Outline(font) => font.ascent,
+		}
+	}
+
+	fn line_units(&self) -> f32 {
+		match &self.kind {
+			FontKind::Bitmap(font) => font.line_height as f32,
+			FontKind::Outline(font) => font.ascent - font.descent + font.line_gap,
+		}
+	}
+
+	// pixels from one baseline to the next at this size
+	pub fn line_height(&self, size: f32) -> f32 {
+		self.line_units() * self.scale(size)
+	}
+
+	// size is the em height in pixels, bitmap fonts snap to whole multiples of theirs.
+	// lines break at newlines and, given a max width, between words
+	pub fn layout(&self, text: &str, size: f32, max_width: Option<f32>) -> TextLayout<'_> {
+		let scale = self.scale(size);
+		let line_height = self.line_units() * scale;
+		let mut layout = TextLayout {
+			font: self,
+			scale,
+			glyphs: vec![],
+			width: 0.0,
+			height: 0.0,
+		};
+		let mut baseline = self.ascent() * scale;
+		for paragraph in text.split('\n') {
+			// glyph, x, x past its end and whether it's a space
+			let mut line: Vec<(u32, f32, f32, bool)> = vec![];
+			let mut pen = 0.0;
+			let mut prev = None;
+			// where the word after the last space starts
+			let mut word = None;
+			for c in paragraph.chars() {
+				let glyph = self.glyph(c);
+				let advance = self.advance(glyph) * scale;
+				let kern = prev.map_or(0.0, |prev| self.kern(prev, glyph) * scale);
+				let space = c.is_whitespace();
+				if max_width.is_some_and(|max| pen + kern + advance > max)
+					&& !space && !line.is_empty()
+				{
+					// the last word goes to the next line, or this glyph if there's only one word
+					let split = word.filter(|at| *at < line.len()).unwrap_or(line.len());
+					let rest = line.split_off(split);
+					layout.push_line(&line, baseline);
+					baseline += line_height;
+					let shift = rest.first().map_or(pen, |g| g.1);
+					line = rest
+						.into_iter()
+						.map(|(g, x, end, s)| (g, x - shift, end - shift, s))
+						.collect();
+					pen -= shift;
+					word = None;
+				}
+				let x = if line.is_empty() {
+					pen
+				} else {
+					pen + kern
+				};
+				line.push((glyph, x, x + advance, space));
+				pen = x + advance;
+				prev = Some(glyph);
+				if space {
+					word = Some(line.len());
+				}
+			}
+			layout.push_line(&line, baseline);
+			baseline += line_height;
+		}
+		layout.height = baseline - self.ascent() * scale;
+		layout
+	}
+
+	fn draw_glyph(
+		&self,
+		painter: &mut Painter,
+		glyph: u32,
+		(x, baseline): (f32, f32),
+		scale: f32,
+		color: Color,
+	) {
+		match &self.kind {
+			FontKind::Bitmap(font) => {
+				font.draw(painter, glyph, (x, baseline - font.h as f32 * scale), scale, color)
+			}
+			FontKind::Outline(font) => font.draw(painter, glyph, (x, baseline), scale, color),
+		}
+	}
+}
+
+// glyphs placed relative to the top left corner of the text
+pub struct TextLayout<'a> {
+	font: &'a Font,
+	scale: f32,
+	// glyph, x, baseline
+	glyphs: Vec<(u32, f32, f32)>,
+	pub width: f32,
+	pub height: f32,
+}
+
+impl TextLayout<'_> {
+	fn push_line(&mut self, line: &[(u32, f32, f32, bool)], baseline: f32) {
+		// trailing spaces don't count towards the width
+		let end = line.iter().filter(|g| !g.3).map(|g| g.2).fold(0.0, f32::max);
+		self.width = self.width.max(end);
+		self.glyphs
+			.extend(line.iter().filter(|g| !g.3).map(|(glyph, x, ..)| (*glyph, *x, baseline)));
+	}
+
+	// color's alpha applies to the whole text
+	pub fn draw(&self, painter: &mut Painter, (x, y): (f32, f32), color: Color) {
+		for (glyph, gx, baseline) in &self.glyphs {
+			self.font.draw_glyph(painter, *glyph, (x + gx, y + baseline), self.scale, color);
+		}
+	}
+}
+
+pub(crate) fn err<T>(what: &'static str) -> Result<T, WaytinierError> {
+	Err(WaytinierError::FontDecode(what))
+}
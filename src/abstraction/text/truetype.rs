@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+
+use crate::abstraction::draw::{Color, Painter};
+
+// composites of composites deeper than this are broken fonts
+const MAX_COMPONENT_DEPTH: u32 = 8;
+
+// glyf flags
+const ON_CURVE: u8 = 0x01;
+const X_SHORT: u8 = 0x02;
+const Y_SHORT: u8 = 0x04;
+const REPEAT: u8 = 0x08;
+const X_SAME_OR_POSITIVE: u8 = 0x10;
+const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+// composite glyph flags
+const ARGS_ARE_WORDS: u16 = 0x0001;
+const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+const HAVE_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const HAVE_XY_SCALE: u16 = 0x0040;
+const HAVE_TWO_BY_TWO: u16 = 0x0080;
+
+// x, y in font units and whether the point is on the curve
+type Point = (f32, f32, bool);
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+	Some(u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]))
+}
+
+fn i16_at(data: &[u8], at: usize) -> Option<i16> {
+	u16_at(data, at).map(|v| v as i16)
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+	Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn f2dot14(data: &[u8], at: usize) -> Option<f32> {
+	Some(i16_at(data, at)? as f32 / 16384.0)
+}
+
+// glyf outlines only, fonts with cff outlines have no glyf table and get turned away
+pub(crate) struct OutlineFont {
+	data: Vec<u8>,
+	glyf: usize,
+	loca: usize,
+	long_loca: bool,
+	num_glyphs: u16,
+	hmtx: usize,
+	num_hmetrics: u16,
+	pub(crate) units_per_em: f32,
+	pub(crate) ascent: f32,
+	pub(crate) descent: f32,
+	pub(crate) line_gap: f32,
+	cmap: Cmap,
+	kerning: HashMap<(u16, u16), i16>,
+}
+
+impl OutlineFont {
+	pub(crate) fn is_truetype(data: &[u8]) -> bool {
+		matches!(data.get(..4), Some([0, 1, 0, 0] | b"true" | b"ttcf"))
+	}
+
+	pub(crate) fn parse(data: Vec<u8>) -> Option<Self> {
+		// collections are a list of fonts, the first one will do
+		let base = match data.get(..4)? {
+			b"ttcf" => u32_at(&data, 12)? as usize,
+			_ => 0,
+		};
+		let num_tables = u16_at(&data, base + 4)? as usize;
+		let table = |tag: &[u8; 4]| {
+			(0..num_tables).find_map(|i| {
+				let record = base + 12 + i * 16;
+				(data.get(record..record + 4)? == tag).then(|| u32_at(&data, record + 8))?
+			})
+		};
+		let head = table(b"head")? as usize;
+		let hhea = table(b"hhea")? as usize;
+		let maxp = table(b"maxp")? as usize;
+		let cmap = table(b"cmap")? as usize;
+		let kern = table(b"kern");
+		let mut font = Self {
+			glyf: table(b"glyf")? as usize,
+			loca: table(b"loca")? as usize,
+			long_loca: i16_at(&data, head + 50)? == 1,
+			num_glyphs: u16_at(&data, maxp + 4)?,
+			hmtx: table(b"hmtx")? as usize,
+			num_hmetrics: u16_at(&data, hhea + 34)?.max(1),
+			units_per_em: u16_at(&data, head + 18)?.max(1) as f32,
+			ascent: i16_at(&data, hhea + 4)? as f32,
+			descent: i16_at(&data, hhea + 6)? as f32,
+			line_gap: i16_at(&data, hhea + 8)? as f32,
+			cmap: parse_cmap(&data, cmap)?,
+			kerning: HashMap::new(),
+			data: vec![],
+		};
+		font.kerning = kern.and_then(|kern| parse_kern(&data, kern as usize)).unwrap_or_default();
+		font.data = data;
+		Some(font)
+	}
+
+	pub(crate) fn glyph(&self, c: char) -> u32 {
+		// glyph 0 is the font's own missing glyph, and what broken entries get
+		let glyph = self.cmap.lookup(&self.data, c as u32);
+		glyph.filter(|glyph| *glyph < self.num_glyphs as u32).unwrap_or(0)
+	}
+
+	pub(crate) fn advance(&self, glyph: u32) -> f32 {
+		let i = (glyph as u16).min(self.num_hmetrics - 1) as usize;
+		u16_at(&self.data, self.hmtx + i * 4).unwrap_or(0) as f32
+	}
+
+	pub(crate) fn kern(&self, left: u32, right: u32) -> f32 {
+		self.kerning.get(&(left as u16, right as u16)).copied().unwrap_or(0) as f32
+	}
+
+	fn glyph_range(&self, glyph: u16) -> Option<(usize, usize)> {
+		if glyph >= self.num_glyphs {
+			return None;
+		}
+		let offset = |g: usize| {
+			if self.long_loca {
+				u32_at(&self.data, self.loca + g * 4).map(|o| o as usize)
+			} else {
+				u16_at(&self.data, self.loca + g * 2).map(|o| o as usize * 2)
+			}
+		};
+		let (start, end) = (offset(glyph as usize)?, offset(glyph as usize + 1)?);
+		(end > start).then_some((self.glyf + start, self.glyf + end))
+	}
+
+	// contours in font units, y up
+	fn contours(&self, glyph: u16, depth: u32) -> Option<Vec<Vec<Point>>> {
+		let Some((at, _)) = self.glyph_range(glyph) else {
+			// spaces have no outline at all
+			return Some(vec![]);
+		};
+		let data = &self.data;
+		let num_contours = i16_at(data, at)?;
+		if num_contours < 0 {
+			return self.composite(at + 10, depth);
+		}
+		let ends = (0..num_contours as usize)
+			.map(|i| u16_at(data, at + 10 + i * 2).map(|e| e as usize))
+			.collect::<Option<Vec<_>>>()?;
+		let num_points = ends.last().map_or(0, |e| e + 1);
+		let instructions = at + 10 + ends.len() * 2;
+		let mut pos = instructions + 2 + u16_at(data, instructions)? as usize;
+
+		let mut flags = Vec::with_capacity(num_points);
+		while flags.len() < num_points {
+			let flag = *data.get(pos)?;
+			pos += 1;
+			let repeat = match flag & REPEAT {
+				0 => 1,
+				_ => {
+					pos += 1;
+					*data.get(pos - 1)? as usize + 1
+				}
+			};
+			flags.extend(std::iter::repeat_n(flag, repeat));
+		}
+		flags.truncate(num_points);
+
+		// deltas, one coordinate at a time, all x's come before all y's
+		let mut coords = |short: u8, same_or_positive: u8| -> Option<Vec<f32>> {
+			let mut v = 0i32;
+			flags
+				.iter()
+				.map(|flag| {
+					v += match (flag & short != 0, flag & same_or_positive != 0) {
+						(true, positive) => {
+							pos += 1;
+							let d = *data.get(pos - 1)? as i32;
+							if positive {
+								d
+							} else {
+								-d
+							}
+						}
+						(false, true) => 0,
+						(false, false) => {
+							pos += 2;
+							i16_at(data, pos - 2)? as i32
+						}
+					};
+					Some(v as f32)
+				})
+				.collect()
+		};
+		let xs = coords(X_SHORT, X_SAME_OR_POSITIVE)?;
+		let ys = coords(Y_SHORT, Y_SAME_OR_POSITIVE)?;
+
+		let mut start = 0;
+		let contours = ends
+			.iter()
+			.map(|end| {
+				let contour = (start..=*end)
+					.filter_map(|i| Some((*xs.get(i)?, *ys.get(i)?, flags.get(i)? & ON_CURVE != 0)))
+					.collect();
+				start = end + 1;
+				contour
+			})
+			.collect();
+		Some(contours)
+	}
+
+	// other glyphs placed with an affine transform each
+	fn composite(&self, mut at: usize, depth: u32) -> Option<Vec<Vec<Point>>> {
+		if depth > MAX_COMPONENT_DEPTH {
+			return None;
+		}
+		let data = &self.data;
+		let mut contours = vec![];
+		loop {
+			let flags = u16_at(data, at)?;
+			let glyph = u16_at(data, at + 2)?;
+			at += 4;
+			let (dx, dy) = match flags & ARGS_ARE_WORDS {
+				0 => {
+					at += 2;
+					(*data.get(at - 2)? as i8 as f32, *data.get(at - 1)? as i8 as f32)
+				}
+				_ => {
+					at += 4;
+					(i16_at(data, at - 4)? as f32, i16_at(data, at - 2)? as f32)
+				}
+			};
+			// otherwise the args are points to line up, which hinting cares about and we don't
+			let (dx, dy) = match flags & ARGS_ARE_XY_VALUES {
+				0 => (0.0, 0.0),
+				_ => (dx, dy),
+			};
+			let (a, b, c, d) = if flags & HAVE_SCALE != 0 {
+				at += 2;
+				let s = f2dot14(data, at - 2)?;
+				(s, 0.0, 0.0, s)
+			} else if flags & HAVE_XY_SCALE != 0 {
+				at += 4;
+				(f2dot14(data, at - 4)?, 0.0, 0.0, f2dot14(data, at - 2)?)
+			} else if flags & HAVE_TWO_BY_TWO != 0 {
+				at += 8;
+				(
+					f2dot14(data, at - 8)?,
+					f2dot14(data, at - 6)?,
+					f2dot14(data, at - 4)?,
+					f2dot14(data, at - 2)?,
+				)
+			} else {
+				(1.0, 0.0, 0.0, 1.0)
+			};
+			for contour in self.contours(glyph, depth + 1)? {
+				contours.push(
+					contour
+						.into_iter()
+						.map(|(x, y, on)| (a * x + c * y + dx, b * x + d * y + dy, on))
+						.collect(),
+				);
+			}
+			if flags & MORE_COMPONENTS == 0 {
+				return Some(contours);
+			}
+		}
+	}
+
+	pub(crate) fn draw(
+		&self,
+		painter: &mut Painter,
+		glyph: u32,
+		(x, baseline): (f32, f32),
+		scale: f32,
+		color: Color,
+	) {
+		let Some(contours) = self.contours(glyph as u16, 0) else {
+			return;
+		};
+		let to_pixels = |(fx, fy): (f32, f32)| (x + fx * scale, baseline - fy * scale);
+		let paths: Vec<Vec<(f32, f32)>> = contours
+			.iter()
+			.filter(|contour| !contour.is_empty())
+			.map(|contour| flatten(contour, scale).into_iter().map(to_pixels).collect())
+			.collect();
+		painter.fill_path(&paths, color);
+	}
+}
+
+// quadratic splines to line segments, off curve points in a row imply an on curve one between
+fn flatten(points: &[Point], scale: f32) -> Vec<(f32, f32)> {
+	let mid = |a: (f32, f32), b: (f32, f32)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+	let n = points.len();
+	// start on an on curve point, which also closes the contour at the end
+	let (start, first) = match points.iter().position(|p| p.2) {
+		Some(i) => ((points[i].0, points[i].1), i + 1),
+		None => (mid((points[0].0, points[0].1), (points[n - 1].0, points[n - 1].1)), 0),
+	};
+	let mut out = vec![start];
+	let mut control: Option<(f32, f32)> = None;
+	let quad = |out: &mut Vec<(f32, f32)>, c: (f32, f32), to: (f32, f32)| {
+		let from = *out.last().unwrap_or(&to);
+		let len = ((c.0 - from.0).hypot(c.1 - from.1) + (to.0 - c.0).hypot(to.1 - c.1)) * scale;
+		// about one segment per two pixels of curve
+		let steps = (len / 2.0).ceil().clamp(1.0, 16.0) as usize;
+		for i in 1..=steps {
+			let t = i as f32 / steps as f32;
+			let u = 1.0 - t;
+			out.push((
+				u * u * from.0 + 2.0 * u * t * c.0 + t * t * to.0,
+				u * u * from.1 + 2.0 * u * t * c.1 + t * t * to.1,
+			));
+		}
+	};
+	for k in 0..n {
+		let (px, py, on) = points[(first + k) % n];
+		match (on, control) {
+			(true, Some(c)) => quad(&mut out, c, (px, py)),
+			(true, None) => out.push((px, py)),
+			(false, Some(c)) => quad(&mut out, c, mid(c, (px, py))),
+			(false, None) => {}
+		}
+		control = (!on).then_some((px, py));
+	}
+	if let Some(c) = control {
+		quad(&mut out, c, start);
+	}
+	out
+}
+
+// the unicode subtable, searched where it lies since ranges can be as large as unicode
+enum Cmap {
+	// format 12, (first, last, glyph) groups sorted by first
+	Groups {
+		at: usize,
+		count: usize,
+	},
+	// format 4, 16 bit segments sorted by their ends
+	Segments {
+		at: usize,
+		count: usize,
+	},
+}
+
+// the first i in 0..count that isn't below, like slice::partition_point
+fn partition_point(count: usize, below: impl Fn(usize) -> bool) -> usize {
+	let (mut lo, mut hi) = (0, count);
+	while lo < hi {
+		let mid = lo + (hi - lo) / 2;
+		if below(mid) {
+			lo = mid + 1;
+		} else {
+			hi = mid;
+		}
+	}
+	lo
+}
+
+impl Cmap {
+	fn lookup(&self, data: &[u8], c: u32) -> Option<u32> {
+		match *self {
+			Cmap::Groups {
+				at,
+				count,
+			} => {
+				let group = |i: usize| at + 16 + i * 12;
+				let i =
+					partition_point(count, |i| u32_at(data, group(i) + 4).unwrap_or(u32::MAX) < c);
+				if i == count {
+					return None;
+				}
+				let (first, glyph) = (u32_at(data, group(i))?, u32_at(data, group(i) + 8)?);
+				glyph.checked_add(c.checked_sub(first)?)
+			}
+			Cmap::Segments {
+				at,
+				count,
+			} => {
+				let c = u16::try_from(c).ok()?;
+				let ends = at + 14;
+				let starts = ends + count * 2 + 2;
+				let deltas = starts + count * 2;
+				let range_offsets = deltas + count * 2;
+				let i =
+					partition_point(count, |i| u16_at(data, ends + i * 2).unwrap_or(u16::MAX) < c);
+				if i == count {
+					return None;
+				}
+				let first = u16_at(data, starts + i * 2)?;
+				if c < first {
+					return None;
+				}
+				let delta = u16_at(data, deltas + i * 2)?;
+				let range_offset = u16_at(data, range_offsets + i * 2)? as usize;
+				let glyph = match range_offset {
+					0 => c.wrapping_add(delta),
+					// an offset from where the range offset itself is stored
+					_ => match u16_at(
+						data,
+						range_offsets + i * 2 + range_offset + (c - first) as usize * 2,
+					)? {
+						0 => 0,
+						g => g.wrapping_add(delta),
+					},
+				};
+				Some(glyph as u32)
+			}
+		}
+	}
+}
+
+// a format 12 subtable if there is one, else format 4
+fn parse_cmap(data: &[u8], cmap: usize) -> Option<Cmap> {
+	let num_tables = u16_at(data, cmap + 2)? as usize;
+	let subtables: Vec<usize> = (0..num_tables)
+		.filter_map(|i| {
+			let record = cmap + 4 + i * 8;
+			let (platform, encoding) = (u16_at(data, record)?, u16_at(data, record + 2)?);
+			// unicode, or windows with unicode bmp or full repertoire
+			let unicode = platform == 0 || platform == 3 && matches!(encoding, 1 | 10);
+			unicode.then(|| u32_at(data, record + 4).map(|o| cmap + o as usize))?
+		})
+		.collect();
+	let format = |at: &usize| u16_at(data, *at);
+	// the whole subtable has to be there, so lookups only fail for missing characters
+	if let Some(&at) = subtables.iter().find(|at| format(at) == Some(12)) {
+		let count = u32_at(data, at + 12)? as usize;
+		data.get(at + 16..at.checked_add(16)?.checked_add(count.checked_mul(12)?)?)?;
+		Some(Cmap::Groups {
+			at,
+			count,
+		})
+	} else if let Some(&at) = subtables.iter().find(|at| format(at) == Some(4)) {
+		let count = u16_at(data, at + 6)? as usize / 2;
+		data.get(at + 14..at + 16 + count * 8)?;
+		Some(Cmap::Segments {
+			at,
+			count,
+		})
+	} else {
+		None
+	}
+}
+
+// pair kerning from the first horizontal format 0 subtable of the old kern table
+fn parse_kern(data: &[u8], kern: usize) -> Option<HashMap<(u16, u16), i16>> {
+	if u16_at(data, kern)? != 0 || u16_at(data, kern + 2)? == 0 {
+		return None;
+	}
+	let sub = kern + 4;
+	let coverage = u16_at(data, sub + 4)?;
+	if coverage >> 8 != 0 || coverage & 1 == 0 {
+		return None;
+	}
+	let pairs = u16_at(data, sub + 6)? as usize;
+	(0..pairs)
+		.map(|i| {
+			let pair = sub + 14 + i * 6;
+			Some(((u16_at(data, pair)?, u16_at(data, pair + 2)?), i16_at(data, pair + 4)?))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn be16(v: &[u16]) -> Vec<u8> {
+		v.iter().flat_map(|v| v.to_be_bytes()).collect()
+	}
+
+	// a square, a triangle with repeated flags and an off curve point, the square
+	// twice as a composite and a composite containing itself
+	fn glyphs() -> Vec<Vec<u8>> {
+		let square =
+			[be16(&[1, 0, 0, 100, 100, 3, 0]), vec![0x31, 0x33, 0x35, 0x23, 100, 100, 100]]
+				.concat();
+		let triangle =
+			[be16(&[1, 0, 0, 250, 300, 2, 0]), vec![0x39, 1, 0x00], be16(&[250, 300])].concat();
+		let composite = [
+			be16(&[0xffff, 0, 0, 0, 0]),
+			be16(&[ARGS_ARE_XY_VALUES | MORE_COMPONENTS, 1]),
+			vec![10, 20],
+			be16(&[ARGS_ARE_XY_VALUES | HAVE_SCALE, 1]),
+			vec![0, 0],
+			be16(&[0x2000]),
+		]
+		.concat();
+		let recursive = [be16(&[0xffff, 0, 0, 0, 0, 0, 4]), vec![0, 0]].concat();
+		vec![vec![], square, triangle, composite, recursive]
+	}
+
+	fn font(cmap: &[u8]) -> Vec<u8> {
+		let glyphs = glyphs();
+		let mut glyf = vec![];
+		let mut loca = vec![0];
+		for glyph in &glyphs {
+			glyf.extend(glyph);
+			glyf.resize(glyf.len().next_multiple_of(2), 0);
+			loca.push(glyf.len() as u16 / 2);
+		}
+		let mut head = vec![0; 54];
+		head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+		let mut hhea = vec![0; 36];
+		hhea[4..10].copy_from_slice(&be16(&[800, -200i16 as u16, 100]));
+		hhea[34..36].copy_from_slice(&2u16.to_be_bytes());
+		let maxp = be16(&[0, 1, glyphs.len() as u16]);
+		let tables = [
+			(b"cmap", cmap.to_vec()),
+			(b"glyf", glyf),
+			(b"head", head),
+			(b"hhea", hhea),
+			(b"hmtx", be16(&[500, 0, 600, 0])),
+			(b"loca", be16(&loca)),
+			(b"maxp", maxp),
+		];
+		let mut data = [vec![0, 1, 0, 0], be16(&[tables.len() as u16, 0, 0, 0])].concat();
+		let mut offset = 12 + tables.len() * 16;
+		for (tag, table) in &tables {
+			data.extend(*tag);
+			data.extend([0; 4]);
+			data.extend((offset as u32).to_be_bytes());
+			data.extend((table.len() as u32).to_be_bytes());
+			offset += table.len().next_multiple_of(4);
+		}
+		for (_, table) in &tables {
+			data.extend(table);
+			data.resize(data.len().next_multiple_of(4), 0);
+		}
+		data
+	}
+
+	// one windows unicode subtable right after the header
+	fn cmap(subtable: &[u8]) -> Vec<u8> {
+		[be16(&[0, 1, 3, 10, 0, 12]), subtable.to_vec()].concat()
+	}
+
+	fn format4() -> Vec<u8> {
+		// A-B by delta, x-y through the glyph array, and the closing 0xffff
+		let ends = [0x42, 0x79, 0xffff];
+		let starts = [0x41, 0x78, 0xffff];
+		let deltas = [1u16.wrapping_sub(0x41), 0, 1];
+		let range_offsets = [0, 4, 0];
+		let glyph_array = [3, 0];
+		cmap(
+			&[
+				be16(&[4, 0, 0, 6, 0, 0, 0]),
+				be16(&ends),
+				be16(&[0]),
+				be16(&starts),
+				be16(&deltas),
+				be16(&range_offsets),
+				be16(&glyph_array),
+			]
+			.concat(),
+		)
+	}
+
+	fn format12(groups: &[(u32, u32, u32)]) -> Vec<u8> {
+		let mut subtable = be16(&[12, 0, 0, 0, 0, 0]);
+		subtable.extend((groups.len() as u32).to_be_bytes());
+		for (first, last, glyph) in groups {
+			subtable.extend([first, last, glyph].iter().flat_map(|v| v.to_be_bytes()));
+		}
+		cmap(&subtable)
+	}
+
+	#[test]
+	fn cmap_format_4() {
+		let font = OutlineFont::parse(font(&format4())).unwrap();
+		let glyphs: Vec<u32> = "@ABCwxyz\u{ffff}".chars().map(|c| font.glyph(c)).collect();
+		assert_eq!(glyphs, [0, 1, 2, 0, 0, 3, 0, 0, 0]);
+	}
+
+	#[test]
+	fn cmap_format_12() {
+		let groups = [
+			(0x41, 0x42, 1),
+			(0x1d538, 0x1d538, 3),
+			// glyph ids past the font's own
+			(0x20000, 0x10ffef, 0),
+			(0x10fff0, 0x10ffff, u32::MAX - 2),
+		];
+		let font = OutlineFont::parse(font(&format12(&groups))).unwrap();
+		let glyphs: Vec<u32> =
+			['A', 'B', 'C', '𝔸', '\u{20001}', '\u{20010}', '\u{10fff0}', '\u{10ffff}']
+				.into_iter()
+				.map(|c| font.glyph(c))
+				.collect();
+		assert_eq!(glyphs, [1, 2, 0, 3, 1, 0, 0, 0]);
+	}
+
+	#[test]
+	fn cmap_must_fit_the_file() {
+		let mut cmap = format12(&[(0x41, 0x42, 1)]);
+		cmap[12 + 12..12 + 16].copy_from_slice(&u32::MAX.to_be_bytes());
+		assert!(OutlineFont::parse(font(&cmap)).is_none());
+	}
+
+	#[test]
+	fn glyf_contours() {
+		let font = OutlineFont::parse(font(&format4())).unwrap();
+		let square =
+			vec![(0.0, 0.0, true), (100.0, 0.0, true), (100.0, 100.0, true), (0.0, 100.0, true)];
+		assert_eq!(font.contours(0, 0).unwrap(), Vec::<Vec<Point>>::new());
+		assert_eq!(font.contours(1, 0).unwrap(), std::slice::from_ref(&square));
+		assert_eq!(
+			font.contours(2, 0).unwrap(),
+			[[(0.0, 0.0, true), (0.0, 0.0, true), (250.0, 300.0, false)]]
+		);
+		let moved: Vec<Point> = square.iter().map(|(x, y, on)| (x + 10.0, y + 20.0, *on)).collect();
+		let halved: Vec<Point> = square.iter().map(|(x, y, on)| (x / 2.0, y / 2.0, *on)).collect();
+		assert_eq!(font.contours(3, 0).unwrap(), [moved, halved]);
+		assert!(font.contours(4, 0).is_none());
+		assert_eq!((font.advance(1), font.advance(3)), (600.0, 600.0));
+	}
+}
@@ -1,15 +1,31 @@
 use crate::{
 	Rl, ShmBackend,
 	abstraction::{
-		app::App,
-		presenter::{PresenterObject, TopLevelWindow},
+		app::{App, Snapshot},
+		csd::{Decorations, TITLEBAR_HEIGHT},
+		presenter::{
+			BufferTransform, Canvas, LayerSurface, Popup, PresenterObject, RenderCallback,
+			RenderMode, SubSurfaceLayer, TopLevelWindow,
+		},
 	},
 	wait_for_sync,
 	wayland::{
 		PixelFormat, WaytinierError,
 		buffer::BufferBackend,
+		decoration::{DecorationMode, ToplevelDecoration},
+		fractional_scale::FractionalScale,
+		layer_shell::{KeyboardInteractivity, Layer, LayerAnchor, WlrLayerSurface},
+		region::Region,
+		subcompositor::SubSurface,
 		surface::Surface,
-		xdg_shell::{surface::XdgSurface, toplevel::XdgTopLevel, wm_base::XdgWmBase},
+		viewporter::Viewport,
+		xdg_shell::{
+			popup::XdgPopup,
+			positioner::{Positioner, XdgPositioner},
+			surface::XdgSurface,
+			toplevel::XdgTopLevel,
+			wm_base::XdgWmBase,
+		},
 	},
 };
 
@@ -23,6 +39,12 @@ pub struct TopLevelWindowWizard<'a> {
 	pub(crate) backend: Option<Rl<BufferBackend>>,
 	pub(crate) pf: Option<PixelFormat>,
 	pub(crate) xdg_wm_base: Option<Rl<XdgWmBase>>,
+	pub(crate) buffer_transform: BufferTransform,
+	pub(crate) opaque_region: Option<Option<Region>>,
+	pub(crate) input_region: Option<Region>,
+	pub(crate) render_mode: RenderMode,
+	pub(crate) decoration_mode: Option<DecorationMode>,
+	pub(crate) builtin_decorations: bool,
 }
 
 impl<'a> TopLevelWindowWizard<'a> {
@@ -37,6 +59,12 @@ impl<'a> TopLevelWindowWizard<'a> {
 			backend: None,
 			pf: None,
 			xdg_wm_base: None,
+			buffer_transform: BufferTransform::default(),
+			opaque_region: None,
+			input_region: None,
+			render_mode: RenderMode::default(),
+			decoration_mode: None,
+			builtin_decorations: false,
 		}
 	}
 
@@ -78,48 +106,512 @@ impl<'a> TopLevelWindowWizard<'a> {
 		self
 	}
 
+	pub fn with_buffer_transform(mut self, transform: BufferTransform) -> Self {
+		self.buffer_transform = transform;
+		self
+	}
+
 	pub fn with_backend(mut self, backend: &Rl<BufferBackend>) -> Self {
 		self.backend = Some(backend.clone());
 		self
 	}
 
+	pub fn with_opaque_region(mut self, region: Option<Region>) -> Self {
+		self.opaque_region = Some(region);
+		self
+	}
+
+	pub fn with_input_region(mut self, region: Region) -> Self {
+		self.input_region = Some(region);
+		self
+	}
+
+	pub fn with_render_mode(mut self, mode: RenderMode) -> Self {
+		self.render_mode = mode;
+		self
+	}
+
+	// a preference, check App::decoration_mode for what the compositor went with
+	pub fn with_decoration_mode(mut self, mode: DecorationMode) -> Self {
+		self.decoration_mode = Some(mode);
+		self
+	}
+
+	// draws our own titlebar and borders unless the compositor decorates the window.
+	// the size given to the wizard is what's left for the render closure
+	pub fn with_builtin_decorations(mut self) -> Self {
+		self.builtin_decorations = true;
+		self
+	}
+
 	pub fn spawn(self) -> Result<Box<dyn PresenterObject>, WaytinierError> {
+		let _xdg_wm_base = match self.xdg_wm_base {
+			Some(b) => b,
+			None => self.parent.xdg_wm_base()?,
+		};
+		let decoration_manager = self.parent.decoration_manager()?;
+		let backend = match self.backend {
+			Some(b) => b,
+			None => ShmBackend::new(self.parent)?,
+		};
 		let god = &mut self.parent.god;
-		let registry = &self.parent.registry;
 		let compositor = &self.parent.compositor;
-		let pf = self.pf.unwrap_or_default();
+		let pf = backend.borrow().choose_format(self.pf.unwrap_or_default());
 		let w = self.width.unwrap_or(800);
 		let h = self.height.unwrap_or(600);
 		let surface = Surface::new_registered_made(god, compositor, w, h, pf);
-		let _xdg_wm_base =
-			self.xdg_wm_base.unwrap_or(XdgWmBase::new_registered_bound(registry, god)?);
+		if let (Some(viewporter), Some(manager)) =
+			(&self.parent.viewporter, &self.parent.fractional_scale_manager)
+		{
+			let viewport = Viewport::new_registered_gotten(god, viewporter, &surface);
+			let fractional = FractionalScale::new_registered_gotten(god, manager, &surface);
+			let mut surf = surface.borrow_mut();
+			surf.viewport = Some(viewport);
+			surf.fractional = Some(fractional);
+		}
 		let xdg_surface = XdgSurface::new_registered(god, &_xdg_wm_base, &surface);
 		let xdg_toplevel = XdgTopLevel::new_registered_gotten(god, &xdg_surface);
-		if let Some(title) = self.title {
-			xdg_toplevel.borrow_mut().set_title(god, &title);
+		if let Some(title) = &self.title {
+			xdg_toplevel.borrow_mut().set_title(god, title);
 		};
 		if let Some(appid) = self.app_id {
 			xdg_toplevel.borrow_mut().set_app_id(god, &appid);
 		};
-		let backend = match self.backend {
-			Some(b) => b,
-			None => ShmBackend::new(self.parent)?,
-		};
+		// without a mode the compositor picks, but it only tells us if we ask at all
+		let decoration = decoration_manager.map(|manager| {
+			let decoration =
+				ToplevelDecoration::new_registered_gotten(god, &manager, &xdg_toplevel);
+			if let Some(mode) = self.decoration_mode {
+				decoration.borrow().set_mode(god, mode);
+			}
+			decoration
+		});
 		let mut god = &mut self.parent.god;
 		surface.borrow().commit(god);
 		wait_for_sync!(&self.parent.display, &mut god);
+		let mut canvas = Canvas::new(surface, backend);
+		canvas.set_buffer_transform(self.buffer_transform);
+		if let Some(region) = self.opaque_region {
+			canvas.set_opaque_region(region);
+		}
+		canvas.set_input_region(self.input_region);
+		canvas.render_mode = self.render_mode;
+		let server_side = decoration
+			.as_ref()
+			.is_some_and(|d| d.borrow().mode == Some(DecorationMode::ServerSide));
+		if self.builtin_decorations && !server_side {
+			let title = self.title.unwrap_or_default();
+			canvas.decorations =
+				Some(Decorations::new(self.parent, &canvas.surface, &xdg_surface, &title)?);
+			xdg_toplevel.borrow_mut().titlebar_height = TITLEBAR_HEIGHT;
+		}
+		// for cursors and the decorations
+		self.parent.pointer()?;
 		let tlw = TopLevelWindow {
 			_xdg_wm_base,
 
 			xdg_toplevel,
 			xdg_surface,
-			backend,
-			surface,
+			decoration,
+			canvas,
 			close_cb: Box::new(|| true),
-			frame: 0,
-			frame_cb: None,
 			finished: false,
 		};
 		Ok(Box::new(tlw))
 	}
 }
+
+pub struct SubSurfaceWizard<'a> {
+	pub(crate) parent: &'a mut App,
+	pub(crate) presenter_id: usize,
+	pub(crate) x: i32,
+	pub(crate) y: i32,
+	pub(crate) width: Option<u32>,
+	pub(crate) height: Option<u32>,
+	pub(crate) backend: Option<Rl<BufferBackend>>,
+	pub(crate) pf: Option<PixelFormat>,
+	pub(crate) desync: bool,
+	pub(crate) below: bool,
+	pub(crate) render_cb: Option<RenderCallback>,
+}
+
+impl<'a> SubSurfaceWizard<'a> {
+	// presenter_id is what App::push_presenter returned for the parent window
+	pub fn new(parent: &'a mut App, presenter_id: usize) -> Self {
+		Self {
+			parent,
+			presenter_id,
+			x: 0,
+			y: 0,
+			width: None,
+			height: None,
+			backend: None,
+			pf: None,
+			desync: false,
+			below: false,
+			render_cb: None,
+		}
+	}
+
+	pub fn with_position(mut self, x: i32, y: i32) -> Self {
+		self.x = x;
+		self.y = y;
+		self
+	}
+
+	pub fn with_width(mut self, width: u32) -> Self {
+		self.width = Some(width);
+		self
+	}
+
+	pub fn with_height(mut self, height: u32) -> Self {
+		self.height = Some(height);
+		self
+	}
+
+	pub fn with_pixel_format(mut self, pf: PixelFormat) -> Self {
+		self.pf = Some(pf);
+		self
+	}
+
+	pub fn with_backend(mut self, backend: &Rl<BufferBackend>) -> Self {
+		self.backend = Some(backend.clone());
+		self
+	}
+
+	pub fn with_desync(mut self) -> Self {
+		self.desync = true;
+		self
+	}
+
+	// puts the subsurface under the window's own surface, like a video layer under the ui
+	pub fn below_parent(mut self) -> Self {
+		self.below = true;
+		self
+	}
+
+	pub fn with_render_callback<F>(mut self, cb: F) -> Self
+	where
+		F: FnMut(Snapshot) + 'static,
+	{
+		self.render_cb = Some(Box::new(cb));
+		self
+	}
+
+	// returns the id of the subsurface within its window
+	pub fn spawn(self) -> Result<usize, WaytinierError> {
+		let subcompositor = self.parent.subcompositor()?;
+		let parent_surface = self
+			.parent
+			.canvas_mut(self.presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("presenter with this id"))?
+			.surface
+			.clone();
+		let backend = match self.backend {
+			Some(b) => b,
+			None => ShmBackend::new(self.parent)?,
+		};
+		let (w, h) = {
+			let parent = parent_surface.borrow();
+			(self.width.unwrap_or(parent.w), self.height.unwrap_or(parent.h))
+		};
+		let god = &mut self.parent.god;
+		let pf = backend.borrow().choose_format(self.pf.unwrap_or_default());
+		let surface = Surface::new_registered_made(god, &self.parent.compositor, w, h, pf);
+		if let Some(viewporter) = &self.parent.viewporter {
+			surface.borrow_mut().viewport =
+				Some(Viewport::new_registered_gotten(god, viewporter, &surface));
+		}
+		let subsurface =
+			SubSurface::new_registered_gotten(god, &subcompositor, &surface, &parent_surface);
+		{
+			let mut sub = subsurface.borrow_mut();
+			sub.set_position(god, self.x, self.y);
+			if self.below {
+				sub.place_below(god, parent_surface.borrow().id);
+			}
+			if self.desync {
+				sub.set_sync(god, false);
+			}
+		}
+		let layer = SubSurfaceLayer {
+			subsurface,
+			surface,
+			backend,
+			render_cb: self.render_cb.unwrap_or(Box::new(|_| {})),
+		};
+		let canvas = self
+			.parent
+			.canvas_mut(self.presenter_id)
+			.ok_or(WaytinierError::ExpectedSomeValue("presenter with this id"))?;
+		canvas.subsurfaces.push(layer);
+		Ok(canvas.subsurfaces.len() - 1)
+	}
+}
+
+pub struct PopupWizard<'a> {
+	pub(crate) parent: &'a mut App,
+	pub(crate) presenter_id: usize,
+	pub(crate) positioner: Positioner,
+	pub(crate) backend: Option<Rl<BufferBackend>>,
+	pub(crate) pf: Option<PixelFormat>,
+	pub(crate) grab: Option<u32>,
+}
+
+impl<'a> PopupWizard<'a> {
+	// presenter_id is the window or popup the new popup gets attached to
+	pub fn new(parent: &'a mut App, presenter_id: usize, positioner: Positioner) -> Self {
+		Self {
+			parent,
+			presenter_id,
+			positioner,
+			backend: None,
+			pf: None,
+			grab: None,
+		}
+	}
+
+	pub fn with_width(mut self, width: u32) -> Self {
+		self.positioner.size.0 = width as i32;
+		self
+	}
+
+	pub fn with_height(mut self, height: u32) -> Self {
+		self.positioner.size.1 = height as i32;
+		self
+	}
+
+	pub fn with_pixel_format(mut self, pf: PixelFormat) -> Self {
+		self.pf = Some(pf);
+		self
+	}
+
+	pub fn with_backend(mut self, backend: &Rl<BufferBackend>) -> Self {
+		self.backend = Some(backend.clone());
+		self
+	}
+
+	// takes keyboard focus and gets dismissed on clicks elsewhere, like menus do.
+	// serial is the one of the input event that opened the popup
+	pub fn with_grab(mut self, serial: u32) -> Self {
+		self.grab = Some(serial);
+		self
+	}
+
+	pub fn spawn(self) -> Result<Box<dyn PresenterObject>, WaytinierError> {
+		let parent_xdg_surface = {
+			let presenter = self
+				.parent
+				.presenters
+				.inner
+				.get_mut(&self.presenter_id)
+				.ok_or(WaytinierError::ExpectedSomeValue("presenter with this id"))?;
+			presenter
+				.xdg_surface()
+				.ok_or(WaytinierError::ExpectedSomeValue("parent with an xdg_surface"))?
+		};
+		let xdg_wm_base = self.parent.xdg_wm_base()?;
+		let seat = match self.grab {
+			Some(_) => Some(self.parent.seat()?),
+			None => None,
+		};
+		let backend = match self.backend {
+			Some(b) => b,
+			None => ShmBackend::new(self.parent)?,
+		};
+		let god = &mut self.parent.god;
+		let pf = backend.borrow().choose_format(self.pf.unwrap_or_default());
+		let (w, h) = self.positioner.size;
+		let surface =
+			Surface::new_registered_made(god, &self.parent.compositor, w as u32, h as u32, pf);
+		if let (Some(viewporter), Some(manager)) =
+			(&self.parent.viewporter, &self.parent.fractional_scale_manager)
+		{
+			let viewport = Viewport::new_registered_gotten(god, viewporter, &surface);
+			let fractional = FractionalScale::new_registered_gotten(god, manager, &surface);
+			let mut surf = surface.borrow_mut();
+			surf.viewport = Some(viewport);
+			surf.fractional = Some(fractional);
+		}
+		let xdg_surface = XdgSurface::new_registered(god, &xdg_wm_base, &surface);
+		let xdg_positioner =
+			XdgPositioner::new_registered_created(god, &xdg_wm_base, &self.positioner);
+		let xdg_popup = XdgPopup::new_registered_gotten(
+			god,
+			&xdg_surface,
+			Some(&parent_xdg_surface),
+			&xdg_positioner,
+		);
+		xdg_positioner.borrow().destroy(god);
+		if let (Some(serial), Some(seat)) = (self.grab, seat) {
+			xdg_popup.borrow().grab(god, seat.borrow().id, serial);
+		}
+		let mut god = &mut self.parent.god;
+		surface.borrow().commit(god);
+		wait_for_sync!(&self.parent.display, &mut god);
+		let popup = Popup {
+			xdg_popup,
+			xdg_surface,
+			parent: parent_xdg_surface,
+			canvas: Canvas::new(surface, backend),
+			finished: false,
+		};
+		Ok(Box::new(popup))
+	}
+}
+
+pub struct LayerSurfaceWizard<'a> {
+	pub(crate) parent: &'a mut App,
+	pub(crate) namespace: String,
+	pub(crate) layer: Layer,
+	pub(crate) output: Option<u32>,
+	pub(crate) width: Option<u32>,
+	pub(crate) height: Option<u32>,
+	pub(crate) anchor: LayerAnchor,
+	pub(crate) margin: (i32, i32, i32, i32),
+	pub(crate) exclusive_zone: i32,
+	pub(crate) keyboard_interactivity: KeyboardInteractivity,
+	pub(crate) backend: Option<Rl<BufferBackend>>,
+	pub(crate) pf: Option<PixelFormat>,
+	pub(crate) render_mode: RenderMode,
+}
+
+impl<'a> LayerSurfaceWizard<'a> {
+	// the namespace tells the compositor what the surface is for, e.g. "panel" or "wallpaper"
+	pub fn new(parent: &'a mut App, namespace: &str) -> Self {
+		Self {
+			parent,
+			namespace: String::from(namespace),
+			layer: Layer::default(),
+			output: None,
+			width: None,
+			height: None,
+			anchor: LayerAnchor::NONE,
+			margin: (0, 0, 0, 0),
+			exclusive_zone: 0,
+			keyboard_interactivity: KeyboardInteractivity::default(),
+			backend: None,
+			pf: None,
+			render_mode: RenderMode::default(),
+		}
+	}
+
+	pub fn with_layer(mut self, layer: Layer) -> Self {
+		self.layer = layer;
+		self
+	}
+
+	// registry name of the output, see OutputInfo::global. without one the compositor picks
+	pub fn on_output(mut self, output: u32) -> Self {
+		self.output = Some(output);
+		self
+	}
+
+	// leave a dimension unset to stretch it between opposite anchors
+	pub fn with_width(mut self, width: u32) -> Self {
+		self.width = Some(width);
+		self
+	}
+
+	pub fn with_height(mut self, height: u32) -> Self {
+		self.height = Some(height);
+		self
+	}
+
+	pub fn with_anchor(mut self, anchor: LayerAnchor) -> Self {
+		self.anchor = anchor;
+		self
+	}
+
+	// top, right, bottom, left
+	pub fn with_margin(mut self, margin: (i32, i32, i32, i32)) -> Self {
+		self.margin = margin;
+		self
+	}
+
+	// a bar anchored to one edge usually wants its own height here
+	pub fn with_exclusive_zone(mut self, zone: i32) -> Self {
+		self.exclusive_zone = zone;
+		self
+	}
+
+	pub fn with_keyboard_interactivity(mut self, mode: KeyboardInteractivity) -> Self {
+		self.keyboard_interactivity = mode;
+		self
+	}
+
+	pub fn with_pixel_format(mut self, pf: PixelFormat) -> Self {
+		self.pf = Some(pf);
+		self
+	}
+
+	pub fn with_backend(mut self, backend: &Rl<BufferBackend>) -> Self {
+		self.backend = Some(backend.clone());
+		self
+	}
+
+	pub fn with_render_mode(mut self, mode: RenderMode) -> Self {
+		self.render_mode = mode;
+		self
+	}
+
+	pub fn spawn(self) -> Result<Box<dyn PresenterObject>, WaytinierError> {
+		// the compositor can only pick a size along a dimension anchored on both ends
+		if self.width.is_none() && !self.anchor.contains(LayerAnchor::LEFT | LayerAnchor::RIGHT) {
+			return Err(WaytinierError::ExpectedSomeValue("a width or a left and right anchor"));
+		}
+		if self.height.is_none() && !self.anchor.contains(LayerAnchor::TOP | LayerAnchor::BOTTOM) {
+			return Err(WaytinierError::ExpectedSomeValue("a height or a top and bottom anchor"));
+		}
+		let shell = self.parent.layer_shell()?;
+		let output_id = self.parent.output_id(self.output)?;
+		let backend = match self.backend {
+			Some(b) => b,
+			None => ShmBackend::new(self.parent)?,
+		};
+		let god = &mut self.parent.god;
+		let pf = backend.borrow().choose_format(self.pf.unwrap_or_default());
+		let (w, h) = (self.width.unwrap_or(0), self.height.unwrap_or(0));
+		// stretched dimensions get their real size with the first configure
+		let surface =
+			Surface::new_registered_made(god, &self.parent.compositor, w.max(1), h.max(1), pf);
+		if let (Some(viewporter), Some(manager)) =
+			(&self.parent.viewporter, &self.parent.fractional_scale_manager)
+		{
+			let viewport = Viewport::new_registered_gotten(god, viewporter, &surface);
+			let fractional = FractionalScale::new_registered_gotten(god, manager, &surface);
+			let mut surf = surface.borrow_mut();
+			surf.viewport = Some(viewport);
+			surf.fractional = Some(fractional);
+		}
+		let layer_surface = WlrLayerSurface::new_registered_gotten(
+			god,
+			&shell,
+			&surface,
+			output_id,
+			self.layer,
+			&self.namespace,
+		);
+		{
+			let layer = layer_surface.borrow();
+			layer.set_size(god, w, h);
+			layer.set_anchor(god, self.anchor);
+			layer.set_margin(god, self.margin);
+			layer.set_exclusive_zone(god, self.exclusive_zone);
+			layer.set_keyboard_interactivity(god, self.keyboard_interactivity);
+		}
+		let mut god = &mut self.parent.god;
+		surface.borrow().commit(god);
+		wait_for_sync!(&self.parent.display, &mut god);
+		let mut canvas = Canvas::new(surface, backend);
+		canvas.render_mode = self.render_mode;
+		// for cursors
+		self.parent.pointer()?;
+		let layer = LayerSurface {
+			layer_surface,
+			canvas,
+			finished: false,
+		};
+		Ok(Box::new(layer))
+	}
+}
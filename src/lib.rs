@@ -98,8 +98,29 @@ macro_rules! rl {
 }
 
 pub use crate::abstraction::app::App;
+pub use crate::abstraction::app::AppEvent;
+pub use crate::abstraction::draw::{Color, Filter, Painter, Rect};
+pub use crate::abstraction::headless::{FrameCallback, FrameSink, Headless, MemoryBackend};
+pub use crate::abstraction::image::Image;
+pub use crate::abstraction::pixels::{Pixel, PixelBuffer};
+pub use crate::abstraction::presenter::BufferTransform;
+pub use crate::abstraction::presenter::RenderMode;
+pub use crate::abstraction::text::{Font, TextLayout};
+pub use crate::abstraction::wizard::LayerSurfaceWizard;
+pub use crate::abstraction::wizard::PopupWizard;
+pub use crate::abstraction::wizard::SubSurfaceWizard;
 pub use crate::abstraction::wizard::TopLevelWindowWizard;
 pub use crate::wayland::PixelFormat;
 pub use crate::wayland::buffer::BufferAccessor;
+pub use crate::wayland::cursor_shape::CursorShape;
+pub use crate::wayland::decoration::DecorationMode;
 pub use crate::wayland::dmabuf::backend::DmaBackend;
+pub use crate::wayland::layer_shell::{KeyboardInteractivity, Layer, LayerAnchor};
+pub use crate::wayland::region::Region;
 pub use crate::wayland::shm::ShmBackend;
+pub use crate::wayland::xdg_shell::positioner::{
+	Anchor, ConstraintAdjustment, Gravity, Positioner,
+};
+pub use crate::wayland::xdg_shell::toplevel::{
+	ResizeEdge, TiledEdges, WindowState, WmCapabilities,
+};
@@ -1,9 +1,9 @@
 use std::{os::fd::OwnedFd, rc::Rc};
 
 use crate::{
-	DebugLevel, DmaBackend, Rl, ShmBackend, Wl, handle_log, rl,
+	DebugLevel, DmaBackend, MemoryBackend, Rl, ShmBackend, Wl, handle_log, rl,
 	wayland::{
-		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		God, Id, OpCode, PixelFormat, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
 		registry::Registry,
 		surface::Surface,
 		wire::{Action, WireRequest},
@@ -13,6 +13,7 @@ use crate::{
 pub enum BufferBackend {
 	Shm(ShmBackend),
 	Dma(DmaBackend),
+	Memory(MemoryBackend),
 }
 
 impl BufferBackend {
@@ -32,6 +33,29 @@ impl BufferBackend {
 			BufferBackend::Dma(dma_backend) => {
 				dma_backend.make_buffer(god, w, h, surface, backend, registry)
 			}
+			BufferBackend::Memory(memory_backend) => {
+				memory_backend.make_buffer(w, h, surface, backend)
+			}
+		}
+	}
+
+	// the requested format if the compositor takes it, otherwise the closest it does
+	pub(crate) fn choose_format(&self, pf: PixelFormat) -> PixelFormat {
+		match self {
+			BufferBackend::Shm(shm_backend) => pf.fallback(&shm_backend.formats),
+			// the dmabuf backend checks the format against its feedback itself
+			BufferBackend::Dma(_) => pf,
+			// plain memory takes anything
+			BufferBackend::Memory(_) => pf,
+		}
+	}
+
+	// how many buffers a surface may cycle through, dmabufs stick to one for now
+	pub(crate) fn max_buffers(&self) -> usize {
+		match self {
+			BufferBackend::Shm(_) => 3,
+			BufferBackend::Dma(_) => 1,
+			BufferBackend::Memory(memory_backend) => memory_backend.buffers,
 		}
 	}
 
@@ -45,6 +69,18 @@ impl BufferBackend {
 		match self {
 			BufferBackend::Shm(shm_backend) => shm_backend.resize(god, buf, w, h),
 			BufferBackend::Dma(dma_backend) => dma_backend.resize(god, buf, w, h),
+			BufferBackend::Memory(memory_backend) => memory_backend.resize(buf, w, h),
+		}
+	}
+
+	pub(crate) fn destroy_buffer(&mut self, god: &mut God, buf: &Rl<Buffer>) {
+		match self {
+			BufferBackend::Shm(shm_backend) => {
+				shm_backend.pool.borrow_mut().destroy_buffer(god, buf)
+			}
+			BufferBackend::Dma(_) => god.wlmm.queue_request(buf.borrow().wl_destroy()),
+			// nothing was ever sent for it
+			BufferBackend::Memory(_) => {}
 		}
 	}
 }
@@ -52,6 +88,24 @@ impl BufferBackend {
 pub enum BufferAccessor {
 	ShmSlice(*mut [u8]),
 	DmaBufFd(OwnedFd),
+	// owned pixels nothing but this process sees, see MemoryBackend. u64s so rows
+	// can be read as u32s or u64s whatever the format
+	Memory(Vec<u64>),
+}
+
+impl BufferAccessor {
+	// the pixels as bytes, None for dmabufs which aren't mapped
+	pub(crate) fn bytes(&mut self) -> Option<&mut [u8]> {
+		match self {
+			// the slice is exactly this buffer, and nothing else touches it while it's borrowed
+			BufferAccessor::ShmSlice(slice) => Some(unsafe { &mut **slice }),
+			// every u64 is 8 plain bytes, and u8 has no alignment to break
+			BufferAccessor::Memory(data) => Some(unsafe {
+				std::slice::from_raw_parts_mut(data.as_mut_ptr().cast::<u8>(), data.len() * 8)
+			}),
+			BufferAccessor::DmaBufFd(_) => None,
+		}
+	}
 }
 
 pub(crate) struct Buffer {
@@ -60,6 +114,8 @@ pub(crate) struct Buffer {
 	pub(crate) w: u32,
 	pub(crate) h: u32,
 	pub(crate) in_use: bool,
+	// the surface's draw count when this was last drawn into, None if its contents are garbage
+	pub(crate) drawn_at: Option<usize>,
 	pub(crate) master: Wl<Surface>,
 	pub(crate) backend: Rl<BufferBackend>,
 	pub(crate) accessor: Option<BufferAccessor>,
@@ -79,6 +135,7 @@ impl Buffer {
 			w: width,
 			h: height,
 			in_use: false,
+			drawn_at: None,
 			master: Rc::downgrade(master),
 			backend: backend.clone(),
 			accessor,
@@ -98,6 +155,14 @@ impl Buffer {
 		Ok(buf)
 	}
 
+	// bytes taken up in its shm pool, None for dmabufs
+	pub(crate) fn shm_len(&self) -> Option<usize> {
+		match &self.accessor {
+			Some(BufferAccessor::ShmSlice(slice)) => Some(slice.len()),
+			_ => None,
+		}
+	}
+
 	pub(crate) fn wl_destroy(&self) -> WireRequest {
 		WireRequest {
 			sender_id: self.id,
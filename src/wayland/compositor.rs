@@ -3,7 +3,8 @@ use std::os::fd::OwnedFd;
 use crate::{
 	Rl, rl,
 	wayland::{
-		God, Id, OpCode, Raw, WaytinierError, WaylandObject, WaylandObjectKind,
+		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		region::{Region, RegionObject},
 		registry::Registry,
 		wire::{Action, WireArgument, WireRequest},
 	},
@@ -11,17 +12,20 @@ use crate::{
 
 pub(crate) struct Compositor {
 	pub(crate) id: Id,
+	// wl_surfaces made from this have the same version
+	pub(crate) version: u32,
 }
 
 impl Compositor {
-	pub(crate) fn new(id: Id) -> Rl<Self> {
+	pub(crate) fn new(id: Id, version: u32) -> Rl<Self> {
 		rl!(Self {
 			id,
+			version,
 		})
 	}
 
-	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
-		let compositor = Self::new(Id(0));
+	pub(crate) fn new_registered(god: &mut God, version: u32) -> Rl<Self> {
+		let compositor = Self::new(Id(0), version);
 		let id = god.wlim.new_id_registered(compositor.clone());
 		compositor.borrow_mut().id = id;
 		compositor
@@ -31,8 +35,15 @@ impl Compositor {
 		god: &mut God,
 		registry: &Rl<Registry>,
 	) -> Result<Rl<Self>, WaytinierError> {
-		let compositor = Self::new_registered(god);
-		registry.borrow_mut().bind(god, compositor.borrow().id, compositor.borrow().kind(), 5)?;
+		// v6 for preferred_buffer_scale and preferred_buffer_transform
+		let version = registry.borrow().version_capped(WaylandObjectKind::Compositor, 6)?;
+		let compositor = Self::new_registered(god, version);
+		registry.borrow_mut().bind(
+			god,
+			compositor.borrow().id,
+			compositor.borrow().kind(),
+			version,
+		)?;
 		Ok(compositor)
 	}
 
@@ -49,6 +60,23 @@ impl Compositor {
 	pub(crate) fn create_surface(&self, god: &mut God, id: Id) {
 		god.wlmm.queue_request(self.wl_create_surface(id));
 	}
+
+	fn wl_create_region(&self, id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "create_region",
+			args: vec![WireArgument::NewId(id)],
+		}
+	}
+
+	pub(crate) fn create_region(&self, god: &mut God, region: &Region) -> Rl<RegionObject> {
+		let obj = RegionObject::new_registered(god);
+		god.wlmm.queue_request(self.wl_create_region(obj.borrow().id));
+		obj.borrow().apply(god, region);
+		obj
+	}
 }
 
 impl WaylandObject for Compositor {
@@ -0,0 +1,210 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	Rl, rl,
+	wayland::{
+		God, Id, OpCode, WaylandObject, WaylandObjectKind, WaytinierError,
+		pointer::Pointer,
+		registry::Registry,
+		wire::{Action, WireArgument, WireRequest},
+	},
+};
+
+// the css cursor names, as in wp_cursor_shape_device_v1 v1
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CursorShape {
+	#[default]
+	Default = 1,
+	ContextMenu,
+	Help,
+	Pointer,
+	Progress,
+	Wait,
+	Cell,
+	Crosshair,
+	Text,
+	VerticalText,
+	Alias,
+	Copy,
+	Move,
+	NoDrop,
+	NotAllowed,
+	Grab,
+	Grabbing,
+	EResize,
+	NResize,
+	NeResize,
+	NwResize,
+	SResize,
+	SeResize,
+	SwResize,
+	WResize,
+	EwResize,
+	NsResize,
+	NeswResize,
+	NwseResize,
+	ColResize,
+	RowResize,
+	AllScroll,
+	ZoomIn,
+	ZoomOut,
+}
+
+impl CursorShape {
+	// what cursor themes call it
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Default => "default",
+			Self::ContextMenu => "context-menu",
+			Self::Help => "help",
+			Self::Pointer => "pointer",
+			Self::Progress => "progress",
+			Self::Wait => "wait",
+			Self::Cell => "cell",
+			Self::Crosshair => "crosshair",
+			Self::Text => "text",
+			Self::VerticalText => "vertical-text",
+			Self::Alias => "alias",
+			Self::Copy => "copy",
+			Self::Move => "move",
+			Self::NoDrop => "no-drop",
+			Self::NotAllowed => "not-allowed",
+			Self::Grab => "grab",
+			Self::Grabbing => "grabbing",
+			Self::EResize => "e-resize",
+			Self::NResize => "n-resize",
+			Self::NeResize => "ne-resize",
+			Self::NwResize => "nw-resize",
+			Self::SResize => "s-resize",
+			Self::SeResize => "se-resize",
+			Self::SwResize => "sw-resize",
+			Self::WResize => "w-resize",
+			Self::EwResize => "ew-resize",
+			Self::NsResize => "ns-resize",
+			Self::NeswResize => "nesw-resize",
+			Self::NwseResize => "nwse-resize",
+			Self::ColResize => "col-resize",
+			Self::RowResize => "row-resize",
+			Self::AllScroll => "all-scroll",
+			Self::ZoomIn => "zoom-in",
+			Self::ZoomOut => "zoom-out",
+		}
+	}
+}
+
+pub(crate) struct CursorShapeManager {
+	pub(crate) id: Id,
+}
+
+impl CursorShapeManager {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let manager = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(manager.clone());
+		manager.borrow_mut().id = id;
+		manager
+	}
+
+	pub(crate) fn new_registered_bound(
+		god: &mut God,
+		registry: &Rl<Registry>,
+	) -> Result<Rl<Self>, WaytinierError> {
+		let manager = Self::new_registered(god);
+		registry.borrow_mut().bind(god, manager.borrow().id, manager.borrow().kind(), 1)?;
+		Ok(manager)
+	}
+
+	fn wl_get_pointer(&self, id: Id, pointer_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "get_pointer",
+			args: vec![WireArgument::NewId(id), WireArgument::Obj(pointer_id)],
+		}
+	}
+
+	pub(crate) fn get_pointer(&self, god: &mut God, id: Id, pointer_id: Id) {
+		god.wlmm.queue_request(self.wl_get_pointer(id, pointer_id));
+	}
+}
+
+impl WaylandObject for CursorShapeManager {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::CursorShapeManager
+	}
+}
+
+pub(crate) struct CursorShapeDevice {
+	pub(crate) id: Id,
+}
+
+impl CursorShapeDevice {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let device = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(device.clone());
+		device.borrow_mut().id = id;
+		device
+	}
+
+	pub(crate) fn new_registered_gotten(
+		god: &mut God,
+		manager: &Rl<CursorShapeManager>,
+		pointer: &Rl<Pointer>,
+	) -> Rl<Self> {
+		let device = Self::new_registered(god);
+		manager.borrow().get_pointer(god, device.borrow().id, pointer.borrow().id);
+		device
+	}
+
+	fn wl_set_shape(&self, serial: u32, shape: CursorShape) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "set_shape",
+			args: vec![WireArgument::UnInt(serial), WireArgument::UnInt(shape as u32)],
+		}
+	}
+
+	// serial has to be the one of the latest pointer enter
+	pub(crate) fn set_shape(&self, god: &mut God, serial: u32, shape: CursorShape) {
+		god.wlmm.queue_request(self.wl_set_shape(serial, shape));
+	}
+}
+
+impl WaylandObject for CursorShapeDevice {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::CursorShapeDevice
+	}
+}
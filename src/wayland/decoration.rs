@@ -0,0 +1,175 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	DebugLevel, Rl, handle_log, rl,
+	wayland::{
+		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		registry::Registry,
+		wire::{Action, FromWirePayload, WireArgument, WireRequest},
+		xdg_shell::toplevel::XdgTopLevel,
+	},
+};
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecorationMode {
+	// the app draws its own titlebar and borders
+	#[default]
+	ClientSide = 1,
+	ServerSide,
+}
+
+impl DecorationMode {
+	pub(crate) fn from_u32(processee: u32) -> Result<Self, WaytinierError> {
+		match processee {
+			1 => Ok(Self::ClientSide),
+			2 => Ok(Self::ServerSide),
+			_ => Err(WaytinierError::InvalidEnumVariant("DecorationMode")),
+		}
+	}
+}
+
+pub(crate) struct DecorationManager {
+	pub(crate) id: Id,
+}
+
+impl DecorationManager {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let manager = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(manager.clone());
+		manager.borrow_mut().id = id;
+		manager
+	}
+
+	pub(crate) fn new_registered_bound(
+		god: &mut God,
+		registry: &Rl<Registry>,
+	) -> Result<Rl<Self>, WaytinierError> {
+		let manager = Self::new_registered(god);
+		registry.borrow_mut().bind(god, manager.borrow().id, manager.borrow().kind(), 1)?;
+		Ok(manager)
+	}
+
+	fn wl_get_toplevel_decoration(&self, id: Id, toplevel_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "get_toplevel_decoration",
+			args: vec![WireArgument::NewId(id), WireArgument::Obj(toplevel_id)],
+		}
+	}
+
+	pub(crate) fn get_toplevel_decoration(&self, god: &mut God, id: Id, toplevel_id: Id) {
+		god.wlmm.queue_request(self.wl_get_toplevel_decoration(id, toplevel_id));
+	}
+}
+
+impl WaylandObject for DecorationManager {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::DecorationManager
+	}
+}
+
+pub(crate) struct ToplevelDecoration {
+	pub(crate) id: Id,
+	// what the compositor settled on, None until the first configure
+	pub(crate) mode: Option<DecorationMode>,
+}
+
+impl ToplevelDecoration {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+			mode: None,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let decoration = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(decoration.clone());
+		decoration.borrow_mut().id = id;
+		decoration
+	}
+
+	// has to happen before the toplevel's first commit
+	pub(crate) fn new_registered_gotten(
+		god: &mut God,
+		manager: &Rl<DecorationManager>,
+		toplevel: &Rl<XdgTopLevel>,
+	) -> Rl<Self> {
+		let decoration = Self::new_registered(god);
+		manager.borrow().get_toplevel_decoration(god, decoration.borrow().id, toplevel.borrow().id);
+		decoration
+	}
+
+	fn wl_set_mode(&self, mode: DecorationMode) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "set_mode",
+			args: vec![WireArgument::UnInt(mode as u32)],
+		}
+	}
+
+	// only a preference, the configure event has the final say
+	pub(crate) fn set_mode(&self, god: &mut God, mode: DecorationMode) {
+		god.wlmm.queue_request(self.wl_set_mode(mode));
+	}
+
+	fn wl_unset_mode(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(2),
+			opname: "unset_mode",
+			args: vec![],
+		}
+	}
+
+	// leaves the choice to the compositor
+	pub(crate) fn unset_mode(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_unset_mode());
+	}
+}
+
+impl WaylandObject for ToplevelDecoration {
+	fn handle(
+		&mut self,
+		payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		let mut pending = vec![];
+		match opcode.raw() {
+			// configure
+			0 => {
+				let mode = DecorationMode::from_u32(u32::from_wire(payload)?)?;
+				self.mode = Some(mode);
+				handle_log!(pending, self, DebugLevel::Important, format!("configure // {mode:?}"));
+			}
+			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
+		}
+		Ok(pending)
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::ToplevelDecoration
+	}
+}
@@ -78,7 +78,7 @@ impl DmaBackend {
 
 		let params_rc = DmaParams::new_registered_gotten(god, &dmabuf);
 		god.handle_events()?;
-		let stride = w * pf.width();
+		let stride = pf.stride(w);
 		let modf = {
 			let fb = feedback.borrow();
 			fb.format_indices
@@ -129,6 +129,7 @@ impl DmaBackend {
 		let mut buf_b = buf.borrow_mut();
 		buf_b.w = w;
 		buf_b.h = h;
+		buf_b.drawn_at = None;
 		let id = god.wlim.new_id_registered(buf.clone());
 		buf_b.id = id;
 		Ok(())
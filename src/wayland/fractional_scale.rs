@@ -0,0 +1,144 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	DebugLevel, Rl, handle_log, rl,
+	wayland::{
+		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		registry::Registry,
+		surface::Surface,
+		wire::{Action, FromWirePayload, WireArgument, WireRequest},
+	},
+};
+
+pub(crate) struct FractionalScaleManager {
+	pub(crate) id: Id,
+}
+
+impl FractionalScaleManager {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let manager = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(manager.clone());
+		manager.borrow_mut().id = id;
+		manager
+	}
+
+	pub(crate) fn new_registered_bound(
+		god: &mut God,
+		registry: &Rl<Registry>,
+	) -> Result<Rl<Self>, WaytinierError> {
+		let manager = Self::new_registered(god);
+		registry.borrow_mut().bind(god, manager.borrow().id, manager.borrow().kind(), 1)?;
+		Ok(manager)
+	}
+
+	fn wl_get_fractional_scale(&self, id: Id, surface_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "get_fractional_scale",
+			args: vec![WireArgument::NewId(id), WireArgument::Obj(surface_id)],
+		}
+	}
+
+	pub(crate) fn get_fractional_scale(&self, god: &mut God, id: Id, surface_id: Id) {
+		god.wlmm.queue_request(self.wl_get_fractional_scale(id, surface_id));
+	}
+}
+
+impl WaylandObject for FractionalScaleManager {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::FractionalScaleManager
+	}
+}
+
+pub(crate) struct FractionalScale {
+	pub(crate) id: Id,
+	// numerator of a fraction with a denominator of 120
+	pub(crate) preferred: Option<u32>,
+}
+
+impl FractionalScale {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+			preferred: None,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let scale = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(scale.clone());
+		scale.borrow_mut().id = id;
+		scale
+	}
+
+	pub(crate) fn new_registered_gotten(
+		god: &mut God,
+		manager: &Rl<FractionalScaleManager>,
+		surface: &Rl<Surface>,
+	) -> Rl<Self> {
+		let scale = Self::new_registered(god);
+		manager.borrow().get_fractional_scale(god, scale.borrow().id, surface.borrow().id);
+		scale
+	}
+
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn destroy(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_destroy());
+	}
+}
+
+impl WaylandObject for FractionalScale {
+	fn handle(
+		&mut self,
+		payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		let mut pending = vec![];
+		match opcode.raw() {
+			// preferred_scale
+			0 => {
+				let scale = u32::from_wire(payload)?;
+				self.preferred = Some(scale);
+				handle_log!(
+					pending,
+					self,
+					DebugLevel::Trivial,
+					format!("preferred scale {scale}/120")
+				);
+			}
+			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
+		}
+		Ok(pending)
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::FractionalScale
+	}
+}
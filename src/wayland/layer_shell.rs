@@ -0,0 +1,340 @@
+use std::{ops::BitOr, os::fd::OwnedFd};
+
+use crate::{
+	DebugLevel, Rl, handle_log, qpush, rl,
+	wayland::{
+		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		registry::Registry,
+		surface::Surface,
+		wire::{Action, FromWirePayload, WireArgument, WireRequest},
+	},
+};
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layer {
+	Background = 0,
+	Bottom,
+	#[default]
+	Top,
+	Overlay,
+}
+
+// the edges a layer surface sticks to, anchoring to opposite edges stretches it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LayerAnchor(pub u32);
+
+impl LayerAnchor {
+	pub const NONE: Self = Self(0);
+	pub const TOP: Self = Self(1);
+	pub const BOTTOM: Self = Self(2);
+	pub const LEFT: Self = Self(4);
+	pub const RIGHT: Self = Self(8);
+
+	pub(crate) fn contains(&self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+impl BitOr for LayerAnchor {
+	type Output = Self;
+
+	fn bitor(self, rhs: Self) -> Self {
+		Self(self.0 | rhs.0)
+	}
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyboardInteractivity {
+	#[default]
+	None = 0,
+	// takes all keyboard input while on the top or overlay layer, like lock screens
+	Exclusive,
+	// focusable like a regular window, needs v4
+	OnDemand,
+}
+
+pub(crate) struct WlrLayerShell {
+	pub(crate) id: Id,
+	pub(crate) version: u32,
+}
+
+impl WlrLayerShell {
+	pub(crate) fn new_registered_bound(
+		god: &mut God,
+		registry: &Rl<Registry>,
+	) -> Result<Rl<Self>, WaytinierError> {
+		let mut reg = registry.borrow_mut();
+		// v2 for set_layer, v4 for on demand keyboard focus
+		let version = reg.version_capped(WaylandObjectKind::WlrLayerShell, 4)?;
+		let obj = rl!(Self {
+			id: Id(0),
+			version,
+		});
+		let id = god.wlim.new_id_registered(obj.clone());
+		obj.borrow_mut().id = id;
+		reg.bind(god, id, WaylandObjectKind::WlrLayerShell, version)?;
+		Ok(obj)
+	}
+
+	fn wl_get_layer_surface(
+		&self,
+		id: Id,
+		surface_id: Id,
+		output_id: Id,
+		layer: Layer,
+		namespace: &str,
+	) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "get_layer_surface",
+			args: vec![
+				WireArgument::NewId(id),
+				WireArgument::Obj(surface_id),
+				WireArgument::Obj(output_id),
+				WireArgument::UnInt(layer as u32),
+				WireArgument::String(String::from(namespace)),
+			],
+		}
+	}
+}
+
+impl WaylandObject for WlrLayerShell {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::WlrLayerShell
+	}
+}
+
+pub(crate) struct WlrLayerSurface {
+	pub(crate) id: Id,
+	pub(crate) parent: Rl<Surface>,
+	pub(crate) version: u32,
+	pub(crate) is_configured: bool,
+	pub(crate) closed: bool,
+	// the last configured size, 0 leaves that dimension up to us
+	pub(crate) size: (u32, u32),
+}
+
+impl WlrLayerSurface {
+	pub(crate) fn new(id: Id, parent: Rl<Surface>, version: u32) -> Rl<Self> {
+		rl!(Self {
+			id,
+			parent,
+			version,
+			is_configured: false,
+			closed: false,
+			size: (0, 0),
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God, parent: Rl<Surface>, version: u32) -> Rl<Self> {
+		let new = Self::new(Id(0), parent, version);
+		let id = god.wlim.new_id_registered(new.clone());
+		new.borrow_mut().id = id;
+		new
+	}
+
+	// output Id::default() lets the compositor pick, usually the focused one
+	pub(crate) fn new_registered_gotten(
+		god: &mut God,
+		shell: &Rl<WlrLayerShell>,
+		surface: &Rl<Surface>,
+		output_id: Id,
+		layer: Layer,
+		namespace: &str,
+	) -> Rl<Self> {
+		let shell = shell.borrow();
+		let new = Self::new_registered(god, surface.clone(), shell.version);
+		let (id, surface_id) = (new.borrow().id, surface.borrow().id);
+		god.wlmm
+			.queue_request(shell.wl_get_layer_surface(id, surface_id, output_id, layer, namespace));
+		new
+	}
+
+	fn wl_set_size(&self, w: u32, h: u32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "set_size",
+			args: vec![WireArgument::UnInt(w), WireArgument::UnInt(h)],
+		}
+	}
+
+	// 0 for a dimension anchored on both sides stretches it between them
+	pub(crate) fn set_size(&self, god: &mut God, w: u32, h: u32) {
+		god.wlmm.queue_request(self.wl_set_size(w, h));
+	}
+
+	fn wl_set_anchor(&self, anchor: LayerAnchor) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "set_anchor",
+			args: vec![WireArgument::UnInt(anchor.0)],
+		}
+	}
+
+	pub(crate) fn set_anchor(&self, god: &mut God, anchor: LayerAnchor) {
+		god.wlmm.queue_request(self.wl_set_anchor(anchor));
+	}
+
+	fn wl_set_exclusive_zone(&self, zone: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(2),
+			opname: "set_exclusive_zone",
+			args: vec![WireArgument::Int(zone)],
+		}
+	}
+
+	// how much of the anchored edge other surfaces should keep clear of,
+	// -1 ignores everyone else's exclusive zones too
+	pub(crate) fn set_exclusive_zone(&self, god: &mut God, zone: i32) {
+		god.wlmm.queue_request(self.wl_set_exclusive_zone(zone));
+	}
+
+	fn wl_set_margin(&self, (top, right, bottom, left): (i32, i32, i32, i32)) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(3),
+			opname: "set_margin",
+			args: vec![
+				WireArgument::Int(top),
+				WireArgument::Int(right),
+				WireArgument::Int(bottom),
+				WireArgument::Int(left),
+			],
+		}
+	}
+
+	// top, right, bottom, left - only matters on anchored edges
+	pub(crate) fn set_margin(&self, god: &mut God, margin: (i32, i32, i32, i32)) {
+		god.wlmm.queue_request(self.wl_set_margin(margin));
+	}
+
+	fn wl_set_keyboard_interactivity(&self, mode: KeyboardInteractivity) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(4),
+			opname: "set_keyboard_interactivity",
+			args: vec![WireArgument::UnInt(mode as u32)],
+		}
+	}
+
+	pub(crate) fn set_keyboard_interactivity(&self, god: &mut God, mode: KeyboardInteractivity) {
+		// before v4 on demand would be a protocol error, no focus is the closest
+		let mode = match mode {
+			KeyboardInteractivity::OnDemand if self.version < 4 => KeyboardInteractivity::None,
+			mode => mode,
+		};
+		god.wlmm.queue_request(self.wl_set_keyboard_interactivity(mode));
+	}
+
+	fn wl_ack_configure(&self, serial: u32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(6),
+			opname: "ack_configure",
+			args: vec![WireArgument::UnInt(serial)],
+		}
+	}
+
+	fn wl_set_layer(&self, layer: Layer) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(8),
+			opname: "set_layer",
+			args: vec![WireArgument::UnInt(layer as u32)],
+		}
+	}
+
+	pub(crate) fn set_layer(&self, god: &mut God, layer: Layer) -> Result<(), WaytinierError> {
+		if self.version < 2 {
+			return Err(WaytinierError::ExpectedSomeValue("zwlr_layer_shell_v1 version 2"));
+		}
+		god.wlmm.queue_request(self.wl_set_layer(layer));
+		Ok(())
+	}
+
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(7),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn destroy(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_destroy());
+	}
+}
+
+impl WaylandObject for WlrLayerSurface {
+	fn handle(
+		&mut self,
+		payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		let mut pending = vec![];
+		match opcode.raw() {
+			// configure
+			0 => {
+				let serial = u32::from_wire(payload)?;
+				let w = u32::from_wire(&payload[4..])?;
+				let h = u32::from_wire(&payload[8..])?;
+				handle_log!(
+					pending,
+					self,
+					DebugLevel::Important,
+					format!("configure // w: {w}, h: {h}, acking")
+				);
+				self.is_configured = true;
+				self.size = (w, h);
+				qpush!(pending, self.wl_ack_configure(serial));
+				// a 0 means we keep our own size for that dimension
+				let (current, (w, h)) = {
+					let surface = self.parent.borrow();
+					let w = Some(w).filter(|w| *w > 0).unwrap_or(surface.w);
+					let h = Some(h).filter(|h| *h > 0).unwrap_or(surface.h);
+					((surface.w, surface.h), (w, h))
+				};
+				if current != (w, h) {
+					pending.push(Action::Resize(w, h, self.parent.clone()));
+				}
+			}
+			// closed
+			1 => {
+				self.closed = true;
+				handle_log!(pending, self, DebugLevel::Important, String::from("closed"));
+			}
+			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
+		}
+		Ok(pending)
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::WlrLayerSurface
+	}
+}
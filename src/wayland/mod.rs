@@ -1,5 +1,5 @@
 use std::{
-	collections::{HashMap, VecDeque},
+	collections::{HashMap, HashSet, VecDeque},
 	error::Error,
 	fmt::Display,
 	os::fd::OwnedFd,
@@ -15,13 +15,23 @@ use crate::{
 pub(crate) mod buffer;
 pub(crate) mod callback;
 pub(crate) mod compositor;
+pub mod cursor_shape;
+pub mod decoration;
 pub(crate) mod display;
 pub(crate) mod dmabuf;
+pub(crate) mod fractional_scale;
+pub mod layer_shell;
+pub mod output;
+pub(crate) mod pointer;
+pub mod region;
 pub(crate) mod registry;
+pub(crate) mod seat;
 pub mod shm;
+pub(crate) mod subcompositor;
 pub(crate) mod surface;
+pub(crate) mod viewporter;
 pub(crate) mod wire;
-pub(crate) mod xdg_shell;
+pub mod xdg_shell;
 
 #[derive(Clone, Copy, Debug)]
 pub struct OpCode(pub(crate) u32);
@@ -88,6 +98,9 @@ pub enum WaytinierError {
 	Dylib(libloading::Error),
 	FdExpected,
 	NullPtr(&'static str),
+	ImageDecode(&'static str),
+	FontDecode(&'static str),
+	NotPresentable,
 }
 
 pub trait ExpectRc<T> {
@@ -165,6 +178,11 @@ impl Display for WaytinierError {
 			WaytinierError::Dylib(er) => write!(f, "libloading error occured: {er}"),
 			WaytinierError::FdExpected => write!(f, "expected fd"),
 			WaytinierError::NullPtr(er) => write!(f, "null pointer at {er}"),
+			WaytinierError::ImageDecode(er) => write!(f, "couldn't decode image: {er}"),
+			WaytinierError::FontDecode(er) => write!(f, "couldn't load font: {er}"),
+			WaytinierError::NotPresentable => {
+				write!(f, "memory buffers can't be shown by a compositor")
+			}
 		}
 	}
 }
@@ -185,6 +203,24 @@ pub enum WaylandObjectKind {
 	DmaFeedback,
 	Callback,
 	DmaParams,
+	Output,
+	Viewporter,
+	Viewport,
+	FractionalScaleManager,
+	FractionalScale,
+	SubCompositor,
+	SubSurface,
+	Region,
+	XdgPositioner,
+	XdgPopup,
+	Seat,
+	DecorationManager,
+	ToplevelDecoration,
+	Pointer,
+	CursorShapeManager,
+	CursorShapeDevice,
+	WlrLayerShell,
+	WlrLayerSurface,
 }
 
 impl Display for WaylandObjectKind {
@@ -210,6 +246,24 @@ impl WaylandObjectKind {
 			WaylandObjectKind::DmaFeedback => "zwp_linux_dmabuf_feedback_v1",
 			WaylandObjectKind::Callback => "wl_callback",
 			WaylandObjectKind::DmaParams => "zwp_linux_buffer_params_v1",
+			WaylandObjectKind::Output => "wl_output",
+			WaylandObjectKind::Viewporter => "wp_viewporter",
+			WaylandObjectKind::Viewport => "wp_viewport",
+			WaylandObjectKind::FractionalScaleManager => "wp_fractional_scale_manager_v1",
+			WaylandObjectKind::FractionalScale => "wp_fractional_scale_v1",
+			WaylandObjectKind::SubCompositor => "wl_subcompositor",
+			WaylandObjectKind::SubSurface => "wl_subsurface",
+			WaylandObjectKind::Region => "wl_region",
+			WaylandObjectKind::XdgPositioner => "xdg_positioner",
+			WaylandObjectKind::XdgPopup => "xdg_popup",
+			WaylandObjectKind::Seat => "wl_seat",
+			WaylandObjectKind::DecorationManager => "zxdg_decoration_manager_v1",
+			WaylandObjectKind::ToplevelDecoration => "zxdg_toplevel_decoration_v1",
+			WaylandObjectKind::Pointer => "wl_pointer",
+			WaylandObjectKind::CursorShapeManager => "wp_cursor_shape_manager_v1",
+			WaylandObjectKind::CursorShapeDevice => "wp_cursor_shape_device_v1",
+			WaylandObjectKind::WlrLayerShell => "zwlr_layer_shell_v1",
+			WaylandObjectKind::WlrLayerSurface => "zwlr_layer_surface_v1",
 		}
 	}
 }
@@ -304,11 +358,23 @@ impl Drop for IdentManager {
 
 // impl<T> Boxed for T {}
 
+// named after the drm fourccs, components listed from the most significant bit of a
+// little endian word. Argb888 and Xrgb888 are ARGB8888 and XRGB8888
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub enum PixelFormat {
 	#[default]
 	Argb888,
 	Xrgb888,
+	Abgr8888,
+	Xbgr8888,
+	Rgb565,
+	Argb2101010,
+	Xrgb2101010,
+	// half floats, for hdr
+	Abgr16161616f,
+	// packed, 3 bytes per pixel
+	Rgb888,
+	Bgr888,
 }
 
 pub(crate) const fn fourcc_code(a: u8, b: u8, c: u8, d: u8) -> u32 {
@@ -320,6 +386,21 @@ pub(crate) const fn fourcc_code(a: u8, b: u8, c: u8, d: u8) -> u32 {
 }
 
 impl PixelFormat {
+	pub(crate) const ALL: [Self; 10] = [
+		Self::Argb888,
+		Self::Xrgb888,
+		Self::Abgr8888,
+		Self::Xbgr8888,
+		Self::Rgb565,
+		Self::Argb2101010,
+		Self::Xrgb2101010,
+		Self::Abgr16161616f,
+		Self::Rgb888,
+		Self::Bgr888,
+	];
+
+	// wl_shm has its own values for the two formats every compositor has to support,
+	// everything else is the fourcc
 	pub(crate) fn from_shm(processee: u32) -> Result<Self, WaytinierError> {
 		match processee {
 			0 => Ok(Self::Argb888),
@@ -328,27 +409,75 @@ impl PixelFormat {
 		}
 	}
 
-	const CC4_ARGB888: u32 = Self::Argb888.to_fourcc();
-	const CC4_XRGB888: u32 = Self::Xrgb888.to_fourcc();
+	pub(crate) const fn to_shm(self) -> u32 {
+		match self {
+			Self::Argb888 => 0,
+			Self::Xrgb888 => 1,
+			_ => self.to_fourcc(),
+		}
+	}
+
 	pub(crate) const fn from_u32(processee: u32) -> Result<Self, WaytinierError> {
-		match processee {
-			x if x == Self::CC4_ARGB888 => Ok(Self::Argb888),
-			x if x == Self::CC4_XRGB888 => Ok(Self::Xrgb888),
-			_ => Err(WaytinierError::ExoticOrInvalidPixelFormat),
+		let mut i = 0;
+		while i < Self::ALL.len() {
+			if Self::ALL[i].to_fourcc() == processee {
+				return Ok(Self::ALL[i]);
+			}
+			i += 1;
 		}
+		Err(WaytinierError::ExoticOrInvalidPixelFormat)
 	}
 
-	pub(crate) const fn width(&self) -> u32 {
+	// bytes per pixel
+	pub const fn width(&self) -> u32 {
 		match self {
-			Self::Argb888 => 4,
-			Self::Xrgb888 => 4,
+			Self::Rgb565 => 2,
+			Self::Rgb888 | Self::Bgr888 => 3,
+			Self::Abgr16161616f => 8,
+			_ => 4,
 		}
 	}
 
+	// bytes per row, padded to 4 so packed formats stay word aligned
+	pub const fn stride(&self, w: u32) -> u32 {
+		(w * self.width()).next_multiple_of(4)
+	}
+
+	pub const fn has_alpha(&self) -> bool {
+		matches!(self, Self::Argb888 | Self::Abgr8888 | Self::Argb2101010 | Self::Abgr16161616f)
+	}
+
 	pub(crate) const fn to_fourcc(&self) -> u32 {
 		match self {
-			Self::Argb888 => fourcc_code(b'X', b'R', b'2', b'4'),
+			Self::Argb888 => fourcc_code(b'A', b'R', b'2', b'4'),
 			Self::Xrgb888 => fourcc_code(b'X', b'R', b'2', b'4'),
+			Self::Abgr8888 => fourcc_code(b'A', b'B', b'2', b'4'),
+			Self::Xbgr8888 => fourcc_code(b'X', b'B', b'2', b'4'),
+			Self::Rgb565 => fourcc_code(b'R', b'G', b'1', b'6'),
+			Self::Argb2101010 => fourcc_code(b'A', b'R', b'3', b'0'),
+			Self::Xrgb2101010 => fourcc_code(b'X', b'R', b'3', b'0'),
+			Self::Abgr16161616f => fourcc_code(b'A', b'B', b'4', b'H'),
+			Self::Rgb888 => fourcc_code(b'R', b'G', b'2', b'4'),
+			Self::Bgr888 => fourcc_code(b'B', b'G', b'2', b'4'),
+		}
+	}
+
+	// the closest format out of the supported ones, argb8888 and xrgb8888 are always there
+	pub(crate) fn fallback(&self, supported: &HashSet<Self>) -> Self {
+		if supported.contains(self) {
+			return *self;
+		}
+		// same alpha and the deepest that isn't wider than asked for. on a tie the later one
+		// in ALL wins, so 10 bit channels beat 8 bit ones
+		let alike = Self::ALL
+			.iter()
+			.filter(|pf| supported.contains(pf) && pf.has_alpha() == self.has_alpha())
+			.filter(|pf| pf.width() <= self.width())
+			.max_by_key(|pf| pf.width());
+		match alike {
+			Some(pf) => *pf,
+			None if self.has_alpha() => Self::Argb888,
+			None => Self::Xrgb888,
 		}
 	}
 }
@@ -447,20 +576,29 @@ impl God {
 				}
 				Action::Resize(w, h, surf) => {
 					dbug!(format!("RESIZING {w} {h}"));
-					let buf = {
+					let (bufs, (w, h)) = {
 						let mut surface = surf.borrow_mut();
 						surface.w = w;
 						surface.h = h;
-						surface.attached_buf.clone()
+						// old damage is in the old size
+						surface.damage_history.clear();
+						// a swapchain gets resized as a whole, lone buffers like the cursor's on their own
+						let bufs = if surface.swapchain.is_empty() {
+							surface.attached_buf.iter().cloned().collect()
+						} else {
+							surface.swapchain.clone()
+						};
+						(bufs, surface.buffer_size())
 					};
-					if let Some(rcbuf) = &buf {
+					for rcbuf in &bufs {
 						let backend = {
 							let buf = rcbuf.borrow();
 							buf.backend.clone()
 						};
 						let mut backend = backend.borrow_mut();
 						backend.resize(self, rcbuf, w, h)?
-					} else {
+					}
+					if bufs.is_empty() {
 						conseq.push_back(Consequence::Trace(
 							DebugLevel::Important,
 							"event handler",
@@ -496,3 +634,46 @@ impl God {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shm_codes() {
+		assert_eq!(PixelFormat::Argb888.to_shm(), 0);
+		assert_eq!(PixelFormat::Xrgb888.to_shm(), 1);
+		assert_eq!(PixelFormat::Rgb565.to_shm(), PixelFormat::Rgb565.to_fourcc());
+		for pf in PixelFormat::ALL {
+			assert_eq!(PixelFormat::from_shm(pf.to_shm()).unwrap(), pf);
+		}
+		assert!(PixelFormat::from_shm(2).is_err());
+	}
+
+	#[test]
+	fn strides() {
+		assert_eq!(PixelFormat::Argb888.stride(3), 12);
+		assert_eq!(PixelFormat::Abgr16161616f.stride(3), 24);
+		// packed formats get padded to 4 bytes
+		assert_eq!(PixelFormat::Rgb565.stride(3), 8);
+		assert_eq!(PixelFormat::Rgb888.stride(3), 12);
+		assert_eq!(PixelFormat::Bgr888.stride(5), 16);
+		assert_eq!(PixelFormat::Rgb888.stride(0), 0);
+	}
+
+	#[test]
+	fn fallbacks() {
+		let base = HashSet::from([PixelFormat::Argb888, PixelFormat::Xrgb888]);
+		assert_eq!(PixelFormat::Rgb565.fallback(&base), PixelFormat::Xrgb888);
+		assert_eq!(PixelFormat::Abgr16161616f.fallback(&base), PixelFormat::Argb888);
+		let mut supported = base.clone();
+		supported.extend([PixelFormat::Argb2101010, PixelFormat::Xrgb2101010, PixelFormat::Rgb565]);
+		assert_eq!(PixelFormat::Xbgr8888.fallback(&supported), PixelFormat::Xrgb2101010);
+		assert_eq!(PixelFormat::Abgr16161616f.fallback(&supported), PixelFormat::Argb2101010);
+		// nothing wider than asked for, even if it's closer in depth
+		assert_eq!(PixelFormat::Rgb888.fallback(&supported), PixelFormat::Rgb565);
+		assert_eq!(PixelFormat::Rgb565.fallback(&supported), PixelFormat::Rgb565);
+		supported.insert(PixelFormat::Abgr16161616f);
+		assert_eq!(PixelFormat::Abgr16161616f.fallback(&supported), PixelFormat::Abgr16161616f);
+	}
+}
@@ -0,0 +1,257 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	DebugLevel, Rl, handle_log, rl,
+	wayland::{
+		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		registry::{Registry, RegistryName},
+		wire::{Action, FromWirePayload, WireRequest},
+	},
+};
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transform {
+	#[default]
+	Normal = 0,
+	Rotated90,
+	Rotated180,
+	Rotated270,
+	Flipped,
+	Flipped90,
+	Flipped180,
+	Flipped270,
+}
+
+impl Transform {
+	pub(crate) fn from_u32(processee: u32) -> Result<Self, WaytinierError> {
+		match processee {
+			0 => Ok(Self::Normal),
+			1 => Ok(Self::Rotated90),
+			2 => Ok(Self::Rotated180),
+			3 => Ok(Self::Rotated270),
+			4 => Ok(Self::Flipped),
+			5 => Ok(Self::Flipped90),
+			6 => Ok(Self::Flipped180),
+			7 => Ok(Self::Flipped270),
+			_ => Err(WaytinierError::InvalidEnumVariant("Transform")),
+		}
+	}
+
+	pub fn swaps_axes(&self) -> bool {
+		matches!(self, Self::Rotated90 | Self::Rotated270 | Self::Flipped90 | Self::Flipped270)
+	}
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Subpixel {
+	#[default]
+	Unknown = 0,
+	None,
+	HorizontalRgb,
+	HorizontalBgr,
+	VerticalRgb,
+	VerticalBgr,
+}
+
+impl Subpixel {
+	pub(crate) fn from_u32(processee: u32) -> Result<Self, WaytinierError> {
+		match processee {
+			0 => Ok(Self::Unknown),
+			1 => Ok(Self::None),
+			2 => Ok(Self::HorizontalRgb),
+			3 => Ok(Self::HorizontalBgr),
+			4 => Ok(Self::VerticalRgb),
+			5 => Ok(Self::VerticalBgr),
+			_ => Err(WaytinierError::InvalidEnumVariant("Subpixel")),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputMode {
+	pub w: i32,
+	pub h: i32,
+	// in mHz
+	pub refresh: i32,
+	pub preferred: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OutputInfo {
+	// registry name of the global, stays the same for the lifetime of the output
+	pub global: u32,
+	pub x: i32,
+	pub y: i32,
+	// in millimeters
+	pub physical_w: i32,
+	pub physical_h: i32,
+	pub subpixel: Subpixel,
+	pub make: String,
+	pub model: String,
+	pub transform: Transform,
+	pub mode: OutputMode,
+	pub scale: i32,
+	pub name: Option<String>,
+	pub description: Option<String>,
+}
+
+pub(crate) struct Output {
+	pub(crate) id: Id,
+	pub(crate) version: u32,
+	pub(crate) global: RegistryName,
+	// what the app sees, only updated on done
+	pub(crate) info: OutputInfo,
+	pub(crate) pending: OutputInfo,
+	pub(crate) changed: bool,
+	// whether the app has been told about this output yet
+	pub(crate) announced: bool,
+}
+
+impl Output {
+	pub(crate) fn new(id: Id, global: RegistryName, version: u32) -> Rl<Self> {
+		let info = OutputInfo {
+			global: global.raw(),
+			scale: 1,
+			..Default::default()
+		};
+		rl!(Self {
+			id,
+			version,
+			global,
+			pending: info.clone(),
+			info,
+			changed: false,
+			announced: false,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God, global: RegistryName, version: u32) -> Rl<Self> {
+		let output = Self::new(Id(0), global, version);
+		let id = god.wlim.new_id_registered(output.clone());
+		output.borrow_mut().id = id;
+		output
+	}
+
+	pub(crate) fn new_registered_bound(
+		god: &mut God,
+		registry: &Rl<Registry>,
+		global: RegistryName,
+	) -> Result<Rl<Self>, WaytinierError> {
+		let version = registry.borrow().version_of(global)?.min(4);
+		let output = Self::new_registered(god, global, version);
+		registry.borrow_mut().bind_global(
+			god,
+			output.borrow().id,
+			global,
+			WaylandObjectKind::Output,
+			version,
+		)?;
+		Ok(output)
+	}
+
+	fn wl_release(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "release",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn release(&self, god: &mut God) {
+		// release only exists since v3, older outputs just get forgotten
+		if self.version >= 3 {
+			god.wlmm.queue_request(self.wl_release());
+		}
+	}
+
+	fn apply_pending(&mut self) {
+		if self.info != self.pending || !self.announced {
+			self.info = self.pending.clone();
+			self.changed = true;
+		}
+	}
+}
+
+impl WaylandObject for Output {
+	fn handle(
+		&mut self,
+		payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		let mut pending = vec![];
+		let p = payload;
+		match opcode.raw() {
+			// geometry
+			0 => {
+				self.pending.x = i32::from_wire(p)?;
+				self.pending.y = i32::from_wire(&p[4..])?;
+				self.pending.physical_w = i32::from_wire(&p[8..])?;
+				self.pending.physical_h = i32::from_wire(&p[12..])?;
+				self.pending.subpixel = Subpixel::from_u32(u32::from_wire(&p[16..])?)?;
+				let make = String::from_wire(&p[20..])?;
+				let make_len = 4 + (make.len() + 1).next_multiple_of(4);
+				let model = String::from_wire(&p[20 + make_len..])?;
+				let model_len = 4 + (model.len() + 1).next_multiple_of(4);
+				self.pending.transform =
+					Transform::from_u32(u32::from_wire(&p[20 + make_len + model_len..])?)?;
+				self.pending.make = make;
+				self.pending.model = model;
+			}
+			// mode
+			1 => {
+				let flags = u32::from_wire(p)?;
+				// we only care about the current mode
+				if flags & 0x1 != 0 {
+					self.pending.mode = OutputMode {
+						w: i32::from_wire(&p[4..])?,
+						h: i32::from_wire(&p[8..])?,
+						refresh: i32::from_wire(&p[12..])?,
+						preferred: flags & 0x2 != 0,
+					};
+				}
+			}
+			// done
+			2 => {
+				self.apply_pending();
+				handle_log!(
+					pending,
+					self,
+					DebugLevel::Important,
+					format!("done // {:?}", self.info)
+				);
+				return Ok(pending);
+			}
+			// scale
+			3 => {
+				self.pending.scale = i32::from_wire(p)?;
+			}
+			// name
+			4 => {
+				self.pending.name = Some(String::from_wire(p)?);
+			}
+			// description
+			5 => {
+				self.pending.description = Some(String::from_wire(p)?);
+			}
+			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
+		}
+		// v1 has no done event, every event is applied as is
+		if self.version < 2 {
+			self.apply_pending();
+		}
+		Ok(pending)
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::Output
+	}
+
+	fn kind_str(&self) -> &'static str {
+		self.kind().as_str()
+	}
+}
@@ -0,0 +1,191 @@
+use std::{collections::VecDeque, os::fd::OwnedFd};
+
+use crate::{
+	DebugLevel, Rl, handle_log, rl,
+	wayland::{
+		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		seat::Seat,
+		wire::{Action, FromWirePayload, WireArgument, WireRequest},
+	},
+};
+
+// linux/input-event-codes.h
+pub(crate) const BTN_LEFT: u32 = 0x110;
+pub(crate) const BTN_RIGHT: u32 = 0x111;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PointerEvent {
+	Enter {
+		serial: u32,
+		surface: Id,
+		x: f64,
+		y: f64,
+	},
+	Leave {
+		serial: u32,
+		surface: Id,
+	},
+	// relative to the focused surface, in logical pixels
+	Motion {
+		x: f64,
+		y: f64,
+	},
+	Button {
+		serial: u32,
+		button: u32,
+		pressed: bool,
+	},
+}
+
+pub(crate) struct Pointer {
+	pub(crate) id: Id,
+	// as of the last event taken out of the queue
+	pub(crate) focus: Option<Id>,
+	pub(crate) position: (f64, f64),
+	// serial of the last enter, needed for set_cursor
+	pub(crate) enter_serial: u32,
+	pub(crate) events: VecDeque<PointerEvent>,
+}
+
+fn fixed_to_f64(raw: i32) -> f64 {
+	raw as f64 / 256.0
+}
+
+impl Pointer {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+			focus: None,
+			position: (0.0, 0.0),
+			enter_serial: 0,
+			events: VecDeque::new(),
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let pointer = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(pointer.clone());
+		pointer.borrow_mut().id = id;
+		pointer
+	}
+
+	pub(crate) fn new_registered_gotten(god: &mut God, seat: &Rl<Seat>) -> Rl<Self> {
+		let pointer = Self::new_registered(god);
+		seat.borrow().get_pointer(god, pointer.borrow().id);
+		pointer
+	}
+
+	fn wl_set_cursor(&self, surface_id: Id, hotspot_x: i32, hotspot_y: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "set_cursor",
+			args: vec![
+				WireArgument::UnInt(self.enter_serial),
+				WireArgument::Obj(surface_id),
+				WireArgument::Int(hotspot_x),
+				WireArgument::Int(hotspot_y),
+			],
+		}
+	}
+
+	// hotspot is in the cursor surface's logical coordinates
+	pub(crate) fn set_cursor(&self, god: &mut God, surface_id: Id, hotspot_x: i32, hotspot_y: i32) {
+		god.wlmm.queue_request(self.wl_set_cursor(surface_id, hotspot_x, hotspot_y));
+	}
+
+	// pops the oldest event and updates focus and position to match it
+	pub(crate) fn next_event(&mut self) -> Option<PointerEvent> {
+		let event = self.events.pop_front()?;
+		match event {
+			PointerEvent::Enter {
+				surface,
+				x,
+				y,
+				..
+			} => {
+				self.focus = Some(surface);
+				self.position = (x, y);
+			}
+			PointerEvent::Leave {
+				..
+			} => self.focus = None,
+			PointerEvent::Motion {
+				x,
+				y,
+			} => self.position = (x, y),
+			PointerEvent::Button {
+				..
+			} => {}
+		}
+		Some(event)
+	}
+}
+
+impl WaylandObject for Pointer {
+	fn handle(
+		&mut self,
+		payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		let mut pending = vec![];
+		let p = payload;
+		match opcode.raw() {
+			// enter
+			0 => {
+				let serial = u32::from_wire(p)?;
+				let surface = Id(u32::from_wire(&p[4..])?);
+				let x = fixed_to_f64(i32::from_wire(&p[8..])?);
+				let y = fixed_to_f64(i32::from_wire(&p[12..])?);
+				self.enter_serial = serial;
+				self.events.push_back(PointerEvent::Enter {
+					serial,
+					surface,
+					x,
+					y,
+				});
+				handle_log!(pending, self, DebugLevel::Verbose, format!("enter {surface}"));
+			}
+			// leave
+			1 => {
+				let serial = u32::from_wire(p)?;
+				let surface = Id(u32::from_wire(&p[4..])?);
+				self.events.push_back(PointerEvent::Leave {
+					serial,
+					surface,
+				});
+				handle_log!(pending, self, DebugLevel::Verbose, format!("leave {surface}"));
+			}
+			// motion
+			2 => {
+				let x = fixed_to_f64(i32::from_wire(&p[4..])?);
+				let y = fixed_to_f64(i32::from_wire(&p[8..])?);
+				self.events.push_back(PointerEvent::Motion {
+					x,
+					y,
+				});
+			}
+			// button
+			3 => {
+				let serial = u32::from_wire(p)?;
+				let button = u32::from_wire(&p[8..])?;
+				let pressed = u32::from_wire(&p[12..])? == 1;
+				self.events.push_back(PointerEvent::Button {
+					serial,
+					button,
+					pressed,
+				});
+			}
+			// axis, frame, axis_source, axis_stop, axis_discrete
+			4..=8 => {}
+			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
+		}
+		Ok(pending)
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::Pointer
+	}
+}
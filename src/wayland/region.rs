@@ -0,0 +1,127 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	Rl, rl,
+	wayland::{
+		God, Id, OpCode, WaylandObject, WaylandObjectKind, WaytinierError,
+		wire::{Action, WireArgument, WireRequest},
+	},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionOp {
+	Add(i32, i32, i32, i32),
+	Subtract(i32, i32, i32, i32),
+}
+
+// a set of rectangles in surface local (logical) coordinates. an empty region
+// is a valid region too, e.g. an empty input region makes a window click-through
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Region {
+	pub(crate) ops: Vec<RegionOp>,
+}
+
+impl Region {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn add(mut self, x: i32, y: i32, w: i32, h: i32) -> Self {
+		self.ops.push(RegionOp::Add(x, y, w, h));
+		self
+	}
+
+	pub fn subtract(mut self, x: i32, y: i32, w: i32, h: i32) -> Self {
+		self.ops.push(RegionOp::Subtract(x, y, w, h));
+		self
+	}
+}
+
+// the wl_region behind a Region, only lives until it's been handed to a surface
+pub(crate) struct RegionObject {
+	pub(crate) id: Id,
+}
+
+impl RegionObject {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let region = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(region.clone());
+		region.borrow_mut().id = id;
+		region
+	}
+
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn destroy(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_destroy());
+	}
+
+	fn wl_add(&self, x: i32, y: i32, w: i32, h: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "add",
+			args: vec![
+				WireArgument::Int(x),
+				WireArgument::Int(y),
+				WireArgument::Int(w),
+				WireArgument::Int(h),
+			],
+		}
+	}
+
+	fn wl_subtract(&self, x: i32, y: i32, w: i32, h: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(2),
+			opname: "subtract",
+			args: vec![
+				WireArgument::Int(x),
+				WireArgument::Int(y),
+				WireArgument::Int(w),
+				WireArgument::Int(h),
+			],
+		}
+	}
+
+	pub(crate) fn apply(&self, god: &mut God, region: &Region) {
+		for op in &region.ops {
+			let req = match *op {
+				RegionOp::Add(x, y, w, h) => self.wl_add(x, y, w, h),
+				RegionOp::Subtract(x, y, w, h) => self.wl_subtract(x, y, w, h),
+			};
+			god.wlmm.queue_request(req);
+		}
+	}
+}
+
+impl WaylandObject for RegionObject {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::Region
+	}
+}
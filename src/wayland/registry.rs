@@ -83,13 +83,18 @@ impl Registry {
 		kind: WaylandObjectKind,
 		version: u32,
 	) -> Result<(), WaytinierError> {
-		let global_id = self
-			.inner
-			.iter()
-			.find(|(_, v)| v.interface == kind.as_str())
-			.map(|(k, _)| k)
-			.copied()
-			.ok_or(WaytinierError::NotInRegistry(kind))?;
+		let global_id = self.find_global(kind)?;
+		self.bind_global(god, id, global_id, kind, version)
+	}
+
+	pub(crate) fn bind_global(
+		&mut self,
+		god: &mut God,
+		id: Id,
+		global_id: RegistryName,
+		kind: WaylandObjectKind,
+		version: u32,
+	) -> Result<(), WaytinierError> {
 		wlog!(
 			DebugLevel::Important,
 			self.kind_str(),
@@ -100,6 +105,43 @@ impl Registry {
 		god.wlmm.queue_request(self.wl_bind(id, global_id, kind.as_str(), version));
 		Ok(())
 	}
+
+	pub(crate) fn find_global(
+		&self,
+		kind: WaylandObjectKind,
+	) -> Result<RegistryName, WaytinierError> {
+		self.inner
+			.iter()
+			.find(|(_, v)| v.interface == kind.as_str())
+			.map(|(k, _)| k)
+			.copied()
+			.ok_or(WaytinierError::NotInRegistry(kind))
+	}
+
+	pub(crate) fn has(&self, kind: WaylandObjectKind) -> bool {
+		self.find_global(kind).is_ok()
+	}
+
+	// every global of a kind, for the ones that can show up more than once like wl_output
+	pub(crate) fn find_globals(&self, kind: WaylandObjectKind) -> Vec<RegistryName> {
+		self.inner.iter().filter(|(_, v)| v.interface == kind.as_str()).map(|(k, _)| *k).collect()
+	}
+
+	pub(crate) fn version_of(&self, global_id: RegistryName) -> Result<u32, WaytinierError> {
+		self.inner
+			.get(&global_id)
+			.map(|e| e.version)
+			.ok_or(WaytinierError::ExpectedSomeValue("global not in registry"))
+	}
+
+	// the advertised version capped at what we implement
+	pub(crate) fn version_capped(
+		&self,
+		kind: WaylandObjectKind,
+		max: u32,
+	) -> Result<u32, WaytinierError> {
+		Ok(self.version_of(self.find_global(kind)?)?.min(max))
+	}
 }
 
 impl WaylandObject for Registry {
@@ -125,10 +167,15 @@ impl WaylandObject for Registry {
 				);
 				handle_log!(pending, self, DebugLevel::Trivial, msg);
 			}
-			// can global_remove even happen
+			// global_remove, happens when e.g. a monitor gets unplugged
 			1 => {
-				// let name = decode_event_payload(&p[8..], WireArgumentKind::UnInt)?;
-				todo!()
+				let name = u32::from_wire(p)?;
+				let removed = self.inner.remove(&RegistryName(name));
+				let msg = match removed {
+					Some(entry) => format!("removed interface {} (name {name})", entry.interface),
+					None => format!("tried to remove unknown global {name}"),
+				};
+				handle_log!(pending, self, DebugLevel::Trivial, msg);
 			}
 			_ => {
 				return Err(WaytinierError::InvalidOpCode(opcode, self.kind()));
@@ -0,0 +1,97 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	DebugLevel, Rl, handle_log, rl,
+	wayland::{
+		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		registry::Registry,
+		wire::{Action, FromWirePayload, WireArgument, WireRequest},
+	},
+};
+
+pub(crate) struct Seat {
+	pub(crate) id: Id,
+	// bitmask, 1 pointer, 2 keyboard, 4 touch
+	pub(crate) capabilities: u32,
+	pub(crate) name: Option<String>,
+}
+
+impl Seat {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+			capabilities: 0,
+			name: None,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let seat = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(seat.clone());
+		seat.borrow_mut().id = id;
+		seat
+	}
+
+	pub(crate) fn new_registered_bound(
+		god: &mut God,
+		registry: &Rl<Registry>,
+	) -> Result<Rl<Self>, WaytinierError> {
+		let version = registry.borrow().version_capped(WaylandObjectKind::Seat, 5)?;
+		let seat = Self::new_registered(god);
+		registry.borrow_mut().bind(god, seat.borrow().id, WaylandObjectKind::Seat, version)?;
+		Ok(seat)
+	}
+
+	pub(crate) fn has_pointer(&self) -> bool {
+		self.capabilities & 1 != 0
+	}
+
+	fn wl_get_pointer(&self, id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "get_pointer",
+			args: vec![WireArgument::NewId(id)],
+		}
+	}
+
+	pub(crate) fn get_pointer(&self, god: &mut God, id: Id) {
+		god.wlmm.queue_request(self.wl_get_pointer(id));
+	}
+}
+
+impl WaylandObject for Seat {
+	fn handle(
+		&mut self,
+		payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		let mut pending = vec![];
+		match opcode.raw() {
+			// capabilities
+			0 => {
+				self.capabilities = u32::from_wire(payload)?;
+				handle_log!(
+					pending,
+					self,
+					DebugLevel::Trivial,
+					format!("capabilities: {:#b}", self.capabilities)
+				);
+			}
+			// name
+			1 => {
+				let name = String::from_wire(payload)?;
+				handle_log!(pending, self, DebugLevel::Trivial, format!("name: {name}"));
+				self.name = Some(name);
+			}
+			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
+		}
+		Ok(pending)
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::Seat
+	}
+}
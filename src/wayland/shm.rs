@@ -1,19 +1,21 @@
 use std::{
 	collections::HashSet,
-	ffi::{CString, c_void},
+	ffi::{CStr, CString, c_void},
 	os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
 	ptr,
+	rc::Rc,
 };
 
 use libc::{
-	MAP_FAILED, MAP_SHARED, O_CREAT, O_RDWR, PROT_READ, PROT_WRITE, ftruncate, mmap, munmap,
+	F_ADD_SEALS, F_SEAL_SHRINK, MAP_FAILED, MAP_SHARED, MFD_ALLOW_SEALING, MFD_CLOEXEC, O_CLOEXEC,
+	O_CREAT, O_EXCL, O_RDWR, PROT_READ, PROT_WRITE, fcntl, ftruncate, memfd_create, mmap, munmap,
 	shm_open, shm_unlink,
 };
 
 use crate::{
-	CYAN, DebugLevel, NONE, Rl, WHITE,
+	CYAN, DebugLevel, NONE, Rl, WHITE, Wl,
 	abstraction::app::App,
-	dbug, handle_log, qpush, rl,
+	dbug, handle_log, qpush, rl, wait_for_sync,
 	wayland::{
 		ExpectRc, God, Id, OpCode, PixelFormat, Raw, WaylandObject, WaylandObjectKind,
 		WaytinierError,
@@ -25,8 +27,15 @@ use crate::{
 	wlog,
 };
 
+// offsets handed out by the pool allocator are multiples of this, a cache line
+const POOL_ALIGN: u32 = 64;
+
+// several windows can share one by passing it to their wizards' with_backend,
+// their buffers then all come out of the same pool
 pub struct ShmBackend {
 	pub(crate) pool: Rl<SharedMemoryPool>,
+	// what the compositor advertised through wl_shm.format
+	pub(crate) formats: HashSet<PixelFormat>,
 }
 
 impl ShmBackend {
@@ -55,16 +64,26 @@ impl ShmBackend {
 		let mut buffer = buf.borrow_mut();
 		god.wlmm.queue_request(buffer.wl_destroy());
 
+		// a resized buffer has nothing worth keeping, so its old range can be reused right away
+		let mut pool = self.pool.borrow_mut();
+		if let Some(len) = buffer.shm_len() {
+			pool.free(buffer.offset, len as u32);
+		}
+
 		buffer.w = w;
 		buffer.h = h;
 
-		let mut pool = self.pool.borrow_mut();
 		let format = buffer.master.upgrade().to_wl_err()?.borrow().pf;
-		let shm_actions = pool.get_resize_actions_if_larger((w * h * format.width()) as i32)?;
-		buffer.accessor = pool.slice.map(|s| BufferAccessor::ShmSlice(s));
+		let len = format.stride(w) * h;
+		let (offset, shm_actions) = pool.allocate(len)?;
+		buffer.offset = offset;
+		buffer.accessor = pool.accessor(offset, len as usize);
 		god.wlmm.q.extend(shm_actions);
 
 		buffer.id = id;
+		// a fresh wl_buffer the compositor has never seen, with nothing drawn in it
+		buffer.in_use = false;
+		buffer.drawn_at = None;
 
 		god.wlmm.queue_request(pool.wl_create_buffer(
 			buffer.id,
@@ -72,7 +91,7 @@ impl ShmBackend {
 				buffer.offset as i32,
 				buffer.w as i32,
 				buffer.h as i32,
-				(buffer.w * format.width()) as i32,
+				format.stride(buffer.w) as i32,
 			),
 			format,
 		));
@@ -84,8 +103,12 @@ impl ShmBackend {
 	pub fn new(app: &mut App) -> Result<Rl<BufferBackend>, WaytinierError> {
 		let shm = SharedMemory::new_registered_bound(&mut app.god, &app.registry)?;
 		let pool = SharedMemoryPool::new_registered_allocated(&mut app.god, &shm, 8)?;
+		// the formats come right after the bind
+		wait_for_sync!(&app.display, &mut app.god);
+		let formats = shm.borrow().valid_pix_formats.clone();
 		Ok(rl!(BufferBackend::Shm(ShmBackend {
 			pool,
+			formats,
 		})))
 	}
 }
@@ -146,6 +169,10 @@ pub(crate) struct SharedMemoryPool {
 	pub(crate) fd: OwnedFd,
 	pub(crate) slice: Option<*mut [u8]>,
 	pub(crate) ptr: Option<*mut c_void>,
+	// unused (offset, len) ranges, sorted and never touching each other
+	pub(crate) free: Vec<(u32, u32)>,
+	// everything carved from the pool, their views into it move along when it grows
+	pub(crate) buffers: Vec<Wl<Buffer>>,
 }
 
 impl SharedMemoryPool {
@@ -157,6 +184,12 @@ impl SharedMemoryPool {
 			fd,
 			slice: None,
 			ptr: None,
+			// only whole aligned chunks are handed out
+			free: match size as u32 / POOL_ALIGN * POOL_ALIGN {
+				0 => vec![],
+				len => vec![(0, len)],
+			},
+			buffers: vec![],
 		})
 	}
 
@@ -181,20 +214,49 @@ impl SharedMemoryPool {
 		new
 	}
 
+	// never shows up in the filesystem, so nothing is left behind even after a crash.
+	// sealed against shrinking so the compositor never maps past the end
+	fn open_memfd(name: &CStr) -> Option<OwnedFd> {
+		let raw_fd = unsafe { memfd_create(name.as_ptr(), MFD_CLOEXEC | MFD_ALLOW_SEALING) };
+		if raw_fd == -1 {
+			dbug!(format!("memfd_create failed: {}", std::io::Error::last_os_error()));
+			return None;
+		}
+		let fd = unsafe { OwnedFd::from_raw_fd(raw_fd) };
+		if unsafe { fcntl(raw_fd, F_ADD_SEALS, F_SEAL_SHRINK) } == -1 {
+			dbug!(format!("sealing the pool failed: {}", std::io::Error::last_os_error()));
+		}
+		Some(fd)
+	}
+
+	// for systems without memfd. unlinked right away, the memory goes when the last fd does
+	fn open_shm(name: &CStr) -> Result<OwnedFd, WaytinierError> {
+		let raw_fd =
+			unsafe { shm_open(name.as_ptr(), O_RDWR | O_CREAT | O_EXCL | O_CLOEXEC, 0o600) };
+		if raw_fd == -1 {
+			return Err(std::io::Error::last_os_error().into());
+		}
+		let fd = unsafe { OwnedFd::from_raw_fd(raw_fd) };
+		if unsafe { shm_unlink(name.as_ptr()) } == -1 {
+			return Err(std::io::Error::last_os_error().into());
+		}
+		Ok(fd)
+	}
+
 	pub(crate) fn new_registered_allocated(
 		god: &mut God,
 		shm: &Rl<SharedMemory>,
 		size: i32,
 	) -> Result<Rl<SharedMemoryPool>, WaytinierError> {
 		let name = Self::make_unique_pool_name()?;
-		let raw_fd = unsafe { shm_open(name.as_ptr(), O_RDWR | O_CREAT, 0) };
-		if raw_fd == -1 {
-			return Err(std::io::Error::last_os_error().into());
-		}
+		let fd = match Self::open_memfd(&name) {
+			Some(fd) => fd,
+			None => Self::open_shm(&name)?,
+		};
+		let raw_fd = fd.as_raw_fd();
 		if unsafe { ftruncate(raw_fd, size.into()) } == -1 {
 			return Err(std::io::Error::last_os_error().into());
 		}
-		let fd = unsafe { OwnedFd::from_raw_fd(raw_fd) };
 
 		let pool = Self::new_registered(god, name, size, fd);
 		{
@@ -202,7 +264,7 @@ impl SharedMemoryPool {
 			wlog!(
 				DebugLevel::Important,
 				pool.kind_str(),
-				format!("new pool fd: {}", raw_fd),
+				format!("new pool {:?} fd: {}", pool.name, raw_fd),
 				WHITE,
 				NONE
 			);
@@ -229,7 +291,7 @@ impl SharedMemoryPool {
 				WireArgument::Int(width),
 				WireArgument::Int(height),
 				WireArgument::Int(stride),
-				WireArgument::UnInt(format as u32),
+				WireArgument::UnInt(format.to_shm()),
 			],
 		}
 	}
@@ -266,19 +328,9 @@ impl SharedMemoryPool {
 		}
 	}
 
-	fn unlink(&self) -> Result<(), std::io::Error> {
-		let r = unsafe { shm_unlink(self.name.as_ptr()) };
-		if r == 0 {
-			Ok(())
-		} else {
-			Err(std::io::Error::last_os_error())
-		}
-	}
-
+	// the fd closes with the pool, there's no file to remove
 	pub(crate) fn destroy(&self) -> Result<(), WaytinierError> {
-		self.unmap()?;
-		self.unlink()?;
-		Ok(())
+		self.unmap()
 	}
 
 	pub(crate) fn update_ptr(&mut self) -> Result<(), WaytinierError> {
@@ -302,48 +354,183 @@ impl SharedMemoryPool {
 		Ok(())
 	}
 
-	pub(crate) fn get_resize_actions_if_larger(
-		&mut self,
-		size: i32,
-	) -> Result<Vec<Action>, WaytinierError> {
-		dbug!(format!("size: {size}"));
+	// wl_shm_pool can only ever grow, the new space goes to the free list
+	fn grow(&mut self, size: u32) -> Result<Vec<Action>, WaytinierError> {
+		let size = size.next_multiple_of(POOL_ALIGN);
 		let mut pending = vec![];
-		if size < self.size {
-			return Ok(pending);
-		}
 		handle_log!(
 			pending,
 			self,
 			DebugLevel::Important,
 			format!("{} | RESIZE size {size}", self.kind_str())
 		);
+		// new pools only hand out whole chunks, so a partial one at the end is free too
+		let start = self.size as u32 / POOL_ALIGN * POOL_ALIGN;
 		self.unmap()?;
-		self.size = size;
-		let r = unsafe { ftruncate(self.fd.as_raw_fd(), size.into()) };
+		self.size = size as i32;
+		let r = unsafe { ftruncate(self.fd.as_raw_fd(), self.size.into()) };
 		if r == 0 {
 			Ok(())
 		} else {
 			Err(std::io::Error::last_os_error())
 		}?;
 		self.update_ptr()?;
+		self.free(start, size - start);
+		self.remap();
 		qpush!(pending, self.wl_resize());
 		Ok(pending)
 	}
 
+	// the mapping moves when the pool grows. buffers borrowed right now are the
+	// ones being worked on, whoever has them sets their accessor themselves
+	fn remap(&mut self) {
+		self.buffers.retain(|b| b.strong_count() > 0);
+		for buffer in self.buffers.iter().filter_map(|b| b.upgrade()) {
+			let Ok(mut buffer) = buffer.try_borrow_mut() else {
+				continue;
+			};
+			if let Some(len) = buffer.shm_len() {
+				buffer.accessor = self.accessor(buffer.offset, len);
+			}
+		}
+	}
+
+	// first fit, growing the pool when nothing fits. the actions resize it on the server
+	pub(crate) fn allocate(&mut self, len: u32) -> Result<(u32, Vec<Action>), WaytinierError> {
+		let len = len.max(1).next_multiple_of(POOL_ALIGN);
+		let mut pending = vec![];
+		if !self.free.iter().any(|(_, free)| *free >= len) {
+			let size = self.size as u32;
+			// free space at the very end counts towards what's needed
+			let tail = match self.free.last() {
+				Some((offset, free)) if offset + free == size => *free,
+				_ => 0,
+			};
+			// wl_shm takes the size as an int
+			let limit = i32::MAX as u32 / POOL_ALIGN * POOL_ALIGN;
+			let needed = size
+				.checked_add(len - tail)
+				.filter(|needed| *needed <= limit)
+				.ok_or(WaytinierError::ExpectedSomeValue("shm pool size that fits an i32"))?;
+			// doubling keeps the number of resizes down when buffers keep coming
+			let doubled = size.checked_mul(2).map_or(limit, |doubled| doubled.min(limit));
+			pending = self.grow(doubled.max(needed))?;
+		}
+		let i = self
+			.free
+			.iter()
+			.position(|(_, free)| *free >= len)
+			.ok_or(WaytinierError::ExpectedSomeValue("free range in a grown pool"))?;
+		let (offset, free) = self.free[i];
+		if free == len {
+			self.free.remove(i);
+		} else {
+			self.free[i] = (offset + len, free - len);
+		}
+		Ok((offset, pending))
+	}
+
+	pub(crate) fn free(&mut self, offset: u32, len: u32) {
+		let len = len.max(1).next_multiple_of(POOL_ALIGN);
+		let i = self.free.partition_point(|(o, _)| *o < offset);
+		self.free.insert(i, (offset, len));
+		if i + 1 < self.free.len() && offset + len == self.free[i + 1].0 {
+			self.free[i].1 += self.free.remove(i + 1).1;
+		}
+		if i > 0 && self.free[i - 1].0 + self.free[i - 1].1 == offset {
+			let (_, len) = self.free.remove(i);
+			self.free[i - 1].1 += len;
+		}
+	}
+
 	pub(crate) fn make_buffer(
 		&mut self,
 		god: &mut God,
 		(w, h): (u32, u32),
 		master: &Rl<Surface>,
 		backend: &Rl<BufferBackend>,
+	) -> Result<Rl<Buffer>, WaytinierError> {
+		let len = master.borrow().pf.stride(w) * h;
+		let (offset, shm_actions) = self.allocate(len)?;
+		god.wlmm.q.extend(shm_actions);
+		self.make_buffer_at(god, offset, (w, h), master, backend)
+	}
+
+	// gives the buffer's range back to the pool
+	pub(crate) fn destroy_buffer(&mut self, god: &mut God, buf: &Rl<Buffer>) {
+		let buffer = buf.borrow();
+		if let Some(len) = buffer.shm_len() {
+			self.free(buffer.offset, len as u32);
+		}
+		god.wlmm.queue_request(buffer.wl_destroy());
+		self.buffers.retain(|b| b.strong_count() > 0 && b.as_ptr() != Rc::as_ptr(buf));
+	}
+
+	// moves released buffers towards the start so later allocations fit without growing.
+	// the memory itself stays, wl_shm_pool has no way to shrink
+	pub(crate) fn compact(&mut self, god: &mut God) -> Result<(), WaytinierError> {
+		let mut buffers: Vec<Rl<Buffer>> =
+			self.buffers.iter().filter_map(|b| b.upgrade()).collect();
+		buffers.sort_by_key(|b| b.borrow().offset);
+		let slice = self.slice.ok_or(WaytinierError::ExpectedSomeValue("shm pool mapping"))?;
+		for buf in buffers {
+			let Ok(mut buffer) = buf.try_borrow_mut() else {
+				continue;
+			};
+			let Some(len) = buffer.shm_len() else {
+				continue;
+			};
+			if buffer.in_use {
+				continue;
+			}
+			let old = buffer.offset;
+			self.free(old, len as u32);
+			// can't grow, the range just freed fits
+			let (offset, _) = self.allocate(len as u32)?;
+			if offset == old {
+				continue;
+			}
+			unsafe {
+				let base = slice as *mut u8;
+				ptr::copy(base.add(old as usize), base.add(offset as usize), len);
+			}
+			god.wlmm.queue_request(buffer.wl_destroy());
+			let format = buffer.master.upgrade().to_wl_err()?.borrow().pf;
+			buffer.id = god.wlim.new_id_registered(buf.clone());
+			buffer.offset = offset;
+			buffer.accessor = self.accessor(offset, len);
+			god.wlmm.queue_request(self.wl_create_buffer(
+				buffer.id,
+				(offset as i32, buffer.w as i32, buffer.h as i32, format.stride(buffer.w) as i32),
+				format,
+			));
+		}
+		Ok(())
+	}
+
+	// a view of just the part of the pool a buffer lives in
+	pub(crate) fn accessor(&self, offset: u32, len: usize) -> Option<BufferAccessor> {
+		let start = (self.slice? as *mut u8).wrapping_add(offset as usize);
+		Some(BufferAccessor::ShmSlice(ptr::slice_from_raw_parts_mut(start, len)))
+	}
+
+	// offset is in bytes from the start of the pool
+	pub(crate) fn make_buffer_at(
+		&mut self,
+		god: &mut God,
+		offset: u32,
+		(w, h): (u32, u32),
+		master: &Rl<Surface>,
+		backend: &Rl<BufferBackend>,
 	) -> Result<Rl<Buffer>, WaytinierError> {
 		let surface = master.borrow();
-		let accessor = self.slice.map(|s| BufferAccessor::ShmSlice(s));
-		let buf = Buffer::new_registered(god, (0, w, h), master, backend, accessor)?;
+		let accessor = self.accessor(offset, (surface.pf.stride(w) * h) as usize);
+		let buf = Buffer::new_registered(god, (offset, w, h), master, backend, accessor)?;
+		self.buffers.push(Rc::downgrade(&buf));
 
 		god.wlmm.queue_request(self.wl_create_buffer(
 			buf.borrow().id,
-			(0, w as i32, h as i32, (w * surface.pf.width()) as i32),
+			(offset as i32, w as i32, h as i32, surface.pf.stride(w) as i32),
 			surface.pf,
 		));
 		Ok(buf)
@@ -427,3 +614,118 @@ impl Drop for SharedMemoryPool {
 		};
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::os::unix::net::UnixStream;
+
+	use super::*;
+	use crate::{
+		abstraction::headless::MemoryBackend,
+		wayland::{IdentManager, wire::MessageManager},
+	};
+
+	fn pool(size: i32) -> Rl<SharedMemoryPool> {
+		let name = SharedMemoryPool::make_unique_pool_name().unwrap();
+		let fd = SharedMemoryPool::open_memfd(&name).unwrap();
+		assert_eq!(unsafe { ftruncate(fd.as_raw_fd(), size.into()) }, 0);
+		let pool = SharedMemoryPool::new(Id(0), name, size, fd);
+		pool.borrow_mut().update_ptr().unwrap();
+		pool
+	}
+
+	// requests go nowhere, they only pile up in the queue
+	fn god() -> God {
+		God {
+			wlim: IdentManager::default(),
+			wlmm: MessageManager {
+				sock: UnixStream::pair().unwrap().0,
+				q: Default::default(),
+			},
+		}
+	}
+
+	#[test]
+	fn first_fit() {
+		let pool = pool(4096);
+		let mut pool = pool.borrow_mut();
+		assert_eq!(pool.allocate(100).unwrap().0, 0);
+		assert_eq!(pool.allocate(64).unwrap().0, 128);
+		assert_eq!(pool.allocate(256).unwrap().0, 192);
+		pool.free(0, 100);
+		// the first hole that fits, not the big one at the end
+		assert_eq!(pool.allocate(1).unwrap().0, 0);
+		assert_eq!(pool.allocate(64).unwrap().0, 64);
+		assert_eq!(pool.allocate(64).unwrap().0, 448);
+	}
+
+	#[test]
+	fn coalesces_with_both_neighbours() {
+		let pool = pool(256);
+		let mut pool = pool.borrow_mut();
+		let offsets: Vec<u32> = (0..4).map(|_| pool.allocate(64).unwrap().0).collect();
+		assert_eq!(offsets, [0, 64, 128, 192]);
+		assert!(pool.free.is_empty());
+		pool.free(0, 64);
+		pool.free(128, 64);
+		assert_eq!(pool.free, [(0, 64), (128, 64)]);
+		pool.free(64, 64);
+		assert_eq!(pool.free, [(0, 192)]);
+		pool.free(192, 64);
+		assert_eq!(pool.free, [(0, 256)]);
+	}
+
+	#[test]
+	fn grows_counting_the_free_tail() {
+		let pool = pool(256);
+		let mut pool = pool.borrow_mut();
+		assert_eq!(pool.allocate(128).unwrap().0, 0);
+		let (offset, actions) = pool.allocate(512).unwrap();
+		assert!(!actions.is_empty());
+		// the free tail at 128 is joined by the new space instead of skipped
+		assert_eq!(offset, 128);
+		assert_eq!(pool.size, 640);
+		assert!(pool.free.is_empty());
+		// doubling wins once it's more than what's needed
+		pool.allocate(64).unwrap();
+		assert_eq!(pool.size, 1280);
+		assert_eq!(pool.free, [(704, 576)]);
+	}
+
+	#[test]
+	fn tiny_pool_hands_out_its_start() {
+		let pool = pool(8);
+		let mut pool = pool.borrow_mut();
+		assert!(pool.free.is_empty());
+		assert_eq!(pool.allocate(64).unwrap().0, 0);
+		assert_eq!(pool.allocate(64).unwrap().0, 64);
+	}
+
+	#[test]
+	fn compact_moves_buffers_down() {
+		let mut god = god();
+		let pool = pool(256);
+		let backend = MemoryBackend::new(1);
+		// 16 argb pixels, one aligned chunk each
+		let surface = Surface::new(Id(0), 16, 1, PixelFormat::Argb888);
+		let mut pool = pool.borrow_mut();
+		let bufs: Vec<Rl<Buffer>> = (0..3)
+			.map(|_| pool.make_buffer(&mut god, (16, 1), &surface, &backend).unwrap())
+			.collect();
+		let offsets: Vec<u32> = bufs.iter().map(|b| b.borrow().offset).collect();
+		assert_eq!(offsets, [0, 64, 128]);
+		if let Some(BufferAccessor::ShmSlice(slice)) = &bufs[2].borrow().accessor {
+			unsafe { (**slice).fill(0xab) };
+		}
+		pool.destroy_buffer(&mut god, &bufs[1]);
+		let bufs = [bufs[0].clone(), bufs[2].clone()];
+		pool.compact(&mut god).unwrap();
+		assert_eq!(bufs[0].borrow().offset, 0);
+		assert_eq!(bufs[1].borrow().offset, 64);
+		assert_eq!(pool.free, [(128, 128)]);
+		let Some(BufferAccessor::ShmSlice(slice)) = &bufs[1].borrow().accessor else {
+			panic!("moved buffer lost its view of the pool");
+		};
+		assert!(unsafe { &**slice }.iter().all(|b| *b == 0xab));
+	}
+}
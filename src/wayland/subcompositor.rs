@@ -0,0 +1,219 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	Rl, rl,
+	wayland::{
+		God, Id, OpCode, WaylandObject, WaylandObjectKind, WaytinierError,
+		registry::Registry,
+		surface::Surface,
+		wire::{Action, WireArgument, WireRequest},
+	},
+};
+
+pub(crate) struct SubCompositor {
+	pub(crate) id: Id,
+}
+
+impl SubCompositor {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let subcompositor = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(subcompositor.clone());
+		subcompositor.borrow_mut().id = id;
+		subcompositor
+	}
+
+	pub(crate) fn new_registered_bound(
+		god: &mut God,
+		registry: &Rl<Registry>,
+	) -> Result<Rl<Self>, WaytinierError> {
+		let subcompositor = Self::new_registered(god);
+		registry.borrow_mut().bind(
+			god,
+			subcompositor.borrow().id,
+			subcompositor.borrow().kind(),
+			1,
+		)?;
+		Ok(subcompositor)
+	}
+
+	fn wl_get_subsurface(&self, id: Id, surface_id: Id, parent_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "get_subsurface",
+			args: vec![
+				WireArgument::NewId(id),
+				WireArgument::Obj(surface_id),
+				WireArgument::Obj(parent_id),
+			],
+		}
+	}
+
+	pub(crate) fn get_subsurface(&self, god: &mut God, id: Id, surface_id: Id, parent_id: Id) {
+		god.wlmm.queue_request(self.wl_get_subsurface(id, surface_id, parent_id));
+	}
+}
+
+impl WaylandObject for SubCompositor {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::SubCompositor
+	}
+}
+
+pub(crate) struct SubSurface {
+	pub(crate) id: Id,
+	// subsurfaces start out synchronized
+	pub(crate) sync: bool,
+}
+
+impl SubSurface {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+			sync: true,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let subsurface = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(subsurface.clone());
+		subsurface.borrow_mut().id = id;
+		subsurface
+	}
+
+	pub(crate) fn new_registered_gotten(
+		god: &mut God,
+		subcompositor: &Rl<SubCompositor>,
+		surface: &Rl<Surface>,
+		parent: &Rl<Surface>,
+	) -> Rl<Self> {
+		let subsurface = Self::new_registered(god);
+		subcompositor.borrow().get_subsurface(
+			god,
+			subsurface.borrow().id,
+			surface.borrow().id,
+			parent.borrow().id,
+		);
+		subsurface
+	}
+
+	fn wl_set_position(&self, x: i32, y: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "set_position",
+			args: vec![WireArgument::Int(x), WireArgument::Int(y)],
+		}
+	}
+
+	pub(crate) fn set_position(&self, god: &mut God, x: i32, y: i32) {
+		god.wlmm.queue_request(self.wl_set_position(x, y));
+	}
+
+	fn wl_place_above(&self, sibling_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(2),
+			opname: "place_above",
+			args: vec![WireArgument::Obj(sibling_id)],
+		}
+	}
+
+	// the sibling is a wl_surface, either the parent or another subsurface of it
+	pub(crate) fn place_above(&self, god: &mut God, sibling_id: Id) {
+		god.wlmm.queue_request(self.wl_place_above(sibling_id));
+	}
+
+	fn wl_place_below(&self, sibling_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(3),
+			opname: "place_below",
+			args: vec![WireArgument::Obj(sibling_id)],
+		}
+	}
+
+	pub(crate) fn place_below(&self, god: &mut God, sibling_id: Id) {
+		god.wlmm.queue_request(self.wl_place_below(sibling_id));
+	}
+
+	fn wl_set_sync(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(4),
+			opname: "set_sync",
+			args: vec![],
+		}
+	}
+
+	fn wl_set_desync(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(5),
+			opname: "set_desync",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn set_sync(&mut self, god: &mut God, sync: bool) {
+		if self.sync == sync {
+			return;
+		}
+		self.sync = sync;
+		if sync {
+			god.wlmm.queue_request(self.wl_set_sync());
+		} else {
+			god.wlmm.queue_request(self.wl_set_desync());
+		}
+	}
+
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn destroy(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_destroy());
+	}
+}
+
+impl WaylandObject for SubSurface {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::SubSurface
+	}
+}
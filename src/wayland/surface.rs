@@ -1,22 +1,48 @@
 use std::os::fd::OwnedFd;
 
 use crate::{
-	Rl, rl,
+	DebugLevel, Rl,
+	abstraction::damage::DamageHistory,
+	handle_log, rl,
 	wayland::{
-		God, Id, OpCode, PixelFormat, WaylandObject, WaylandObjectKind, WaytinierError,
+		God, Id, OpCode, PixelFormat, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
 		buffer::Buffer,
 		callback::Callback,
 		compositor::Compositor,
-		wire::{Action, WireArgument, WireRequest},
+		fractional_scale::FractionalScale,
+		output::Transform,
+		region::Region,
+		viewporter::Viewport,
+		wire::{Action, FromWirePayload, WireArgument, WireRequest},
 	},
 };
 
 pub(crate) struct Surface {
 	pub(crate) id: Id,
+	pub(crate) version: u32,
 	pub(crate) pf: PixelFormat,
 	pub(crate) w: u32,
 	pub(crate) h: u32,
 	pub(crate) attached_buf: Option<Rl<Buffer>>,
+	// every buffer drawn into for this surface, attached_buf is one of them
+	pub(crate) swapchain: Vec<Rl<Buffer>>,
+	// counts frames drawn, buffers remember it to know their age
+	pub(crate) drawn: usize,
+	pub(crate) damage_history: DamageHistory,
+	// wl_output ids this surface is currently shown on
+	pub(crate) outputs: Vec<Id>,
+	// w and h are logical, the buffer is w * scale by h * scale
+	pub(crate) scale: u32,
+	pub(crate) preferred_scale: Option<u32>,
+	// in 120ths, replaces the integer scale when the compositor supports it
+	pub(crate) fractional_scale: Option<u32>,
+	pub(crate) fractional: Option<Rl<FractionalScale>>,
+	pub(crate) viewport: Option<Rl<Viewport>>,
+	pub(crate) transform: Transform,
+	pub(crate) preferred_transform: Option<Transform>,
+	// what the compositor was last told, None is the protocol default
+	pub(crate) opaque_region: Option<Region>,
+	pub(crate) input_region: Option<Region>,
 }
 
 impl Surface {
@@ -25,8 +51,22 @@ impl Surface {
 			w,
 			h,
 			id,
+			version: 1,
 			pf,
 			attached_buf: None,
+			swapchain: vec![],
+			drawn: 0,
+			damage_history: DamageHistory::default(),
+			outputs: vec![],
+			scale: 1,
+			preferred_scale: None,
+			fractional_scale: None,
+			fractional: None,
+			viewport: None,
+			transform: Transform::Normal,
+			preferred_transform: None,
+			opaque_region: None,
+			input_region: None,
 		})
 	}
 
@@ -45,10 +85,38 @@ impl Surface {
 		pf: PixelFormat,
 	) -> Rl<Self> {
 		let surf = Self::new_registered(god, w, h, pf);
+		surf.borrow_mut().version = compositor.borrow().version;
 		compositor.borrow().create_surface(god, surf.borrow().id);
 		surf
 	}
 
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	// its role object has to be gone already. the viewport and fractional scale go
+	// first, the buffers once nothing can show them anymore
+	pub(crate) fn destroy(&mut self, god: &mut God) {
+		if let Some(viewport) = self.viewport.take() {
+			viewport.borrow().destroy(god);
+		}
+		if let Some(fractional) = self.fractional.take() {
+			fractional.borrow().destroy(god);
+		}
+		god.wlmm.queue_request(self.wl_destroy());
+		self.attached_buf = None;
+		for buf in self.swapchain.drain(..) {
+			let backend = buf.borrow().backend.clone();
+			backend.borrow_mut().destroy_buffer(god, &buf);
+		}
+	}
+
 	pub(crate) fn wl_attach(&self, buf_id: Id) -> WireRequest {
 		WireRequest {
 			sender_id: self.id,
@@ -73,6 +141,8 @@ impl Surface {
 			.attached_buf
 			.clone()
 			.ok_or(WaytinierError::ExpectedSomeValue("no buffer attached to surface"))?;
+		// the compositor owns it until wl_buffer.release
+		buf.borrow_mut().in_use = true;
 		god.wlmm.queue_request(self.wl_attach(buf.borrow().id));
 		Ok(())
 	}
@@ -126,12 +196,142 @@ impl Surface {
 		god.wlmm.queue_request(self.wl_damage_buffer(x, y, w, h))
 	}
 
-	pub(crate) fn repaint(&self, god: &mut God) -> Result<(), WaytinierError> {
-		if self.attached_buf.is_some() {
-			self.damage_buffer(god, (0, 0), (self.w as i32, self.h as i32));
-			Ok(())
+	fn wl_set_buffer_scale(&self, scale: u32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(8),
+			opname: "set_buffer_scale",
+			args: vec![WireArgument::Int(scale as i32)],
+		}
+	}
+
+	// returns true if the scale changed. before v3 there's no set_buffer_scale and
+	// the buffer stays at scale 1
+	pub(crate) fn set_buffer_scale(&mut self, god: &mut God, scale: u32) -> bool {
+		if self.version < 3 || self.scale == scale {
+			return false;
+		}
+		self.scale = scale;
+		god.wlmm.queue_request(self.wl_set_buffer_scale(scale));
+		true
+	}
+
+	// returns true if the scale changed and the buffer needs to be resized
+	pub(crate) fn set_fractional_scale(&mut self, god: &mut God, scale: u32) -> bool {
+		if self.fractional_scale == Some(scale) {
+			return false;
+		}
+		// the viewport does the scaling, the buffer itself stays at 1
+		self.set_buffer_scale(god, 1);
+		self.fractional_scale = Some(scale);
+		true
+	}
+
+	fn wl_set_buffer_transform(&self, transform: Transform) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(7),
+			opname: "set_buffer_transform",
+			args: vec![WireArgument::Int(transform as i32)],
+		}
+	}
+
+	pub(crate) fn set_buffer_transform(&mut self, god: &mut God, transform: Transform) {
+		self.transform = transform;
+		god.wlmm.queue_request(self.wl_set_buffer_transform(transform))
+	}
+
+	fn wl_set_opaque_region(&self, region_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(4),
+			opname: "set_opaque_region",
+			args: vec![WireArgument::Obj(region_id)],
+		}
+	}
+
+	fn wl_set_input_region(&self, region_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(5),
+			opname: "set_input_region",
+			args: vec![WireArgument::Obj(region_id)],
+		}
+	}
+
+	// None means nothing is opaque. only sent when it changes, applied on commit
+	pub(crate) fn set_opaque_region(
+		&mut self,
+		god: &mut God,
+		compositor: &Rl<Compositor>,
+		region: Option<Region>,
+	) {
+		if self.opaque_region == region {
+			return;
+		}
+		let obj = region.as_ref().map(|r| compositor.borrow().create_region(god, r));
+		let id = obj.as_ref().map(|o| o.borrow().id).unwrap_or_default();
+		god.wlmm.queue_request(self.wl_set_opaque_region(id));
+		// the surface keeps its own copy, the wl_region isn't needed anymore
+		if let Some(obj) = obj {
+			obj.borrow().destroy(god);
+		}
+		self.opaque_region = region;
+	}
+
+	// None means the whole surface takes input
+	pub(crate) fn set_input_region(
+		&mut self,
+		god: &mut God,
+		compositor: &Rl<Compositor>,
+		region: Option<Region>,
+	) {
+		if self.input_region == region {
+			return;
+		}
+		let obj = region.as_ref().map(|r| compositor.borrow().create_region(god, r));
+		let id = obj.as_ref().map(|o| o.borrow().id).unwrap_or_default();
+		god.wlmm.queue_request(self.wl_set_input_region(id));
+		if let Some(obj) = obj {
+			obj.borrow().destroy(god);
+		}
+		self.input_region = region;
+	}
+
+	// size in physical pixels, before the transform
+	pub(crate) fn physical_size(&self) -> (u32, u32) {
+		match self.fractional_scale {
+			Some(s) => ((self.w * s).div_ceil(120), (self.h * s).div_ceil(120)),
+			None => (self.w * self.scale, self.h * self.scale),
+		}
+	}
+
+	pub(crate) fn buffer_size(&self) -> (u32, u32) {
+		let (w, h) = self.physical_size();
+		if self.transform.swaps_axes() {
+			(h, w)
 		} else {
-			Err(WaytinierError::ExpectedSomeValue("no buffer attached to surface"))
+			(w, h)
+		}
+	}
+
+	pub(crate) fn scale_factor(&self) -> f64 {
+		match self.fractional_scale {
+			Some(s) => s as f64 / 120.0,
+			None => self.scale as f64,
+		}
+	}
+
+	// with fractional scaling the buffer has to be mapped back onto the logical size
+	pub(crate) fn update_viewport(&self, god: &mut God) {
+		if let Some(viewport) = &self.viewport
+			&& self.fractional_scale.is_some()
+		{
+			viewport.borrow_mut().set_destination(god, self.w, self.h);
 		}
 	}
 }
@@ -139,11 +339,46 @@ impl Surface {
 impl WaylandObject for Surface {
 	fn handle(
 		&mut self,
-		_payload: &[u8],
-		_opcode: super::OpCode,
+		payload: &[u8],
+		opcode: OpCode,
 		_fds: &[OwnedFd],
 	) -> Result<Vec<Action>, WaytinierError> {
-		todo!()
+		let mut pending = vec![];
+		match opcode.raw() {
+			// enter
+			0 => {
+				let output = Id(u32::from_wire(payload)?);
+				if !self.outputs.contains(&output) {
+					self.outputs.push(output);
+				}
+				handle_log!(pending, self, DebugLevel::Trivial, format!("entered output {output}"));
+			}
+			// leave
+			1 => {
+				let output = Id(u32::from_wire(payload)?);
+				self.outputs.retain(|o| *o != output);
+				handle_log!(pending, self, DebugLevel::Trivial, format!("left output {output}"));
+			}
+			// preferred_buffer_scale
+			2 => {
+				let scale = i32::from_wire(payload)?;
+				self.preferred_scale = Some(scale.max(1) as u32);
+				handle_log!(pending, self, DebugLevel::Trivial, format!("preferred scale {scale}"));
+			}
+			// preferred_buffer_transform
+			3 => {
+				let transform = Transform::from_u32(u32::from_wire(payload)?)?;
+				self.preferred_transform = Some(transform);
+				handle_log!(
+					pending,
+					self,
+					DebugLevel::Trivial,
+					format!("preferred transform {transform:?}")
+				);
+			}
+			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
+		}
+		Ok(pending)
 	}
 
 	fn kind(&self) -> WaylandObjectKind {
@@ -0,0 +1,147 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	Rl, rl,
+	wayland::{
+		God, Id, OpCode, WaylandObject, WaylandObjectKind, WaytinierError,
+		registry::Registry,
+		surface::Surface,
+		wire::{Action, WireArgument, WireRequest},
+	},
+};
+
+pub(crate) struct Viewporter {
+	pub(crate) id: Id,
+}
+
+impl Viewporter {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let viewporter = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(viewporter.clone());
+		viewporter.borrow_mut().id = id;
+		viewporter
+	}
+
+	pub(crate) fn new_registered_bound(
+		god: &mut God,
+		registry: &Rl<Registry>,
+	) -> Result<Rl<Self>, WaytinierError> {
+		let viewporter = Self::new_registered(god);
+		registry.borrow_mut().bind(god, viewporter.borrow().id, viewporter.borrow().kind(), 1)?;
+		Ok(viewporter)
+	}
+
+	fn wl_get_viewport(&self, id: Id, surface_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "get_viewport",
+			args: vec![WireArgument::NewId(id), WireArgument::Obj(surface_id)],
+		}
+	}
+
+	pub(crate) fn get_viewport(&self, god: &mut God, id: Id, surface_id: Id) {
+		god.wlmm.queue_request(self.wl_get_viewport(id, surface_id));
+	}
+}
+
+impl WaylandObject for Viewporter {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::Viewporter
+	}
+}
+
+pub(crate) struct Viewport {
+	pub(crate) id: Id,
+	pub(crate) destination: Option<(u32, u32)>,
+}
+
+impl Viewport {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+			destination: None,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let viewport = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(viewport.clone());
+		viewport.borrow_mut().id = id;
+		viewport
+	}
+
+	pub(crate) fn new_registered_gotten(
+		god: &mut God,
+		viewporter: &Rl<Viewporter>,
+		surface: &Rl<Surface>,
+	) -> Rl<Self> {
+		let viewport = Self::new_registered(god);
+		viewporter.borrow().get_viewport(god, viewport.borrow().id, surface.borrow().id);
+		viewport
+	}
+
+	fn wl_set_destination(&self, w: i32, h: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(2),
+			opname: "set_destination",
+			args: vec![WireArgument::Int(w), WireArgument::Int(h)],
+		}
+	}
+
+	// logical size the buffer gets stretched to, only sent when it changes
+	pub(crate) fn set_destination(&mut self, god: &mut God, w: u32, h: u32) {
+		if self.destination == Some((w, h)) {
+			return;
+		}
+		self.destination = Some((w, h));
+		god.wlmm.queue_request(self.wl_set_destination(w as i32, h as i32));
+	}
+
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn destroy(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_destroy());
+	}
+}
+
+impl WaylandObject for Viewport {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::Viewport
+	}
+}
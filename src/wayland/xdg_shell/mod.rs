@@ -1,3 +1,5 @@
+pub(crate) mod popup;
+pub mod positioner;
 pub(crate) mod surface;
 pub(crate) mod toplevel;
 pub(crate) mod wm_base;
@@ -0,0 +1,146 @@
+use std::os::fd::OwnedFd;
+
+use crate::{
+	DebugLevel, Rl, handle_log, rl,
+	wayland::{
+		God, Id, OpCode, Raw, WaylandObject, WaylandObjectKind, WaytinierError,
+		wire::{Action, FromWirePayload, WireArgument, WireRequest},
+		xdg_shell::{positioner::XdgPositioner, surface::XdgSurface},
+	},
+};
+
+pub(crate) struct XdgPopup {
+	pub(crate) id: Id,
+	pub(crate) parent: Rl<XdgSurface>,
+	// position relative to the parent and size, as last configured
+	pub(crate) geometry: (i32, i32, i32, i32),
+	pub(crate) done: bool,
+	pub(crate) last_token: u32,
+	pub(crate) repositioned_token: Option<u32>,
+}
+
+impl XdgPopup {
+	pub(crate) fn new(id: Id, parent: Rl<XdgSurface>) -> Rl<Self> {
+		rl!(Self {
+			id,
+			parent,
+			geometry: (0, 0, 0, 0),
+			done: false,
+			last_token: 0,
+			repositioned_token: None,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God, xdg_surface: &Rl<XdgSurface>) -> Rl<Self> {
+		let popup = Self::new(Id(0), xdg_surface.clone());
+		let id = god.wlim.new_id_registered(popup.clone());
+		popup.borrow_mut().id = id;
+		popup
+	}
+
+	pub(crate) fn new_registered_gotten(
+		god: &mut God,
+		xdg_surface: &Rl<XdgSurface>,
+		parent: Option<&Rl<XdgSurface>>,
+		positioner: &Rl<XdgPositioner>,
+	) -> Rl<Self> {
+		let popup = Self::new_registered(god, xdg_surface);
+		let parent_id = parent.map(|p| p.borrow().id).unwrap_or_default();
+		xdg_surface.borrow().get_popup(god, popup.borrow().id, parent_id, positioner.borrow().id);
+		popup
+	}
+
+	fn wl_grab(&self, seat_id: Id, serial: u32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "grab",
+			args: vec![WireArgument::Obj(seat_id), WireArgument::UnInt(serial)],
+		}
+	}
+
+	// has to happen before the first commit, serial is from the input event that opened the popup
+	pub(crate) fn grab(&self, god: &mut God, seat_id: Id, serial: u32) {
+		god.wlmm.queue_request(self.wl_grab(seat_id, serial));
+	}
+
+	fn wl_reposition(&self, positioner_id: Id, token: u32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(2),
+			opname: "reposition",
+			args: vec![WireArgument::Obj(positioner_id), WireArgument::UnInt(token)],
+		}
+	}
+
+	// needs xdg_wm_base v3, returns the token the repositioned event will carry
+	pub(crate) fn reposition(&mut self, god: &mut God, positioner: &Rl<XdgPositioner>) -> u32 {
+		self.last_token = self.last_token.wrapping_add(1);
+		god.wlmm.queue_request(self.wl_reposition(positioner.borrow().id, self.last_token));
+		self.last_token
+	}
+
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn destroy(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_destroy());
+	}
+}
+
+impl WaylandObject for XdgPopup {
+	fn handle(
+		&mut self,
+		payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		let mut pending = vec![];
+		match opcode.raw() {
+			// configure
+			0 => {
+				let x = i32::from_wire(payload)?;
+				let y = i32::from_wire(&payload[4..])?;
+				let w = i32::from_wire(&payload[8..])?;
+				let h = i32::from_wire(&payload[12..])?;
+				self.geometry = (x, y, w, h);
+				handle_log!(
+					pending,
+					self,
+					DebugLevel::Important,
+					format!("configure // x: {x}, y: {y}, w: {w}, h: {h}")
+				);
+				if w > 0 && h > 0 {
+					let surface = self.parent.borrow().parent.clone();
+					pending.push(Action::Resize(w as u32, h as u32, surface));
+				}
+			}
+			// popup_done
+			1 => {
+				self.done = true;
+				handle_log!(pending, self, DebugLevel::Important, String::from("popup done"));
+			}
+			// repositioned
+			2 => {
+				let token = u32::from_wire(payload)?;
+				self.repositioned_token = Some(token);
+				handle_log!(pending, self, DebugLevel::Trivial, format!("repositioned {token}"));
+			}
+			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
+		}
+		Ok(pending)
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::XdgPopup
+	}
+}
@@ -0,0 +1,252 @@
+use std::{ops::BitOr, os::fd::OwnedFd};
+
+use crate::{
+	Rl, rl,
+	wayland::{
+		God, Id, OpCode, WaylandObject, WaylandObjectKind, WaytinierError,
+		wire::{Action, WireArgument, WireRequest},
+		xdg_shell::wm_base::XdgWmBase,
+	},
+};
+
+// used for both the anchor and the gravity, the values are the same
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+	#[default]
+	None = 0,
+	Top,
+	Bottom,
+	Left,
+	Right,
+	TopLeft,
+	BottomLeft,
+	TopRight,
+	BottomRight,
+}
+
+pub type Gravity = Anchor;
+
+// what the compositor may do with the popup if it would end up off screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConstraintAdjustment(pub u32);
+
+impl ConstraintAdjustment {
+	pub const NONE: Self = Self(0);
+	pub const SLIDE_X: Self = Self(1);
+	pub const SLIDE_Y: Self = Self(2);
+	pub const FLIP_X: Self = Self(4);
+	pub const FLIP_Y: Self = Self(8);
+	pub const RESIZE_X: Self = Self(16);
+	pub const RESIZE_Y: Self = Self(32);
+}
+
+impl BitOr for ConstraintAdjustment {
+	type Output = Self;
+
+	fn bitor(self, rhs: Self) -> Self {
+		Self(self.0 | rhs.0)
+	}
+}
+
+// describes where a popup goes relative to its parent, all in the parent's
+// logical surface coordinates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Positioner {
+	pub(crate) size: (i32, i32),
+	pub(crate) anchor_rect: (i32, i32, i32, i32),
+	pub(crate) anchor: Anchor,
+	pub(crate) gravity: Gravity,
+	pub(crate) constraint_adjustment: ConstraintAdjustment,
+	pub(crate) offset: (i32, i32),
+	pub(crate) reactive: bool,
+}
+
+impl Positioner {
+	// the size of the popup and the rectangle on the parent it's anchored to
+	pub fn new(w: i32, h: i32, anchor_rect: (i32, i32, i32, i32)) -> Self {
+		Self {
+			size: (w, h),
+			anchor_rect,
+			anchor: Anchor::None,
+			gravity: Gravity::None,
+			constraint_adjustment: ConstraintAdjustment::NONE,
+			offset: (0, 0),
+			reactive: false,
+		}
+	}
+
+	pub fn with_anchor(mut self, anchor: Anchor) -> Self {
+		self.anchor = anchor;
+		self
+	}
+
+	pub fn with_gravity(mut self, gravity: Gravity) -> Self {
+		self.gravity = gravity;
+		self
+	}
+
+	pub fn with_constraint_adjustment(mut self, adjustment: ConstraintAdjustment) -> Self {
+		self.constraint_adjustment = adjustment;
+		self
+	}
+
+	pub fn with_offset(mut self, x: i32, y: i32) -> Self {
+		self.offset = (x, y);
+		self
+	}
+
+	// the popup gets reconstrained when the parent moves or resizes, needs xdg_wm_base v3
+	pub fn reactive(mut self) -> Self {
+		self.reactive = true;
+		self
+	}
+}
+
+// the xdg_positioner a Positioner gets turned into, destroyed right after use
+pub(crate) struct XdgPositioner {
+	pub(crate) id: Id,
+}
+
+impl XdgPositioner {
+	pub(crate) fn new(id: Id) -> Rl<Self> {
+		rl!(Self {
+			id,
+		})
+	}
+
+	pub(crate) fn new_registered(god: &mut God) -> Rl<Self> {
+		let positioner = Self::new(Id(0));
+		let id = god.wlim.new_id_registered(positioner.clone());
+		positioner.borrow_mut().id = id;
+		positioner
+	}
+
+	pub(crate) fn new_registered_created(
+		god: &mut God,
+		wm_base: &Rl<XdgWmBase>,
+		desc: &Positioner,
+	) -> Rl<Self> {
+		let positioner = Self::new_registered(god);
+		let wm_base = wm_base.borrow();
+		wm_base.create_positioner(god, positioner.borrow().id);
+		positioner.borrow().apply(god, desc, wm_base.version);
+		positioner
+	}
+
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn destroy(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_destroy());
+	}
+
+	fn wl_set_size(&self, w: i32, h: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "set_size",
+			args: vec![WireArgument::Int(w), WireArgument::Int(h)],
+		}
+	}
+
+	fn wl_set_anchor_rect(&self, (x, y, w, h): (i32, i32, i32, i32)) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(2),
+			opname: "set_anchor_rect",
+			args: vec![
+				WireArgument::Int(x),
+				WireArgument::Int(y),
+				WireArgument::Int(w),
+				WireArgument::Int(h),
+			],
+		}
+	}
+
+	fn wl_set_anchor(&self, anchor: Anchor) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(3),
+			opname: "set_anchor",
+			args: vec![WireArgument::UnInt(anchor as u32)],
+		}
+	}
+
+	fn wl_set_gravity(&self, gravity: Gravity) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(4),
+			opname: "set_gravity",
+			args: vec![WireArgument::UnInt(gravity as u32)],
+		}
+	}
+
+	fn wl_set_constraint_adjustment(&self, adjustment: ConstraintAdjustment) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(5),
+			opname: "set_constraint_adjustment",
+			args: vec![WireArgument::UnInt(adjustment.0)],
+		}
+	}
+
+	fn wl_set_offset(&self, x: i32, y: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(6),
+			opname: "set_offset",
+			args: vec![WireArgument::Int(x), WireArgument::Int(y)],
+		}
+	}
+
+	fn wl_set_reactive(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(7),
+			opname: "set_reactive",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn apply(&self, god: &mut God, desc: &Positioner, version: u32) {
+		god.wlmm.queue_request(self.wl_set_size(desc.size.0, desc.size.1));
+		god.wlmm.queue_request(self.wl_set_anchor_rect(desc.anchor_rect));
+		god.wlmm.queue_request(self.wl_set_anchor(desc.anchor));
+		god.wlmm.queue_request(self.wl_set_gravity(desc.gravity));
+		god.wlmm.queue_request(self.wl_set_constraint_adjustment(desc.constraint_adjustment));
+		god.wlmm.queue_request(self.wl_set_offset(desc.offset.0, desc.offset.1));
+		if desc.reactive && version >= 3 {
+			god.wlmm.queue_request(self.wl_set_reactive());
+		}
+	}
+}
+
+impl WaylandObject for XdgPositioner {
+	fn handle(
+		&mut self,
+		_payload: &[u8],
+		opcode: OpCode,
+		_fds: &[OwnedFd],
+	) -> Result<Vec<Action>, WaytinierError> {
+		Err(WaytinierError::InvalidOpCode(opcode, self.kind()))
+	}
+
+	fn kind(&self) -> WaylandObjectKind {
+		WaylandObjectKind::XdgPositioner
+	}
+}
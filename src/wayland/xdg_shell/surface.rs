@@ -10,7 +10,7 @@ use crate::{
 	},
 };
 
-pub(crate) struct XdgSurface {
+pub struct XdgSurface {
 	pub(crate) id: Id,
 	pub(crate) is_configured: bool,
 	pub(crate) parent: Rl<Surface>,
@@ -51,6 +51,46 @@ impl XdgSurface {
 		god.wlmm.queue_request(self.wl_get_toplevel(id));
 	}
 
+	fn wl_get_popup(&self, xdg_popup_id: Id, parent_id: Id, positioner_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(2),
+			opname: "get_popup",
+			args: vec![
+				WireArgument::NewId(xdg_popup_id),
+				WireArgument::Obj(parent_id),
+				WireArgument::Obj(positioner_id),
+			],
+		}
+	}
+
+	// a null parent is allowed, the parent then has to be set through another protocol
+	pub(crate) fn get_popup(&self, god: &mut God, id: Id, parent_id: Id, positioner_id: Id) {
+		god.wlmm.queue_request(self.wl_get_popup(id, parent_id, positioner_id));
+	}
+
+	fn wl_set_window_geometry(&self, x: i32, y: i32, w: i32, h: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(3),
+			opname: "set_window_geometry",
+			args: vec![
+				WireArgument::Int(x),
+				WireArgument::Int(y),
+				WireArgument::Int(w),
+				WireArgument::Int(h),
+			],
+		}
+	}
+
+	// the part of the surface tree that is the actual window, without shadows and
+	// resize borders. relative to the surface
+	pub(crate) fn set_window_geometry(&self, god: &mut God, x: i32, y: i32, w: i32, h: i32) {
+		god.wlmm.queue_request(self.wl_set_window_geometry(x, y, w, h));
+	}
+
 	fn wl_ack_configure(&self, serial: u32) -> WireRequest {
 		WireRequest {
 			sender_id: self.id,
@@ -61,15 +101,19 @@ impl XdgSurface {
 		}
 	}
 
-	// fn wl_destroy(&self) -> WireRequest {
-	// 	WireRequest {
-	// 		sender_id: self.id,
-	// 		kind: self.kind(),
-	// 		opcode: OpCode(0),
-	// 		opname: "destroy",
-	// 		args: vec![],
-	// 	}
-	// }
+	fn wl_destroy(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(0),
+			opname: "destroy",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn destroy(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_destroy());
+	}
 }
 
 impl WaylandObject for XdgSurface {
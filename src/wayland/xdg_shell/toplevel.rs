@@ -13,6 +13,9 @@ pub struct XdgTopLevel {
 	pub(crate) id: Id,
 	pub(crate) close_requested: bool,
 	pub(crate) parent: Rl<XdgSurface>,
+	pub(crate) state: WindowState,
+	// configures are for the whole window, this much of it is taken by our own titlebar
+	pub(crate) titlebar_height: u32,
 }
 
 impl XdgTopLevel {
@@ -43,6 +46,8 @@ impl XdgTopLevel {
 			id,
 			close_requested: false,
 			parent,
+			state: WindowState::default(),
+			titlebar_height: 0,
 		})
 	}
 
@@ -89,6 +94,173 @@ impl XdgTopLevel {
 		god.wlmm.queue_request(self.wl_set_title(id))
 	}
 
+	fn wl_set_parent(&self, parent_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "set_parent",
+			args: vec![WireArgument::Obj(parent_id)],
+		}
+	}
+
+	// a null id makes the window a top level one again
+	pub(crate) fn set_parent(&self, god: &mut God, parent_id: Id) {
+		god.wlmm.queue_request(self.wl_set_parent(parent_id));
+	}
+
+	fn wl_show_window_menu(&self, seat_id: Id, serial: u32, x: i32, y: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(4),
+			opname: "show_window_menu",
+			args: vec![
+				WireArgument::Obj(seat_id),
+				WireArgument::UnInt(serial),
+				WireArgument::Int(x),
+				WireArgument::Int(y),
+			],
+		}
+	}
+
+	pub(crate) fn show_window_menu(&self, god: &mut God, seat_id: Id, serial: u32, x: i32, y: i32) {
+		god.wlmm.queue_request(self.wl_show_window_menu(seat_id, serial, x, y));
+	}
+
+	fn wl_move(&self, seat_id: Id, serial: u32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(5),
+			opname: "move",
+			args: vec![WireArgument::Obj(seat_id), WireArgument::UnInt(serial)],
+		}
+	}
+
+	pub(crate) fn start_move(&self, god: &mut God, seat_id: Id, serial: u32) {
+		god.wlmm.queue_request(self.wl_move(seat_id, serial));
+	}
+
+	fn wl_resize(&self, seat_id: Id, serial: u32, edges: ResizeEdge) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(6),
+			opname: "resize",
+			args: vec![
+				WireArgument::Obj(seat_id),
+				WireArgument::UnInt(serial),
+				WireArgument::UnInt(edges as u32),
+			],
+		}
+	}
+
+	pub(crate) fn start_resize(&self, god: &mut God, seat_id: Id, serial: u32, edges: ResizeEdge) {
+		god.wlmm.queue_request(self.wl_resize(seat_id, serial, edges));
+	}
+
+	fn wl_set_max_size(&self, w: i32, h: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(7),
+			opname: "set_max_size",
+			args: vec![WireArgument::Int(w), WireArgument::Int(h)],
+		}
+	}
+
+	// 0 means no limit in that direction
+	pub(crate) fn set_max_size(&self, god: &mut God, w: i32, h: i32) {
+		god.wlmm.queue_request(self.wl_set_max_size(w, h));
+	}
+
+	fn wl_set_min_size(&self, w: i32, h: i32) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(8),
+			opname: "set_min_size",
+			args: vec![WireArgument::Int(w), WireArgument::Int(h)],
+		}
+	}
+
+	pub(crate) fn set_min_size(&self, god: &mut God, w: i32, h: i32) {
+		god.wlmm.queue_request(self.wl_set_min_size(w, h));
+	}
+
+	fn wl_set_maximized(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(9),
+			opname: "set_maximized",
+			args: vec![],
+		}
+	}
+
+	fn wl_unset_maximized(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(10),
+			opname: "unset_maximized",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn set_maximized(&self, god: &mut God, maximized: bool) {
+		if maximized {
+			god.wlmm.queue_request(self.wl_set_maximized());
+		} else {
+			god.wlmm.queue_request(self.wl_unset_maximized());
+		}
+	}
+
+	fn wl_set_fullscreen(&self, output_id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(11),
+			opname: "set_fullscreen",
+			args: vec![WireArgument::Obj(output_id)],
+		}
+	}
+
+	// a null output lets the compositor pick one
+	pub(crate) fn set_fullscreen(&self, god: &mut God, output_id: Id) {
+		god.wlmm.queue_request(self.wl_set_fullscreen(output_id));
+	}
+
+	fn wl_unset_fullscreen(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(12),
+			opname: "unset_fullscreen",
+			args: vec![],
+		}
+	}
+
+	pub(crate) fn unset_fullscreen(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_unset_fullscreen());
+	}
+
+	fn wl_set_minimized(&self) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(13),
+			opname: "set_minimized",
+			args: vec![],
+		}
+	}
+
+	// there's no way to unminimize or even tell if it worked, that's up to the user
+	pub(crate) fn set_minimized(&self, god: &mut God) {
+		god.wlmm.queue_request(self.wl_set_minimized());
+	}
+
 	// fn wl_destroy(&self) -> WireRequest {
 	// 	WireRequest {
 	// 		sender_id: self.id,
@@ -100,23 +272,98 @@ impl XdgTopLevel {
 	// }
 }
 
-#[allow(dead_code)]
+// which edge or corner of the window is being dragged
 #[repr(u32)]
-#[derive(Debug)]
-enum XdgTopLevelStates {
-	Maximized = 1,
-	Fullscreen,
-	Resizing,
-	Activated,
-	TiledLeft,
-	TiledRight,
-	TiledTop,
-	TiledBottom,
-	Suspended,
-	ConstrainedLeft,
-	ConstrainedRight,
-	ConstrainedTop,
-	ConstrainedBottom,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizeEdge {
+	#[default]
+	None = 0,
+	Top = 1,
+	Bottom = 2,
+	Left = 4,
+	TopLeft = 5,
+	BottomLeft = 6,
+	Right = 8,
+	TopRight = 9,
+	BottomRight = 10,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TiledEdges {
+	pub left: bool,
+	pub right: bool,
+	pub top: bool,
+	pub bottom: bool,
+}
+
+// what the compositor lets the window do, everything is assumed supported until
+// wm_capabilities says otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WmCapabilities {
+	pub window_menu: bool,
+	pub maximize: bool,
+	pub fullscreen: bool,
+	pub minimize: bool,
+}
+
+impl Default for WmCapabilities {
+	fn default() -> Self {
+		Self {
+			window_menu: true,
+			maximize: true,
+			fullscreen: true,
+			minimize: true,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WindowState {
+	pub maximized: bool,
+	pub fullscreen: bool,
+	pub resizing: bool,
+	// has keyboard focus or is otherwise the one the user is working with
+	pub activated: bool,
+	// tiled against these edges, so no shadows or rounded corners there
+	pub tiled: TiledEdges,
+	// not visible at all, e.g. on another workspace. no point in drawing
+	pub suspended: bool,
+	// can't be resized from these edges
+	pub constrained: TiledEdges,
+	// logical size the window should stay within, e.g. the output minus panels
+	pub bounds: Option<(u32, u32)>,
+	pub capabilities: WmCapabilities,
+}
+
+impl WindowState {
+	// states from a configure, unknown values are from newer protocol versions and get ignored
+	fn apply_states(&mut self, states: &[u32]) {
+		let bounds = self.bounds;
+		let capabilities = self.capabilities;
+		*self = Self {
+			bounds,
+			capabilities,
+			..Default::default()
+		};
+		for state in states {
+			match state {
+				1 => self.maximized = true,
+				2 => self.fullscreen = true,
+				3 => self.resizing = true,
+				4 => self.activated = true,
+				5 => self.tiled.left = true,
+				6 => self.tiled.right = true,
+				7 => self.tiled.top = true,
+				8 => self.tiled.bottom = true,
+				9 => self.suspended = true,
+				10 => self.constrained.left = true,
+				11 => self.constrained.right = true,
+				12 => self.constrained.top = true,
+				13 => self.constrained.bottom = true,
+				_ => {}
+			}
+		}
+	}
 }
 
 impl WaylandObject for XdgTopLevel {
@@ -132,24 +379,16 @@ impl WaylandObject for XdgTopLevel {
 			0 => {
 				let w = i32::from_wire(payload)? as u32;
 				let h = i32::from_wire(&payload[4..])? as u32;
-				let states: Vec<XdgTopLevelStates> = Vec::from_wire(&payload[8..])?
-					.iter()
-					.map(|en| {
-						if (*en as usize) < std::mem::variant_count::<XdgTopLevelStates>() {
-							Ok(unsafe { std::mem::transmute::<u32, XdgTopLevelStates>(*en) })
-						} else {
-							// maybe try formatting this automatically
-							Err(WaytinierError::InvalidEnumVariant("XdgTopLevelStates"))
-						}
-					})
-					.collect::<Result<Vec<_>, _>>()?;
+				let states: Vec<u32> = Vec::from_wire(&payload[8..])?;
+				self.state.apply_states(&states);
 				handle_log!(
 					pending,
 					self,
 					DebugLevel::Important,
-					format!("configure // w: {w}, h: {h}, states: {states:?}")
+					format!("configure // w: {w}, h: {h}, state: {:?}", self.state)
 				);
 				if w != 0 && h != 0 {
+					let h = h.saturating_sub(self.titlebar_height).max(1);
 					pending.push(Action::Resize(w, h, self.parent.borrow().parent.clone()));
 				}
 			}
@@ -160,11 +399,36 @@ impl WaylandObject for XdgTopLevel {
 			}
 			// configure_bounds
 			2 => {
-				todo!()
+				let w = i32::from_wire(payload)? as u32;
+				let h = i32::from_wire(&payload[4..])? as u32;
+				// 0x0 means the bounds are unknown
+				self.state.bounds = if w != 0 && h != 0 {
+					Some((w, h))
+				} else {
+					None
+				};
+				handle_log!(
+					pending,
+					self,
+					DebugLevel::Trivial,
+					format!("bounds // w: {w}, h: {h}")
+				);
 			}
 			// wm_capabilities
 			3 => {
-				todo!()
+				let caps: Vec<u32> = Vec::from_wire(payload)?;
+				self.state.capabilities = WmCapabilities {
+					window_menu: caps.contains(&1),
+					maximize: caps.contains(&2),
+					fullscreen: caps.contains(&3),
+					minimize: caps.contains(&4),
+				};
+				handle_log!(
+					pending,
+					self,
+					DebugLevel::Trivial,
+					format!("capabilities // {:?}", self.state.capabilities)
+				);
 			}
 			_ => return Err(WaytinierError::InvalidOpCode(opcode, self.kind())),
 		}
@@ -11,6 +11,7 @@ use crate::{
 
 pub struct XdgWmBase {
 	pub(crate) id: Id,
+	pub(crate) version: u32,
 }
 
 impl XdgWmBase {
@@ -19,12 +20,15 @@ impl XdgWmBase {
 		god: &mut God,
 	) -> Result<Rl<Self>, WaytinierError> {
 		let mut reg = registry.borrow_mut();
+		// v3 for popup repositioning, the rest adds toplevel events and states
+		let version = reg.version_capped(WaylandObjectKind::XdgWmBase, 7)?;
 		let obj = rl!(Self {
 			id: Id(0),
+			version,
 		});
 		let id = god.wlim.new_id_registered(obj.clone());
 		obj.borrow_mut().id = id;
-		reg.bind(god, id, WaylandObjectKind::XdgWmBase, 1)?;
+		reg.bind(god, id, WaylandObjectKind::XdgWmBase, version)?;
 		Ok(obj)
 	}
 
@@ -52,6 +56,20 @@ impl XdgWmBase {
 		}
 	}
 
+	fn wl_create_positioner(&self, id: Id) -> WireRequest {
+		WireRequest {
+			sender_id: self.id,
+			kind: self.kind(),
+			opcode: OpCode(1),
+			opname: "create_positioner",
+			args: vec![WireArgument::NewId(id)],
+		}
+	}
+
+	pub(crate) fn create_positioner(&self, god: &mut God, id: Id) {
+		god.wlmm.queue_request(self.wl_create_positioner(id));
+	}
+
 	fn wl_get_xdg_surface(&self, wl_surface_id: Id, xdg_surface_id: Id) -> WireRequest {
 		WireRequest {
 			sender_id: self.id,